@@ -1,20 +1,35 @@
-use day16_19::inst::{self, Executor, Error, RegisterGroup};
+use day16_19::{opt, inst::{self, ExecutionOutcome, Executor, RegisterGroup, StdoutTrace}};
 
+// Usage: day19 [--part2] [--opt] [--steps {n}] [--trace]
 fn main() {
     let input_path = "day19_input.txt";
-    let program = inst::load_program(input_path).expect(&format!("Failed to load program from input file({})", input_path));
-    // let mut executor = Executor::new(); // Part1
-    let mut executor = Executor::with_regs(&RegisterGroup::from_arr(&[1])); // Part 2
+    let mut program = inst::load_program(input_path)
+        .unwrap_or_else(|e| panic!("Failed to load program from input file({}), get error({})", input_path, e));
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.iter().any(|a| a == "--opt") {
+        let rewrites = opt::optimize(&mut program).unwrap_or_else(|e| panic!("Failed to optimize program, get error({})", e));
+        for rewrite in rewrites {
+            println!("Rewrite {}", rewrite);
+        }
+    }
+    let mut executor = if args.iter().any(|a| a == "--part2") {
+        Executor::with_regs(&RegisterGroup::from_arr(&[1]))
+    } else {
+        Executor::new()
+    };
+    if let Some(pos) = args.iter().position(|a| a == "--steps") {
+        let limit = args.get(pos + 1).and_then(|s| s.parse::<usize>().ok()).expect("Step limit should be a number");
+        executor.set_step_limit(Some(limit));
+    }
+    if args.iter().any(|a| a == "--trace") {
+        executor.set_trace(StdoutTrace);
+    }
     match executor.execute(&program) {
-        Err(e) => {
-            match e {
-                Error::InvalidInstructionPointer(ip) => {
-                    println!("Program halt at instruction#{}", ip);
-                    println!("Final state is {}", executor.regs());
-                },
-                other => println!("Failed to execute program, get error({})", other),
-            }
+        Ok(outcome @ ExecutionOutcome::Halted { .. }) => {
+            println!("Program {}", outcome);
+            println!("Final state is {}", executor.regs());
         },
-        _ => {},
+        Ok(outcome) => println!("Program stops, {}, current state is {}", outcome, executor.regs()),
+        Err(e) => println!("Failed to execute program, get error({})", e),
     }
-}
\ No newline at end of file
+}
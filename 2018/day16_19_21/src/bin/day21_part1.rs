@@ -1,24 +1,21 @@
-use day16_19::inst::{self, Executor, Error, RegisterGroup};
+use day16_19::inst::{self, ExecutionOutcome, Executor, RegisterGroup};
 
 fn main() {
     let input_path = "day21_input.txt";
-    let program = inst::load_program(input_path).expect(&format!("Failed to load program from input file({})", input_path));
+    let program = inst::load_program(input_path)
+        .unwrap_or_else(|e| panic!("Failed to load program from input file({}), get error({})", input_path, e));
     let mut executor = Executor::with_regs(&RegisterGroup::from_arr(&[0]));
     executor.set_break_at(28);
     match executor.execute(&program) {
-        Err(e) => {
-            match e {
-                Error::InvalidInstructionPointer(ip) => {
-                    println!("Program halt at instruction#{}", ip);
-                    println!("Final state is {}", executor.regs());
-                },
-                Error::Break(ip) => {
-                    println!("Program halt at instruction#{}", ip);
-                    println!("Current state is {}, if set register 0 to register 1 can halt program and cost least steps", executor.regs());
-                }
-                other => println!("Failed to execute program, get error({})", other),
-            }
+        Ok(ExecutionOutcome::Halted { ip, ticks }) => {
+            println!("Program halt at instruction#{} after {} ticks", ip, ticks);
+            println!("Final state is {}", executor.regs());
         },
-        _ => {},
+        Ok(ExecutionOutcome::Break { ip, ticks }) => {
+            println!("Program break at instruction#{} after {} ticks", ip, ticks);
+            println!("Current state is {}, if set register 0 to register 1 can halt program and cost least steps", executor.regs());
+        },
+        Ok(outcome) => println!("Program stops unexpectedly, {}", outcome),
+        Err(e) => println!("Failed to execute program, get error({})", e),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,31 @@
+use day16_19::{inst::{self, ExecutionOutcome, Executor, Observer, RegisterGroup}, opt};
+
+// Observe the register compared with register 0, its first value halts program soonest, and the last one before
+// values repeat halts program latest.
+fn main() {
+    let input_path = "day21_input.txt";
+    let mut program = inst::load_program(input_path)
+        .unwrap_or_else(|e| panic!("Failed to load program from input file({}), get error({})", input_path, e));
+    let (cmp_ip, reg_ind) = program.insts().iter().enumerate()
+        .find_map(|(ip, inst)| match (inst::op_name(inst.op_code()), inst.oprands()) {
+            (Some("eqrr"), [0, r, _]) | (Some("eqrr"), [r, 0, _]) => Some((ip, *r)),
+            _ => None,
+        })
+        .expect("Failed to find instruction comparing with register 0");
+    opt::optimize(&mut program).unwrap_or_else(|e| panic!("Failed to optimize program, get error({})", e));
+
+    let mut executor = Executor::with_regs(&RegisterGroup::from_arr(&[0]));
+    let observer_ind = executor.add_observer(Observer::new(cmp_ip, reg_ind).stop_on_repeat());
+    match executor.execute(&program) {
+        Ok(outcome @ ExecutionOutcome::Repeated { .. }) => {
+            let observer = executor.observer(observer_ind).unwrap();
+            let (start, len) = observer.cycle().unwrap();
+            println!("Program {}, register {} at instruction#{} repeats from value#{} every {} values",
+                outcome, reg_ind, cmp_ip, start, len);
+            println!("Set register 0 to {}, can halt program and cost the least steps", observer.first_unique().unwrap());
+            println!("Set register 0 to {}, can halt program and cost the most steps", observer.last_unique().unwrap());
+        },
+        Ok(outcome) => println!("Program stops before observed values repeat, {}", outcome),
+        Err(e) => println!("Failed to execute program, get error({})", e),
+    }
+}
@@ -0,0 +1,18 @@
+use day16_19::{decomp::{self, Cfg}, inst};
+
+// Usage: decompile [input path] [--cfg]
+fn main() {
+    let input_path = std::env::args().skip(1).find(|a| !a.starts_with("--")).unwrap_or("day19_input.txt".to_string());
+    let program = inst::load_program(&input_path)
+        .unwrap_or_else(|e| panic!("Failed to load program from input file({}), get error({})", input_path, e));
+    if std::env::args().any(|a| a == "--cfg") {
+        match Cfg::from_program(&program) {
+            Ok(cfg) => print!("{}", cfg),
+            Err(e) => println!("Failed to build control flow graph, get error({})", e),
+        }
+    }
+    match decomp::decompile(&program) {
+        Ok(decompiled) => print!("{}", decompiled),
+        Err(e) => println!("Failed to decompile program, get error({})", e),
+    }
+}
@@ -0,0 +1,31 @@
+use day16_19::{inst::{self, RegisterGroup}, opt};
+
+// Usage: hot_loops [input path] [--reg0 {n}] [--steps {n}]
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let value_of = |name: &str| args.iter().position(|a| a == name)
+        .map(|pos| args.get(pos + 1).and_then(|s| s.parse::<usize>().ok()).unwrap_or_else(|| panic!("Value of {} should be a number", name)));
+    let input_path = args.iter().skip(1).find(|a| a.ends_with(".txt")).cloned().unwrap_or("day19_input.txt".to_string());
+    let regs = RegisterGroup::from_arr(&[value_of("--reg0").unwrap_or(0)]);
+    let step_limit = value_of("--steps").unwrap_or(1_000_000);
+    let mut program = inst::load_program(&input_path)
+        .unwrap_or_else(|e| panic!("Failed to load program from input file({}), get error({})", input_path, e));
+
+    let (loops, outcome) = opt::hot_loops(&program, &regs, step_limit).unwrap_or_else(|e| panic!("Failed to profile program, get error({})", e));
+    println!("Before optimization, {}:", outcome);
+    for hot_loop in loops {
+        println!("  {}", hot_loop);
+    }
+
+    let rewrites = opt::optimize(&mut program).unwrap_or_else(|e| panic!("Failed to optimize program, get error({})", e));
+    println!("{} rewrite(s):", rewrites.len());
+    for rewrite in rewrites {
+        println!("  {}", rewrite);
+    }
+
+    let (loops, outcome) = opt::hot_loops(&program, &regs, step_limit).unwrap_or_else(|e| panic!("Failed to profile program, get error({})", e));
+    println!("After optimization, {}:", outcome);
+    for hot_loop in loops {
+        println!("  {}", hot_loop);
+    }
+}
@@ -0,0 +1,773 @@
+use std::{collections::{BTreeSet, HashMap, HashSet}, fmt::Display};
+
+use crate::inst::{Error, Instruction, Program, GENERAL_REGISTER_COUNT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Reg(usize),
+    Imm(usize),
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Reg(ind) => write!(f, "r{}", ind),
+            Operand::Imm(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl Operand {
+    fn reg_mask(&self) -> u8 {
+        match self {
+            Operand::Reg(ind) => 1 << ind,
+            Operand::Imm(_) => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Mul,
+    And,
+    Or,
+    Gt,
+    Eq,
+}
+
+impl BinOp {
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Mul => "*",
+            BinOp::And => "&",
+            BinOp::Or => "|",
+            BinOp::Gt => ">",
+            BinOp::Eq => "==",
+        }
+    }
+
+    fn eval(&self, a: usize, b: usize) -> usize {
+        match self {
+            BinOp::Add => a.wrapping_add(b),
+            BinOp::Mul => a.wrapping_mul(b),
+            BinOp::And => a & b,
+            BinOp::Or => a | b,
+            BinOp::Gt => (a > b) as usize,
+            BinOp::Eq => (a == b) as usize,
+        }
+    }
+
+    fn is_cmp(&self) -> bool {
+        matches!(self, BinOp::Gt | BinOp::Eq)
+    }
+}
+
+// Value assigned to register by one instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expr {
+    Value(Operand),
+    Binary(BinOp, Operand, Operand),
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Value(v) => write!(f, "{}", v),
+            Expr::Binary(op, a, b) => write!(f, "{} {} {}", a, op.symbol(), b),
+        }
+    }
+}
+
+impl Expr {
+    fn folded(self) -> Self {
+        match self {
+            Expr::Binary(op, Operand::Imm(a), Operand::Imm(b)) => Expr::Value(Operand::Imm(op.eval(a, b))),
+            other => other,
+        }
+    }
+
+    pub fn constant(&self) -> Option<usize> {
+        match self {
+            Expr::Value(Operand::Imm(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn reg_mask(&self) -> u8 {
+        match self {
+            Expr::Value(v) => v.reg_mask(),
+            Expr::Binary(_, a, b) => a.reg_mask() | b.reg_mask(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Gt,
+    Le,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cond {
+    op: CmpOp,
+    lhs: Operand,
+    rhs: Operand,
+}
+
+impl Display for Cond {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self.op {
+            CmpOp::Gt => ">",
+            CmpOp::Le => "<=",
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+        };
+        write!(f, "{} {} {}", self.lhs, symbol, self.rhs)
+    }
+}
+
+impl Cond {
+    fn non_zero(reg_ind: usize) -> Self {
+        Cond { op: CmpOp::Ne, lhs: Operand::Reg(reg_ind), rhs: Operand::Imm(0) }
+    }
+
+    fn from_cmp(op: BinOp, lhs: Operand, rhs: Operand) -> Option<Self> {
+        match op {
+            BinOp::Gt => Some(Cond { op: CmpOp::Gt, lhs, rhs }),
+            BinOp::Eq => Some(Cond { op: CmpOp::Eq, lhs, rhs }),
+            _ => None,
+        }
+    }
+
+    pub fn negate(&self) -> Self {
+        let op = match self.op {
+            CmpOp::Gt => CmpOp::Le,
+            CmpOp::Le => CmpOp::Gt,
+            CmpOp::Eq => CmpOp::Ne,
+            CmpOp::Ne => CmpOp::Eq,
+        };
+        Cond { op, ..*self }
+    }
+
+    fn reg_mask(&self) -> u8 {
+        self.lhs.reg_mask() | self.rhs.reg_mask()
+    }
+}
+
+// Lift instruction at given address into an assignment, reading ip register gives the address.
+pub fn lift(inst: &Instruction, addr: usize, ip_reg: Option<usize>) -> Result<(usize, Expr), Error> {
+    let [a, b, c] = *inst.oprands();
+    let reg = |ind: usize| if Some(ind) == ip_reg {
+        Ok(Operand::Imm(addr))
+    } else if ind < GENERAL_REGISTER_COUNT {
+        Ok(Operand::Reg(ind))
+    } else {
+        Err(Error::InvalidRegisterIndex(ind))
+    };
+    let imm = Operand::Imm;
+    let expr = match inst.op_code() {
+        0 => Expr::Binary(BinOp::Add, reg(a)?, reg(b)?),
+        1 => Expr::Binary(BinOp::Add, reg(a)?, imm(b)),
+        2 => Expr::Binary(BinOp::Mul, reg(a)?, reg(b)?),
+        3 => Expr::Binary(BinOp::Mul, reg(a)?, imm(b)),
+        4 => Expr::Binary(BinOp::And, reg(a)?, reg(b)?),
+        5 => Expr::Binary(BinOp::And, reg(a)?, imm(b)),
+        6 => Expr::Binary(BinOp::Or, reg(a)?, reg(b)?),
+        7 => Expr::Binary(BinOp::Or, reg(a)?, imm(b)),
+        8 => Expr::Value(reg(a)?),
+        9 => Expr::Value(imm(a)),
+        10 => Expr::Binary(BinOp::Gt, imm(a), reg(b)?),
+        11 => Expr::Binary(BinOp::Gt, reg(a)?, imm(b)),
+        12 => Expr::Binary(BinOp::Gt, reg(a)?, reg(b)?),
+        13 => Expr::Binary(BinOp::Eq, imm(a), reg(b)?),
+        14 => Expr::Binary(BinOp::Eq, reg(a)?, imm(b)),
+        15 => Expr::Binary(BinOp::Eq, reg(a)?, reg(b)?),
+        op_code => return Err(Error::InvalidOpCode(op_code)),
+    };
+    if Some(c) != ip_reg && c >= GENERAL_REGISTER_COUNT {
+        return Err(Error::InvalidRegisterIndex(c));
+    }
+
+    Ok((c, expr.folded()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    Block(usize),
+    // Instruction pointer leaves the program.
+    Halt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminator {
+    Jump(Target),
+    Branch { cond: Cond, then_target: Target, else_target: Target },
+    // Jump to the value of expression plus 1, which can't be resolved statically.
+    Indirect(Expr),
+}
+
+impl Terminator {
+    pub fn targets(&self) -> Vec<Target> {
+        match self {
+            Terminator::Jump(t) => vec![*t],
+            Terminator::Branch { then_target, else_target, .. } => vec![*then_target, *else_target],
+            Terminator::Indirect(_) => Vec::new(),
+        }
+    }
+
+    fn map_targets<F: Fn(Target) -> Target>(&self, f: F) -> Self {
+        match *self {
+            Terminator::Jump(t) => Terminator::Jump(f(t)),
+            Terminator::Branch { cond, then_target, else_target } => Terminator::Branch { cond, then_target: f(then_target), else_target: f(else_target) },
+            Terminator::Indirect(expr) => Terminator::Indirect(expr),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    start: usize,
+    end: usize,
+    stmts: Vec<(usize, Expr)>,
+    term: Terminator,
+}
+
+impl BasicBlock {
+    // Address range of instructions in block.
+    pub fn addrs(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn stmts(&self) -> &[(usize, Expr)] {
+        &self.stmts
+    }
+
+    pub fn term(&self) -> &Terminator {
+        &self.term
+    }
+}
+
+// How control leaves one instruction.
+enum Flow {
+    Next,
+    Jump(usize),
+    // Jump offset is in the flag register, assumed to be 0 or 1.
+    Branch { flag: usize, then_addr: usize, else_addr: usize },
+    Indirect(Expr),
+}
+
+// Control flow graph of program, blocks are in reverse post order from the entry, which is the first block.
+// Blocks only jumping somewhere else are threaded through, so they don't show in graph.
+pub struct Cfg {
+    ip_reg: Option<usize>,
+    blocks: Vec<BasicBlock>,
+    notes: Vec<String>,
+}
+
+impl Display for Cfg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let target_text = |t: &Target| match t {
+            Target::Block(id) => format!("B{}", id),
+            Target::Halt => "halt".to_string(),
+        };
+        for (id, block) in self.blocks.iter().enumerate() {
+            let targets = block.term.targets().iter().map(target_text).collect::<Vec<_>>();
+            writeln!(f, "B{} [{}..{}) -> {}", id, block.start, block.end,
+                if targets.is_empty() { "?".to_string() } else { targets.join(", ") })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Cfg {
+    pub fn from_program(program: &Program) -> Result<Cfg, Error> {
+        let ip_reg = program.ip_reg();
+        let insts = program.insts();
+        let inst_n = insts.len();
+        let mut lifted = Vec::with_capacity(inst_n);
+        for (addr, inst) in insts.iter().enumerate() {
+            let (dst, expr) = lift(inst, addr, ip_reg)?;
+            let flow = if Some(dst) != ip_reg {
+                Flow::Next
+            } else if let Some(value) = expr.constant() {
+                Flow::Jump(value + 1)
+            } else if let Expr::Binary(BinOp::Add, Operand::Reg(flag), Operand::Imm(base))
+                | Expr::Binary(BinOp::Add, Operand::Imm(base), Operand::Reg(flag)) = expr {
+                Flow::Branch { flag, then_addr: base + 2, else_addr: base + 1 }
+            } else {
+                Flow::Indirect(expr)
+            };
+            lifted.push((dst, expr, flow));
+        }
+
+        let mut leaders = BTreeSet::from([0]);
+        for (addr, (_, _, flow)) in lifted.iter().enumerate() {
+            match flow {
+                Flow::Next => continue,
+                Flow::Jump(t) => { leaders.insert(*t); },
+                Flow::Branch { then_addr, else_addr, .. } => {
+                    leaders.insert(*then_addr);
+                    leaders.insert(*else_addr);
+                },
+                Flow::Indirect(_) => {},
+            }
+            leaders.insert(addr + 1);
+        }
+        leaders.retain(|&addr| addr < inst_n);
+        let starts = leaders.into_iter().collect::<Vec<_>>();
+        let block_inds = starts.iter().enumerate().map(|(ind, addr)| (*addr, ind)).collect::<HashMap<_, _>>();
+        let target_at = |addr: usize| block_inds.get(&addr).map_or(Target::Halt, |ind| Target::Block(*ind));
+
+        let mut notes = Vec::new();
+        let mut raw_blocks = Vec::with_capacity(starts.len());
+        for (ind, start) in starts.iter().enumerate() {
+            let end = starts.get(ind + 1).copied().unwrap_or(inst_n);
+            let stmts = lifted[*start..end].iter()
+                .filter(|(_, _, flow)| matches!(flow, Flow::Next))
+                .map(|(dst, expr, _)| (*dst, *expr))
+                .collect::<Vec<_>>();
+            let term = match &lifted[end - 1].2 {
+                Flow::Next => Terminator::Jump(target_at(end)),
+                Flow::Jump(t) => Terminator::Jump(target_at(*t)),
+                Flow::Branch { flag, then_addr, else_addr } => {
+                    let is_bool = stmts.iter().rev().find(|(dst, _)| dst == flag)
+                        .is_some_and(|(_, expr)| matches!(expr, Expr::Binary(op, _, _) if op.is_cmp()));
+                    if !is_bool {
+                        notes.push(format!("jump offset r{} at {} is assumed to be 0 or 1", flag, end - 1));
+                    }
+                    Terminator::Branch { cond: Cond::non_zero(*flag), then_target: target_at(*then_addr), else_target: target_at(*else_addr) }
+                },
+                Flow::Indirect(expr) => Terminator::Indirect(*expr),
+            };
+            raw_blocks.push(BasicBlock { start: *start, end, stmts, term });
+        }
+
+        let thread = |mut target: Target| {
+            let mut visited = HashSet::new();
+            while let Target::Block(ind) = target {
+                match raw_blocks[ind].term {
+                    Terminator::Jump(next) if raw_blocks[ind].stmts.is_empty() && visited.insert(ind) => target = next,
+                    _ => break,
+                }
+            }
+            target
+        };
+        let raw_blocks = raw_blocks.iter()
+            .map(|b| BasicBlock { term: b.term.map_targets(thread), ..b.clone() })
+            .collect::<Vec<_>>();
+
+        let mut post_order = Vec::with_capacity(raw_blocks.len());
+        let mut visited = vec![false; raw_blocks.len()];
+        if let Target::Block(entry) = thread(target_at(0)) {
+            Self::visit(&raw_blocks, entry, &mut visited, &mut post_order);
+        }
+        let ids = post_order.iter().rev().enumerate().map(|(id, ind)| (*ind, id)).collect::<HashMap<_, _>>();
+        let blocks = post_order.iter().rev()
+            .map(|ind| {
+                let block = &raw_blocks[*ind];
+                let term = block.term.map_targets(|t| match t {
+                    Target::Block(ind) => Target::Block(ids[&ind]),
+                    Target::Halt => Target::Halt,
+                });
+                BasicBlock { term, ..block.clone() }
+            })
+            .collect::<Vec<_>>();
+
+        let mut cfg = Cfg { ip_reg, blocks, notes };
+        cfg.fold_conds();
+        Ok(cfg)
+    }
+
+    fn visit(blocks: &[BasicBlock], ind: usize, visited: &mut [bool], post_order: &mut Vec<usize>) {
+        visited[ind] = true;
+        for target in blocks[ind].term.targets() {
+            if let Target::Block(next) = target {
+                if !visited[next] {
+                    Self::visit(blocks, next, visited, post_order);
+                }
+            }
+        }
+        post_order.push(ind);
+    }
+
+    // Registers live at entry of every block, registers are all live when program halts.
+    fn live_ins(&self) -> Vec<u8> {
+        const ALL: u8 = (1 << GENERAL_REGISTER_COUNT) - 1;
+        let uses_defs = self.blocks.iter()
+            .map(|block| {
+                let (mut uses, mut defs) = match block.term {
+                    Terminator::Branch { cond, .. } => (cond.reg_mask(), 0),
+                    Terminator::Indirect(_) => (ALL, 0),
+                    Terminator::Jump(_) => (0, 0),
+                };
+                for (dst, expr) in block.stmts.iter().rev() {
+                    uses &= !(1 << dst);
+                    defs |= 1 << dst;
+                    uses |= expr.reg_mask();
+                }
+                (uses, defs)
+            })
+            .collect::<Vec<_>>();
+        let mut live_ins = vec![0u8; self.blocks.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in (0..self.blocks.len()).rev() {
+                let live_out = self.blocks[id].term.targets().iter().fold(0, |live, t| live | match t {
+                    Target::Block(next) => live_ins[*next],
+                    Target::Halt => ALL,
+                });
+                let (uses, defs) = uses_defs[id];
+                let live_in = uses | (live_out & !defs);
+                if live_in != live_ins[id] {
+                    live_ins[id] = live_in;
+                    changed = true;
+                }
+            }
+        }
+
+        live_ins
+    }
+
+    // Merge comparison setting flag register into branch, when the flag isn't used afterwards.
+    fn fold_conds(&mut self) {
+        let live_ins = self.live_ins();
+        for block in &mut self.blocks {
+            let Terminator::Branch { cond, then_target, else_target } = block.term else { continue };
+            let Some(&(dst, Expr::Binary(op, lhs, rhs))) = block.stmts.last() else { continue };
+            let live_after = [then_target, else_target].iter().fold(0, |live, t| live | match t {
+                Target::Block(next) => live_ins[*next],
+                Target::Halt => u8::MAX,
+            });
+            if cond != Cond::non_zero(dst) || live_after & (1 << dst) != 0 {
+                continue;
+            }
+            if let Some(cond) = Cond::from_cmp(op, lhs, rhs) {
+                block.stmts.pop();
+                block.term = Terminator::Branch { cond, then_target, else_target };
+            }
+        }
+    }
+
+    pub fn ip_reg(&self) -> Option<usize> {
+        self.ip_reg
+    }
+
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    // Assumptions made while building graph.
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+}
+
+// Structured statement, addresses are start addresses of blocks.
+#[derive(Debug, Clone)]
+pub enum Node {
+    Assign(usize, Expr),
+    If(Cond, Vec<Node>, Vec<Node>),
+    Loop(usize, Vec<Node>),
+    // Address of loop header, and whether loop isn't the innermost one.
+    Break(usize, bool),
+    Continue(usize, bool),
+    Goto(usize),
+    Label(usize),
+    Halt,
+    IndirectJump(Expr),
+}
+
+struct Structurer<'a> {
+    cfg: &'a Cfg,
+    // Headers and exits of enclosing loops.
+    loops: Vec<(usize, Target)>,
+}
+
+impl Structurer<'_> {
+    // Structure blocks in range, control falling off the range goes to follow.
+    fn emit_range(&mut self, from: usize, to: usize, follow: Target, loop_header: Option<usize>) -> Vec<Node> {
+        let blocks = &self.cfg.blocks;
+        let mut nodes = Vec::new();
+        let mut b = from;
+        while b < to {
+            let block = &blocks[b];
+            let latch = (b..to).filter(|&l| blocks[l].term.targets().contains(&Target::Block(b))).max();
+            if let (Some(latch), true) = (latch, loop_header != Some(b)) {
+                let exit = if latch + 1 < to { Target::Block(latch + 1) } else { follow };
+                self.loops.push((b, exit));
+                let mut body = self.emit_range(b, latch + 1, Target::Block(b), Some(b));
+                self.loops.pop();
+                if let Some(Node::Continue(_, false)) = body.last() {
+                    body.pop();
+                }
+                nodes.push(Node::Loop(block.start, body));
+                b = latch + 1;
+                continue;
+            }
+
+            nodes.push(Node::Label(block.start));
+            nodes.extend(block.stmts.iter().map(|(dst, expr)| Node::Assign(*dst, *expr)));
+            let next = if b + 1 < to { Target::Block(b + 1) } else { follow };
+            b += 1;
+            let (cond, far) = match block.term {
+                Terminator::Jump(t) => {
+                    if t != next {
+                        nodes.push(self.jump(t));
+                    }
+                    continue;
+                },
+                Terminator::Indirect(expr) => {
+                    nodes.push(Node::IndirectJump(expr));
+                    continue;
+                },
+                Terminator::Branch { cond, then_target, else_target } if else_target == next => (cond, then_target),
+                Terminator::Branch { cond, then_target, else_target } if then_target == next => (cond.negate(), else_target),
+                Terminator::Branch { cond, then_target, else_target } => {
+                    nodes.push(Node::If(cond, vec![self.jump(then_target)], Vec::new()));
+                    nodes.push(self.jump(else_target));
+                    continue;
+                },
+            };
+
+            // Control goes to far when cond holds, otherwise to the next block.
+            let far_end = match far {
+                Target::Block(id) if id > b && id < to => Some(id),
+                _ if far == Target::Halt || self.is_loop_target(far) => None,
+                _ if far == follow && b < to => Some(to),
+                _ => None,
+            };
+            let Some(far_end) = far_end else {
+                nodes.push(Node::If(cond, vec![self.jump(far)], Vec::new()));
+                continue;
+            };
+            // Block before far jumping forward over following blocks makes them else branch.
+            let join = match blocks[far_end - 1].term {
+                Terminator::Jump(Target::Block(j)) if j > far_end && j < to => Some((j, Target::Block(j))),
+                Terminator::Jump(t) if t == follow && t != far && far_end < to && !self.is_loop_target(t) => Some((to, follow)),
+                _ => None,
+            };
+            if let Some((join_end, join)) = join {
+                let then_nodes = self.emit_range(b, far_end, join, None);
+                let else_nodes = self.emit_range(far_end, join_end, join, None);
+                nodes.push(Node::If(cond.negate(), then_nodes, else_nodes));
+                b = join_end;
+            } else {
+                let then_nodes = self.emit_range(b, far_end, far, None);
+                nodes.push(Node::If(cond.negate(), then_nodes, Vec::new()));
+                b = far_end;
+            }
+        }
+
+        nodes
+    }
+
+    fn is_loop_target(&self, target: Target) -> bool {
+        self.loops.iter().any(|(header, exit)| target == Target::Block(*header) || target == *exit)
+    }
+
+    fn jump(&self, target: Target) -> Node {
+        let Target::Block(id) = target else {
+            return Node::Halt;
+        };
+        for (depth, (header, exit)) in self.loops.iter().enumerate().rev() {
+            let labeled = depth + 1 != self.loops.len();
+            if *header == id {
+                return Node::Continue(self.cfg.blocks[*header].start, labeled);
+            } else if *exit == target {
+                return Node::Break(self.cfg.blocks[*header].start, labeled);
+            }
+        }
+
+        Node::Goto(self.cfg.blocks[id].start)
+    }
+}
+
+// Structured pseudocode of program.
+pub struct Decompiled {
+    ip_reg: Option<usize>,
+    notes: Vec<String>,
+    nodes: Vec<Node>,
+}
+
+impl Display for Decompiled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ip_reg) = self.ip_reg {
+            writeln!(f, "// ip is bound to r{}, reading it gives the current address", ip_reg)?;
+        }
+        for note in &self.notes {
+            writeln!(f, "// note: {}", note)?;
+        }
+
+        let mut goto_addrs = HashSet::new();
+        let mut loop_labels = HashSet::new();
+        Self::collect_labels(&self.nodes, &mut goto_addrs, &mut loop_labels);
+        self.write_nodes(f, &self.nodes, 0, &goto_addrs, &loop_labels)
+    }
+}
+
+impl Decompiled {
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    fn collect_labels(nodes: &[Node], goto_addrs: &mut HashSet<usize>, loop_labels: &mut HashSet<usize>) {
+        for node in nodes {
+            match node {
+                Node::If(_, then_nodes, else_nodes) => {
+                    Self::collect_labels(then_nodes, goto_addrs, loop_labels);
+                    Self::collect_labels(else_nodes, goto_addrs, loop_labels);
+                },
+                Node::Loop(_, body) => Self::collect_labels(body, goto_addrs, loop_labels),
+                Node::Break(addr, true) | Node::Continue(addr, true) => { loop_labels.insert(*addr); },
+                Node::Goto(addr) => { goto_addrs.insert(*addr); },
+                _ => {},
+            }
+        }
+    }
+
+    fn write_nodes(&self, f: &mut std::fmt::Formatter<'_>, nodes: &[Node], depth: usize,
+        goto_addrs: &HashSet<usize>, loop_labels: &HashSet<usize>) -> std::fmt::Result {
+        let indent = "    ".repeat(depth);
+        for node in nodes {
+            match node {
+                Node::Assign(dst, expr) => match expr {
+                    Expr::Binary(op, Operand::Reg(a), b) if !op.is_cmp() && a == dst => writeln!(f, "{}r{} {}= {}", indent, dst, op.symbol(), b)?,
+                    Expr::Binary(op, a, Operand::Reg(b)) if !op.is_cmp() && b == dst => writeln!(f, "{}r{} {}= {}", indent, dst, op.symbol(), a)?,
+                    Expr::Binary(op, _, _) if op.is_cmp() => writeln!(f, "{}r{} = ({}) ? 1 : 0", indent, dst, expr)?,
+                    _ => writeln!(f, "{}r{} = {}", indent, dst, expr)?,
+                },
+                Node::If(cond, then_nodes, else_nodes) => {
+                    let (cond, then_nodes, else_nodes) = if then_nodes.is_empty() {
+                        (cond.negate(), else_nodes, then_nodes)
+                    } else {
+                        (*cond, then_nodes, else_nodes)
+                    };
+                    writeln!(f, "{}if {} {{", indent, cond)?;
+                    self.write_nodes(f, then_nodes, depth + 1, goto_addrs, loop_labels)?;
+                    if !else_nodes.is_empty() {
+                        writeln!(f, "{}}} else {{", indent)?;
+                        self.write_nodes(f, else_nodes, depth + 1, goto_addrs, loop_labels)?;
+                    }
+                    writeln!(f, "{}}}", indent)?;
+                },
+                Node::Loop(addr, body) => {
+                    if loop_labels.contains(addr) {
+                        writeln!(f, "{}'l{}: loop {{", indent, addr)?;
+                    } else {
+                        writeln!(f, "{}loop {{", indent)?;
+                    }
+                    self.write_nodes(f, body, depth + 1, goto_addrs, loop_labels)?;
+                    writeln!(f, "{}}}", indent)?;
+                },
+                Node::Break(addr, labeled) => if *labeled {
+                    writeln!(f, "{}break 'l{}", indent, addr)?
+                } else {
+                    writeln!(f, "{}break", indent)?
+                },
+                Node::Continue(addr, labeled) => if *labeled {
+                    writeln!(f, "{}continue 'l{}", indent, addr)?
+                } else {
+                    writeln!(f, "{}continue", indent)?
+                },
+                Node::Goto(addr) => writeln!(f, "{}goto L{}", indent, addr)?,
+                Node::Label(addr) => if goto_addrs.contains(addr) {
+                    writeln!(f, "{}L{}:", indent, addr)?
+                },
+                Node::Halt => writeln!(f, "{}halt", indent)?,
+                Node::IndirectJump(expr) => writeln!(f, "{}goto ({}) + 1", indent, expr)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn decompile(program: &Program) -> Result<Decompiled, Error> {
+    let cfg = Cfg::from_program(program)?;
+    let mut structurer = Structurer { cfg: &cfg, loops: Vec::new() };
+    let nodes = structurer.emit_range(0, cfg.blocks.len(), Target::Halt, None);
+
+    Ok(Decompiled { ip_reg: cfg.ip_reg, notes: cfg.notes.clone(), nodes })
+}
+
+#[cfg(test)]
+fn count_loops(nodes: &[Node]) -> usize {
+    nodes.iter().map(|node| match node {
+        Node::Loop(_, body) => 1 + count_loops(body),
+        Node::If(_, then_nodes, else_nodes) => count_loops(then_nodes) + count_loops(else_nodes),
+        _ => 0,
+    }).sum()
+}
+
+#[test]
+fn test_lift() {
+    let ip_reg = Some(4);
+    let lifted = |text: &str, addr: usize| lift(&Instruction::try_from(text).unwrap(), addr, ip_reg).unwrap();
+    assert_eq!(lifted("mulr 1 2 3", 0), (3, Expr::Binary(BinOp::Mul, Operand::Reg(1), Operand::Reg(2))));
+    assert_eq!(lifted("gtir 256 2 4", 0), (4, Expr::Binary(BinOp::Gt, Operand::Imm(256), Operand::Reg(2))));
+    // Reading ip register gives the address, so constant operands are folded.
+    assert_eq!(lifted("addi 4 16 4", 3), (4, Expr::Value(Operand::Imm(19))));
+    assert_eq!(lifted("addr 3 4 4", 5), (4, Expr::Binary(BinOp::Add, Operand::Reg(3), Operand::Imm(5))));
+    assert!(matches!(lift(&Instruction::new(0, [1, 6, 2]), 0, ip_reg), Err(Error::InvalidRegisterIndex(6))));
+    assert!(matches!(lift(&Instruction::new(9, [1, 0, 7]), 0, ip_reg), Err(Error::InvalidRegisterIndex(7))));
+}
+
+#[test]
+fn test_cfg_folds_dead_flag() {
+    let program = Program::try_from("#ip 5\nseti 0 0 1\naddi 1 1 1\ngtri 1 9 2\naddr 2 5 5\nseti 0 0 5\nseti 7 0 2").unwrap();
+    let cfg = Cfg::from_program(&program).unwrap();
+    // Block at 4 only jumps back, so it's threaded through.
+    assert_eq!(cfg.to_string(), "B0 [0..1) -> B1\nB1 [1..4) -> B2, B1\nB2 [5..6) -> halt\n");
+    assert_eq!(cfg.blocks()[1].stmts(), &[(1, Expr::Binary(BinOp::Add, Operand::Reg(1), Operand::Imm(1)))]);
+    let cond = Cond { op: CmpOp::Gt, lhs: Operand::Reg(1), rhs: Operand::Imm(9) };
+    assert!(matches!(cfg.blocks()[1].term(), Terminator::Branch { cond: c, .. } if *c == cond));
+    assert!(cfg.notes().is_empty());
+
+    let decompiled = decompile(&program).unwrap();
+    assert_eq!(decompiled.to_string(), "// ip is bound to r5, reading it gives the current address\n\
+        r1 = 0\nloop {\n    r1 += 1\n    if r1 > 9 {\n        break\n    }\n}\nr2 = 7\n");
+}
+
+#[test]
+fn test_cfg_keeps_live_flag() {
+    // Flag is read after halting, so the comparison stays.
+    let program = Program::try_from("#ip 5\nseti 0 0 1\naddi 1 1 1\ngtri 1 9 2\naddr 2 5 5\nseti 0 0 5").unwrap();
+    let cfg = Cfg::from_program(&program).unwrap();
+    assert_eq!(cfg.to_string(), "B0 [0..1) -> B1\nB1 [1..4) -> halt, B1\n");
+    assert_eq!(cfg.blocks()[1].stmts().last(), Some(&(2, Expr::Binary(BinOp::Gt, Operand::Reg(1), Operand::Imm(9)))));
+    assert!(matches!(cfg.blocks()[1].term(), Terminator::Branch { cond, .. } if *cond == Cond::non_zero(2)));
+}
+
+#[test]
+fn test_cfg_unresolved_jumps() {
+    let program = Program::try_from("#ip 1\naddr 0 1 1\nseti 3 0 0\nmulr 0 0 1\nseti 4 0 0").unwrap();
+    let cfg = Cfg::from_program(&program).unwrap();
+    assert_eq!(cfg.notes(), &["jump offset r0 at 0 is assumed to be 0 or 1".to_string()]);
+    assert_eq!(cfg.to_string(), "B0 [0..1) -> B2, B1\nB1 [1..2) -> B2\nB2 [2..3) -> ?\n");
+
+    let decompiled = decompile(&program).unwrap();
+    let text = decompiled.to_string();
+    assert!(text.contains("// note: jump offset r0 at 0 is assumed to be 0 or 1\n"));
+    assert!(text.contains("goto (r0 * r0) + 1\n"));
+}
+
+#[test]
+fn test_decompile_puzzle_inputs() {
+    let program = crate::inst::load_program("day19_input.txt").unwrap();
+    let decompiled = decompile(&program).unwrap();
+    // Divisor sum is a loop scanning factors nested in a loop over divisors.
+    assert_eq!(count_loops(decompiled.nodes()), 2);
+    assert!(decompiled.to_string().contains("        r3 = r1 * r2\n        if r3 == r5 {\n            r0 += r1\n        }\n"));
+
+    let program = crate::inst::load_program("day21_input.txt").unwrap();
+    let cfg = Cfg::from_program(&program).unwrap();
+    assert!(cfg.notes().is_empty());
+    assert!(cfg.blocks().iter().all(|block| !matches!(block.term(), Terminator::Indirect(_))));
+    assert!(count_loops(decompile(&program).unwrap().nodes()) >= 2);
+}
@@ -0,0 +1,256 @@
+use std::{collections::{BTreeSet, HashMap}, fmt::Display};
+
+use crate::inst::{self, ExeSample, OpCodeMap, OP_CODE_COUNT};
+
+fn names(op_inds: &BTreeSet<usize>) -> String {
+    let names = op_inds.iter().map(|ind| inst::op_name(*ind).unwrap_or("?")).collect::<Vec<_>>();
+    format!("{{{}}}", names.join(", "))
+}
+
+// Problem found while inferring operation codes, samples are given by their indices in loaded order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InferIssue {
+    // Sample no operation can produce.
+    NoCandidate { sample: usize, op_code: usize },
+    // Sample ruling out every operation left for its code by earlier samples.
+    Contradiction { sample: usize, op_code: usize, before: BTreeSet<usize>, candidates: BTreeSet<usize> },
+    // Operation code without any sample.
+    Unobserved(usize),
+    // Candidates can't be matched to distinct operation codes.
+    Unsatisfiable,
+    // Operation codes with more than one operation in different solutions.
+    Ambiguous(Vec<(usize, BTreeSet<usize>)>),
+}
+
+impl Display for InferIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InferIssue::NoCandidate { sample, op_code } => write!(f, "sample#{} of op code {} matches no operation", sample, op_code),
+            InferIssue::Contradiction { sample, op_code, before, candidates } => write!(f,
+                "sample#{} of op code {} matches {}, but earlier samples only leave {}", sample, op_code, names(candidates), names(before)),
+            InferIssue::Unobserved(op_code) => write!(f, "op code {} has no sample", op_code),
+            InferIssue::Unsatisfiable => write!(f, "candidates can't be assigned to distinct op codes"),
+            InferIssue::Ambiguous(op_codes) => {
+                let texts = op_codes.iter().map(|(op_code, op_inds)| format!("{} => {}", op_code, names(op_inds))).collect::<Vec<_>>();
+                write!(f, "op codes have more than one solution: {}", texts.join(", "))
+            },
+        }
+    }
+}
+
+// Result of inferring operation codes, the map is given only when it's the unique solution.
+#[derive(Debug, Clone)]
+pub struct Inference {
+    map: Option<OpCodeMap>,
+    candidates: HashMap<usize, BTreeSet<usize>>,
+    issues: Vec<InferIssue>,
+}
+
+impl Inference {
+    pub fn map(&self) -> Option<&OpCodeMap> {
+        self.map.as_ref()
+    }
+
+    // Operations each code could be, after samples are checked and before solving.
+    pub fn candidates(&self, op_code: usize) -> Option<&BTreeSet<usize>> {
+        self.candidates.get(&op_code)
+    }
+
+    pub fn issues(&self) -> &[InferIssue] {
+        &self.issues
+    }
+}
+
+// Infer operation of every code from samples: each code keeps operations all its samples agree with,
+// then codes are matched to distinct operations by propagation and backtracking.
+pub fn infer_op_codes(samples: &[ExeSample]) -> Inference {
+    let all_ops = (0..OP_CODE_COUNT).collect::<BTreeSet<_>>();
+    let mut candidates: HashMap<usize, BTreeSet<usize>> = (0..OP_CODE_COUNT).map(|c| (c, all_ops.clone())).collect();
+    let mut observed = BTreeSet::new();
+    let mut issues = Vec::new();
+    for (ind, sample) in samples.iter().enumerate() {
+        let op_code = sample.op_code();
+        let guess = inst::guess_insts(sample).into_iter().collect::<BTreeSet<_>>();
+        observed.insert(op_code);
+        if guess.is_empty() {
+            issues.push(InferIssue::NoCandidate { sample: ind, op_code });
+            continue;
+        }
+
+        let before = candidates.entry(op_code).or_insert_with(|| all_ops.clone());
+        let left = before.intersection(&guess).copied().collect::<BTreeSet<_>>();
+        if left.is_empty() {
+            // Keep candidates of earlier samples, so later ones are still checked.
+            issues.push(InferIssue::Contradiction { sample: ind, op_code, before: before.clone(), candidates: guess });
+        } else {
+            *before = left;
+        }
+    }
+    issues.extend((0..OP_CODE_COUNT).filter(|c| !observed.contains(c)).map(InferIssue::Unobserved));
+
+    let mut solutions = Vec::new();
+    let propagated = propagate(candidates.clone());
+    if let Some(propagated) = &propagated {
+        solve(propagated.clone(), &mut solutions, 2);
+    }
+    let map = match solutions.len() {
+        0 => {
+            issues.push(InferIssue::Unsatisfiable);
+            None
+        },
+        1 => Some(solutions[0].iter().map(|(c, ind)| (*c, *ind)).collect::<OpCodeMap>()),
+        _ => {
+            let propagated = propagated.as_ref().unwrap_or(&candidates);
+            let mut op_codes = solutions[0].keys()
+                .filter(|c| solutions[0][c] != solutions[1][c])
+                .map(|c| (*c, propagated[c].clone()))
+                .collect::<Vec<_>>();
+            op_codes.sort();
+            issues.push(InferIssue::Ambiguous(op_codes));
+            None
+        },
+    };
+
+    Inference { map, candidates, issues }
+}
+
+// Remove operations decided by other codes, and decide codes being the only one to have an operation,
+// until nothing changes. None when some code has no operation left.
+fn propagate(mut candidates: HashMap<usize, BTreeSet<usize>>) -> Option<HashMap<usize, BTreeSet<usize>>> {
+    loop {
+        let mut changed = false;
+        let decided = candidates.iter()
+            .filter(|(_, ops)| ops.len() == 1)
+            .map(|(c, ops)| (*c, *ops.first().unwrap()))
+            .collect::<Vec<_>>();
+        for (op_code, op_ind) in decided {
+            for (c, ops) in candidates.iter_mut() {
+                if *c != op_code && ops.remove(&op_ind) {
+                    changed = true;
+                }
+            }
+        }
+
+        for op_ind in 0..OP_CODE_COUNT {
+            let holders = candidates.iter().filter(|(_, ops)| ops.contains(&op_ind)).map(|(c, _)| *c).collect::<Vec<_>>();
+            if let [op_code] = holders[..] {
+                let ops = candidates.get_mut(&op_code).unwrap();
+                if ops.len() > 1 {
+                    *ops = BTreeSet::from([op_ind]);
+                    changed = true;
+                }
+            }
+        }
+
+        if candidates.values().any(|ops| ops.is_empty()) {
+            return None;
+        } else if !changed {
+            return Some(candidates);
+        }
+    }
+}
+
+// Collect at most max_n solutions, trying every operation of the code with the fewest candidates.
+fn solve(candidates: HashMap<usize, BTreeSet<usize>>, solutions: &mut Vec<HashMap<usize, usize>>, max_n: usize) {
+    let Some((op_code, ops)) = candidates.iter().filter(|(_, ops)| ops.len() > 1).min_by_key(|(c, ops)| (ops.len(), **c)) else {
+        solutions.push(candidates.iter().map(|(c, ops)| (*c, *ops.first().unwrap())).collect());
+        return;
+    };
+
+    for op_ind in ops {
+        if solutions.len() >= max_n {
+            return;
+        }
+        let mut guess = candidates.clone();
+        guess.insert(*op_code, BTreeSet::from([*op_ind]));
+        if let Some(propagated) = propagate(guess) {
+            solve(propagated, solutions, max_n);
+        }
+    }
+}
+
+#[cfg(test)]
+fn sample(before: [usize; 4], inst: &str, after: [usize; 4]) -> ExeSample {
+    ExeSample::from_str(&format!("Before: {:?}", before), inst, &format!("After:  {:?}", after)).unwrap()
+}
+
+#[cfg(test)]
+fn op_inds(op_names: &[&str]) -> BTreeSet<usize> {
+    (0..OP_CODE_COUNT).filter(|ind| inst::op_name(*ind).is_some_and(|name| op_names.contains(&name))).collect()
+}
+
+#[cfg(test)]
+fn code_of(map: &OpCodeMap, op_name: &str) -> usize {
+    (0..OP_CODE_COUNT).find(|c| map.name(*c) == Some(op_name)).unwrap()
+}
+
+#[test]
+fn test_infer_puzzle_samples() {
+    let samples = inst::load_samples("samples.txt").unwrap();
+    let inference = infer_op_codes(&samples);
+    assert!(inference.issues().is_empty());
+    let map = inference.map().unwrap();
+    assert_eq!(map.len(), OP_CODE_COUNT);
+    assert_eq!((0..OP_CODE_COUNT).filter_map(|c| map.get(c)).collect::<BTreeSet<_>>().len(), OP_CODE_COUNT);
+    assert!((0..OP_CODE_COUNT).all(|c| inference.candidates(c).unwrap().contains(&map.get(c).unwrap())));
+    // Samples alone leave codes undecided, the solver has to match them.
+    assert!((0..OP_CODE_COUNT).any(|c| inference.candidates(c).unwrap().len() > 1));
+}
+
+#[test]
+fn test_infer_contradictory_samples() {
+    let mut samples = inst::load_samples("samples.txt").unwrap();
+    let inference = infer_op_codes(&samples);
+    let map = inference.map().unwrap().clone();
+    let mulr = code_of(&map, "mulr");
+    let before = inference.candidates(mulr).unwrap().clone();
+    assert!(before.contains(&map.get(mulr).unwrap()));
+    let sample_n = samples.len();
+    // r1 = 7 comes from copying r0 or adding r5 = 0 to it, never from multiplying.
+    samples.push(sample([7, 0, 0, 0], &format!("{} 0 5 1", mulr), [7, 7, 0, 0]));
+    samples.push(sample([0, 0, 0, 0], &format!("{} 0 0 0", mulr), [5, 5, 5, 5]));
+
+    let inference = infer_op_codes(&samples);
+    assert_eq!(inference.issues(), &[
+        InferIssue::Contradiction { sample: sample_n, op_code: mulr, before: before.clone(), candidates: op_inds(&["addr", "setr", "borr", "bori"]) },
+        InferIssue::NoCandidate { sample: sample_n + 1, op_code: mulr },
+    ]);
+    // Candidates of earlier samples are kept, so the map is still found.
+    assert_eq!(inference.candidates(mulr), Some(&before));
+    assert_eq!(inference.map(), Some(&map));
+}
+
+#[test]
+fn test_infer_ambiguous_samples() {
+    let samples = inst::load_samples("samples.txt").unwrap();
+    let map = infer_op_codes(&samples).map().unwrap().clone();
+    let (addr, mulr) = (code_of(&map, "addr"), code_of(&map, "mulr"));
+    let samples = samples.into_iter().filter(|s| s.op_code() != addr && s.op_code() != mulr).collect::<Vec<_>>();
+
+    // Other codes are still decided, but addr and mulr can be swapped.
+    let inference = infer_op_codes(&samples);
+    assert!(inference.map().is_none());
+    let [InferIssue::Unobserved(c1), InferIssue::Unobserved(c2), InferIssue::Ambiguous(op_codes)] = inference.issues() else {
+        panic!("unexpected issues {:?}", inference.issues());
+    };
+    assert_eq!((*c1, *c2), (addr.min(mulr), addr.max(mulr)));
+    assert_eq!(op_codes.iter().map(|(c, _)| *c).collect::<Vec<_>>(), vec![*c1, *c2]);
+    assert!(op_codes.iter().all(|(_, ops)| ops.is_superset(&op_inds(&["addr", "mulr"]))));
+}
+
+#[test]
+fn test_infer_unsatisfiable_samples() {
+    let samples = inst::load_samples("samples.txt").unwrap();
+    let map = infer_op_codes(&samples).map().unwrap().clone();
+    let (addr, mulr) = (code_of(&map, "addr"), code_of(&map, "mulr"));
+    let mut samples = samples.into_iter().filter(|s| s.op_code() != addr && s.op_code() != mulr).collect::<Vec<_>>();
+    // Only addr gives 2 + 3, so both codes need it.
+    samples.push(sample([2, 3, 0, 0], &format!("{} 0 1 2", addr), [2, 3, 5, 0]));
+    samples.push(sample([2, 3, 0, 0], &format!("{} 0 1 2", mulr), [2, 3, 5, 0]));
+
+    let inference = infer_op_codes(&samples);
+    assert_eq!(inference.candidates(addr), Some(&op_inds(&["addr"])));
+    assert_eq!(inference.candidates(mulr), Some(&op_inds(&["addr"])));
+    assert!(inference.map().is_none());
+    assert_eq!(inference.issues(), &[InferIssue::Unsatisfiable]);
+}
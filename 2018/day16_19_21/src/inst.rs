@@ -1,4 +1,4 @@
-use std::{io::{self, BufReader, BufRead}, fs::File, collections::{HashSet, HashMap}, fmt::Display, path::Path};
+use std::{io::{self, BufReader, BufRead}, fs::File, collections::{HashSet, HashMap, hash_map::Entry}, fmt::Display, path::Path};
 
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -12,10 +12,8 @@ pub enum Error {
     InvalidOpCode(usize),
     InvalidRegisterIndex(usize),
     OpCodeNotfound(usize),
-    InvalidInstructionPointer(usize),
     IPMapParseError(String),
     OperationCodeParseError(String),
-    Break(usize),
 }
 
 impl Display for Error {
@@ -28,15 +26,13 @@ impl Display for Error {
             Error::InvalidOpCode(op_code) => write!(f, "Invalid operation code({})", op_code),
             Error::InvalidRegisterIndex(index) => write!(f, "Invalid register index({})", index),
             Error::OpCodeNotfound(op_code) => write!(f, "Can't find operation code({}) in map", op_code),
-            Error::InvalidInstructionPointer(ip) => write!(f, "Invalid instruction pointer({})", ip),
             Error::IPMapParseError(s) => write!(f, "Failed to parse ip map declaration from text({})", s),
             Error::OperationCodeParseError(s) => write!(f, "Failed to parse operation code from text({})", s),
-            Error::Break(ip) => write!(f, "Execution break at {}", ip),
         }
     }
 }
 
-const GENERAL_REGISTER_COUNT: usize = 6;
+pub const GENERAL_REGISTER_COUNT: usize = 6;
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct RegisterGroup {
     regs: [usize; GENERAL_REGISTER_COUNT],
@@ -139,6 +135,9 @@ impl RegisterGroup {
 
 type Oprands = [usize; 3];
 
+pub const OP_CODE_COUNT: usize = 16;
+const OP_CODE_MAX: usize = OP_CODE_COUNT - 1;
+
 pub struct Instruction {
     op_code: usize,
     oprands: Oprands,
@@ -148,18 +147,36 @@ impl TryFrom<&str> for Instruction {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::parse(value, None)
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "opcode: {}, oprands: {:?}", self.op_code, self.oprands)
+    }
+}
+
+impl Instruction {
+    pub fn new(op_code: usize, oprands: Oprands) -> Self {
+        Instruction { op_code, oprands }
+    }
+
+    // Parse instruction text, numeric operation code is translated by given map if any.
+    pub fn parse(value: &str, code_map: Option<&OpCodeMap>) -> Result<Self, Error> {
         static INST_NUMBER_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+) (\d+) (\d+) (\d+)").unwrap());
         static INST_NAME_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\w+) (\d+) (\d+) (\d+)").unwrap());
-        const OP_CODE_COUNT: usize = 16;
-        const OP_CODE_MAX: usize = OP_CODE_COUNT - 1;
 
         let caps = INST_NUMBER_PATTERN.captures(value).or_else(|| INST_NAME_PATTERN.captures(value))
             .ok_or(Error::InstructionFormatError(value.to_string()))?;
         let op_code = if let Ok(op_code) = caps[1].parse::<usize>() {
-            if op_code > OP_CODE_MAX {
+            if let Some(code_map) = code_map {
+                code_map.get(op_code).ok_or(Error::OpCodeNotfound(op_code))?
+            } else if op_code > OP_CODE_MAX {
                 return Err(Error::InvalidOpCode(op_code));
+            } else {
+                op_code
             }
-            op_code
         } else if let Some(op_code) = OP_NAME_MAP.get(&caps[1]) {
             *op_code
         } else {
@@ -175,18 +192,6 @@ impl TryFrom<&str> for Instruction {
             ]
         })
     }
-}
-
-impl Display for Instruction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "opcode: {}, oprands: {:?}", self.op_code, self.oprands)
-    }
-}
-
-impl Instruction {
-    pub fn new(op_code: usize, oprands: Oprands) -> Self {
-        Instruction { op_code, oprands }
-    }
 
     pub fn op_code(&self) -> usize {
         self.op_code
@@ -203,6 +208,12 @@ pub struct ExeSample {
     inst: Instruction,
 }
 
+impl Display for ExeSample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {} by {}", self.before, self.after, self.inst)
+    }
+}
+
 impl ExeSample {
     pub fn from_str(before: &str, inst: &str, after: &str) -> Result<ExeSample, Error> {
         static SAMPLE_BEFORE_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"Before:(.+)").unwrap());
@@ -255,6 +266,60 @@ pub fn load_insts<P>(input_path: P) -> Result<Vec<Instruction>, Error> where P:
         .collect::<Result<Vec<_>, _>>()
 }
 
+// Numeric operation codes translated to operation indices, as indices in OP_NAME_MAP.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OpCodeMap {
+    map: HashMap<usize, usize>,
+}
+
+impl Display for OpCodeMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut op_codes = self.map.keys().collect::<Vec<_>>();
+        op_codes.sort();
+        let pairs = op_codes.iter().map(|c| format!("{} => {}", c, op_name(self.map[c]).unwrap_or("?"))).collect::<Vec<_>>();
+        write!(f, "{{{}}}", pairs.join(", "))
+    }
+}
+
+impl FromIterator<(usize, usize)> for OpCodeMap {
+    fn from_iter<T: IntoIterator<Item = (usize, usize)>>(iter: T) -> Self {
+        OpCodeMap { map: iter.into_iter().collect() }
+    }
+}
+
+impl OpCodeMap {
+    pub fn get(&self, op_code: usize) -> Option<usize> {
+        self.map.get(&op_code).copied()
+    }
+
+    pub fn name(&self, op_code: usize) -> Option<&'static str> {
+        self.get(op_code).and_then(op_name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+pub fn load_insts_mapped<P>(input_path: P, code_map: &OpCodeMap) -> Result<Vec<Instruction>, Error> where P: AsRef<Path> {
+    let input_file = File::open(input_path).map_err(Error::IOError)?;
+    let reader = BufReader::new(input_file);
+    reader.lines()
+        .filter(|s| s.is_err() || !s.as_ref().unwrap().is_empty())
+        .map(|se| se.map_err(Error::IOError)
+            .and_then(|s| Instruction::parse(s.as_str(), Some(code_map))))
+        .collect::<Result<Vec<_>, _>>()
+}
+
+// Name of operation at given index, as in elfcode.
+pub fn op_name(op_ind: usize) -> Option<&'static str> {
+    OP_NAME_MAP.iter().find(|(_, ind)| **ind == op_ind).map(|(name, _)| *name)
+}
+
 static OP_NAME_MAP: Lazy<HashMap<&'static str, usize>> = Lazy::new(|| HashMap::from([
     ("addr", 0),
     ("addi", 1),
@@ -426,18 +491,183 @@ impl Operation for EqRR {
     }
 }
 
+// Why execution stops, ticks are instructions(or macro operations) executed since execution started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionOutcome {
+    // Instruction pointer leaves the program.
+    Halted { ip: usize, ticks: usize },
+    // Instruction pointer reaches a breakpoint, the instruction there isn't executed yet.
+    Break { ip: usize, ticks: usize },
+    StepLimit { ip: usize, ticks: usize },
+    // Observer stopping on repeat sees a value again, the instruction there isn't executed yet.
+    Repeated { ip: usize, ticks: usize },
+}
+
+impl Display for ExecutionOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionOutcome::Halted { ip, ticks } => write!(f, "halted at instruction#{} after {} ticks", ip, ticks),
+            ExecutionOutcome::Break { ip, ticks } => write!(f, "break at instruction#{} after {} ticks", ip, ticks),
+            ExecutionOutcome::StepLimit { ip, ticks } => write!(f, "step limit reached at instruction#{} after {} ticks", ip, ticks),
+            ExecutionOutcome::Repeated { ip, ticks } => write!(f, "observed value repeated at instruction#{} after {} ticks", ip, ticks),
+        }
+    }
+}
+
+impl ExecutionOutcome {
+    pub fn ip(&self) -> usize {
+        match self {
+            ExecutionOutcome::Halted { ip, .. } | ExecutionOutcome::Break { ip, .. } | ExecutionOutcome::StepLimit { ip, .. }
+            | ExecutionOutcome::Repeated { ip, .. } => *ip,
+        }
+    }
+
+    pub fn ticks(&self) -> usize {
+        match self {
+            ExecutionOutcome::Halted { ticks, .. } | ExecutionOutcome::Break { ticks, .. } | ExecutionOutcome::StepLimit { ticks, .. }
+            | ExecutionOutcome::Repeated { ticks, .. } => *ticks,
+        }
+    }
+}
+
+pub type BreakCond = Box<dyn Fn(&RegisterGroup) -> bool>;
+
+struct BreakPoint {
+    ip: usize,
+    cond: Option<BreakCond>,
+}
+
+impl BreakPoint {
+    fn hits(&self, regs: &RegisterGroup) -> bool {
+        self.ip == regs.ip() && self.cond.as_ref().is_none_or(|cond| cond(regs))
+    }
+}
+
+// Receives every tick before its instruction is executed.
+pub trait TraceSink {
+    fn trace(&mut self, tick: usize, inst: &Instruction, regs: &RegisterGroup);
+}
+
+impl<F> TraceSink for F where F: FnMut(usize, &Instruction, &RegisterGroup) {
+    fn trace(&mut self, tick: usize, inst: &Instruction, regs: &RegisterGroup) {
+        self(tick, inst, regs)
+    }
+}
+
+// Prints every tick to stdout.
+pub struct StdoutTrace;
+
+impl TraceSink for StdoutTrace {
+    fn trace(&mut self, tick: usize, _inst: &Instruction, regs: &RegisterGroup) {
+        println!("Tick #{}: general registers: {}, ip: {}", tick, regs, regs.ip());
+    }
+}
+
+// Collects values of a register whenever execution reaches an instruction, until they repeat.
+#[derive(Debug, Clone)]
+pub struct Observer {
+    ip: usize,
+    reg_ind: usize,
+    stop_on_repeat: bool,
+    values: Vec<usize>,
+    first_inds: HashMap<usize, usize>,
+    // Index of the first repeated value in values.
+    repeat_ind: Option<usize>,
+    last_tick: Option<usize>,
+}
+
+impl Observer {
+    pub fn new(ip: usize, reg_ind: usize) -> Self {
+        Observer { ip, reg_ind, stop_on_repeat: false, values: Vec::new(), first_inds: HashMap::new(), repeat_ind: None, last_tick: None }
+    }
+
+    // Stop execution with ExecutionOutcome::Repeated, when the first repeated value is seen.
+    pub fn stop_on_repeat(mut self) -> Self {
+        self.stop_on_repeat = true;
+        self
+    }
+
+    // Record register value, returns true when it's the first repeated value.
+    fn observe(&mut self, tick: usize, regs: &RegisterGroup) -> Result<bool, Error> {
+        if self.ip != regs.ip() || self.last_tick == Some(tick) {
+            return Ok(false);
+        }
+        self.last_tick = Some(tick);
+
+        let value = *regs.reg(self.reg_ind)?;
+        let ind = self.values.len();
+        self.values.push(value);
+        if self.repeat_ind.is_some() {
+            return Ok(false);
+        }
+        if let Entry::Vacant(entry) = self.first_inds.entry(value) {
+            entry.insert(ind);
+            Ok(false)
+        } else {
+            self.repeat_ind = Some(ind);
+            Ok(true)
+        }
+    }
+
+    // Forget observed values, execute does it for every observer before running.
+    pub fn reset(&mut self) {
+        self.values.clear();
+        self.first_inds.clear();
+        self.repeat_ind = None;
+        self.last_tick = None;
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn reg_ind(&self) -> usize {
+        self.reg_ind
+    }
+
+    // All observed values in order, including ones after the first repeat.
+    pub fn values(&self) -> &[usize] {
+        &self.values
+    }
+
+    // Values before the first repeat.
+    pub fn unique_values(&self) -> &[usize] {
+        &self.values[..self.repeat_ind.unwrap_or(self.values.len())]
+    }
+
+    pub fn first_unique(&self) -> Option<usize> {
+        self.unique_values().first().copied()
+    }
+
+    // The last value seen for the first time, only known once values repeat.
+    pub fn last_unique(&self) -> Option<usize> {
+        self.repeat_ind.and_then(|_| self.unique_values().last().copied())
+    }
+
+    // Index where the repeating sequence starts and its length.
+    pub fn cycle(&self) -> Option<(usize, usize)> {
+        let repeat_ind = self.repeat_ind?;
+        let start = self.first_inds[&self.values[repeat_ind]];
+        Some((start, repeat_ind - start))
+    }
+}
+
 pub struct Executor {
     regs: RegisterGroup,
-    break_point: Option<usize>,
+    break_points: Vec<BreakPoint>,
+    observers: Vec<Observer>,
+    step_limit: Option<usize>,
+    trace: Option<Box<dyn TraceSink>>,
+    ticks: usize,
 }
 
 impl Executor {
     pub fn new() -> Self {
-        Executor { regs: RegisterGroup::new(), break_point: None }
+        Self::with_regs(&RegisterGroup::new())
     }
 
     pub fn with_regs(regs: &RegisterGroup) -> Self {
-        Executor { regs: *regs, break_point: None }
+        Executor { regs: *regs, break_points: Vec::new(), observers: Vec::new(), step_limit: None, trace: None, ticks: 0 }
     }
 
     pub fn regs_mut(&mut self) -> &mut RegisterGroup {
@@ -448,21 +678,62 @@ impl Executor {
         &self.regs
     }
 
-    pub fn execute(&mut self, program: &Program) -> Result<(), Error> {
+    // Instructions executed since program execution started.
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    // Apply declarations of program, then execute it from current instruction pointer with observers reset.
+    pub fn execute(&mut self, program: &Program) -> Result<ExecutionOutcome, Error> {
         for decl in &program.decls {
             decl.apply(self)?;
         }
+        self.ticks = 0;
+        for observer in self.observers.iter_mut() {
+            observer.reset();
+        }
 
-        let mut tick_ind: usize = 0;
+        self.run(program, false)
+    }
+
+    // Continue stopped execution, breakpoint at current instruction is passed.
+    pub fn resume(&mut self, program: &Program) -> Result<ExecutionOutcome, Error> {
+        self.run(program, true)
+    }
+
+    fn run(&mut self, program: &Program, mut pass_break: bool) -> Result<ExecutionOutcome, Error> {
+        let mut step_n = 0;
         loop {
-            if Some(self.regs().ip()) == self.break_point {
-                return Err(Error::Break(self.regs().ip()));
+            let ip = self.regs.ip();
+            let Some(inst) = program.insts.get(ip) else {
+                return Ok(ExecutionOutcome::Halted { ip, ticks: self.ticks });
+            };
+            if !pass_break && self.break_points.iter().any(|b| b.hits(&self.regs)) {
+                return Ok(ExecutionOutcome::Break { ip, ticks: self.ticks });
+            }
+            pass_break = false;
+            if Some(step_n) == self.step_limit {
+                return Ok(ExecutionOutcome::StepLimit { ip, ticks: self.ticks });
+            }
+            let mut repeated = false;
+            for observer in self.observers.iter_mut() {
+                repeated |= observer.observe(self.ticks, &self.regs)? && observer.stop_on_repeat;
             }
-            println!("Tick #{}: general registers: {}, ip: {}", tick_ind, self.regs(), self.regs().ip());
-            let inst = program.insts.get(self.regs.ip()).ok_or(Error::InvalidInstructionPointer(self.regs.ip()))?;
-            self.execute_inst(inst)?;
-            *self.regs.ip_mut() += 1;
-            tick_ind += 1;
+            if repeated {
+                return Ok(ExecutionOutcome::Repeated { ip, ticks: self.ticks });
+            }
+
+            if let Some(trace) = self.trace.as_mut() {
+                trace.trace(self.ticks, inst, &self.regs);
+            }
+            if let Some(op) = program.macro_op(ip) {
+                op.execute(&mut self.regs)?;
+            } else {
+                self.execute_inst(inst)?;
+                *self.regs.ip_mut() += 1;
+            }
+            self.ticks += 1;
+            step_n += 1;
         }
     }
 
@@ -474,8 +745,46 @@ impl Executor {
         OPERATIONS[inst.op_code].execute(&inst.oprands, self.regs_mut())
     }
 
+    // Replace all breakpoints with the one at given instruction.
     pub fn set_break_at(&mut self, b_ind: usize) {
-        self.break_point = Some(b_ind);
+        self.clear_breaks();
+        self.add_break_at(b_ind);
+    }
+
+    pub fn add_break_at(&mut self, b_ind: usize) {
+        self.break_points.push(BreakPoint { ip: b_ind, cond: None });
+    }
+
+    // Break at given instruction only when registers meet the condition.
+    pub fn add_break_if<F>(&mut self, b_ind: usize, cond: F) where F: Fn(&RegisterGroup) -> bool + 'static {
+        self.break_points.push(BreakPoint { ip: b_ind, cond: Some(Box::new(cond)) });
+    }
+
+    pub fn clear_breaks(&mut self) {
+        self.break_points.clear();
+    }
+
+    // Returns index of added observer.
+    pub fn add_observer(&mut self, observer: Observer) -> usize {
+        self.observers.push(observer);
+        self.observers.len() - 1
+    }
+
+    pub fn observer(&self, ind: usize) -> Option<&Observer> {
+        self.observers.get(ind)
+    }
+
+    // Max instructions executed by one call of execute or resume, None for no limit.
+    pub fn set_step_limit(&mut self, limit: Option<usize>) {
+        self.step_limit = limit;
+    }
+
+    pub fn set_trace<T>(&mut self, trace: T) where T: TraceSink + 'static {
+        self.trace = Some(Box::new(trace));
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.trace = None;
     }
 }
 
@@ -502,6 +811,11 @@ pub fn guess_insts(sample: &ExeSample) -> HashSet<usize> {
 
 trait Declaration {
     fn apply(&self, executor: &mut Executor) -> Result<(), Error>;
+
+    // Index of register bound to instruction pointer by this declaration.
+    fn ip_reg(&self) -> Option<usize> {
+        None
+    }
 }
 
 struct IPMap {
@@ -513,6 +827,10 @@ impl Declaration for IPMap {
         executor.regs_mut().map_ip(self.reg_ind);
         Ok(())
     }
+
+    fn ip_reg(&self) -> Option<usize> {
+        Some(self.reg_ind)
+    }
 }
 
 impl TryFrom<&str> for IPMap {
@@ -539,9 +857,39 @@ impl TryFrom<&str> for Statement {
     }
 }
 
+// Native operation standing for instructions from its address, it moves instruction pointer to the next instruction to execute.
+pub trait MacroOp: Display + Sync + Send {
+    fn execute(&self, regs: &mut RegisterGroup) -> Result<(), Error>;
+}
+
 pub struct Program {
     decls: Vec<Box<dyn Declaration>>,
     insts: Vec<Instruction>,
+    macro_ops: HashMap<usize, Box<dyn MacroOp>>,
+}
+
+impl Program {
+    pub fn insts(&self) -> &[Instruction] {
+        &self.insts
+    }
+
+    // Register bound to instruction pointer, the last declaration wins as in execution.
+    pub fn ip_reg(&self) -> Option<usize> {
+        self.decls.iter().rev().find_map(|d| d.ip_reg())
+    }
+
+    // Execute macro operation instead of instruction at given address.
+    pub fn set_macro_op(&mut self, addr: usize, op: Box<dyn MacroOp>) {
+        self.macro_ops.insert(addr, op);
+    }
+
+    pub fn macro_op(&self, addr: usize) -> Option<&dyn MacroOp> {
+        self.macro_ops.get(&addr).map(|op| op.as_ref())
+    }
+
+    pub fn clear_macro_ops(&mut self) {
+        self.macro_ops.clear();
+    }
 }
 
 struct ProgramBuilder {
@@ -564,21 +912,47 @@ impl ProgramBuilder {
     }
 
     pub fn build(self) -> Program {
-        Program { decls: self.decls, insts: self.insts }
+        Program { decls: self.decls, insts: self.insts, macro_ops: HashMap::new() }
     }
 }
 
-pub fn load_program<P>(input_path: P) -> Result<Program, Error> where P: AsRef<Path> {
-    let input_file = File::open(input_path).map_err(Error::IOError)?;
-    let reader = BufReader::new(input_file);
-    let lines = reader.lines()
-        .map(|lr| lr.map_err(Error::IOError))
-        .collect::<Result<Vec<_>, _>>()?;
-    
-    let mut builder = ProgramBuilder::new();
-    for line in lines {
-        builder.add_stm(line.as_str())?;
+impl TryFrom<&str> for Program {
+    type Error = Error;
+
+    // Program text with one statement per line.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut builder = ProgramBuilder::new();
+        for line in value.lines() {
+            builder.add_stm(line)?;
+        }
+
+        Ok(builder.build())
     }
+}
 
-    Ok(builder.build())
-}
\ No newline at end of file
+pub fn load_program<P>(input_path: P) -> Result<Program, Error> where P: AsRef<Path> {
+    let text = std::fs::read_to_string(input_path).map_err(Error::IOError)?;
+    Program::try_from(text.as_str())
+}
+
+#[test]
+fn test_observer_reset_on_execute() {
+    // r1 steps through 0, 3, 6, 1, 4, 7, 2, 5 and back to 0.
+    let program = Program::try_from("#ip 5\nseti 0 0 1\naddi 1 3 1\nbani 1 7 1\nseti 0 0 5").unwrap();
+    let mut executor = Executor::new();
+    executor.set_step_limit(Some(1000));
+    let ind = executor.add_observer(Observer::new(1, 1).stop_on_repeat());
+    for _ in 0..2 {
+        *executor.regs_mut() = RegisterGroup::new();
+        assert_eq!(executor.execute(&program).unwrap(), ExecutionOutcome::Repeated { ip: 1, ticks: 25 });
+        let observer = executor.observer(ind).unwrap();
+        assert_eq!(observer.values(), &[0, 3, 6, 1, 4, 7, 2, 5, 0]);
+        assert_eq!(observer.cycle(), Some((0, 8)));
+        assert_eq!((observer.first_unique(), observer.last_unique()), (Some(0), Some(5)));
+    }
+
+    let mut observer = executor.observer(ind).unwrap().clone();
+    observer.reset();
+    assert!(observer.values().is_empty());
+    assert_eq!((observer.cycle(), observer.first_unique()), (None, None));
+}
@@ -1 +1,4 @@
 pub mod inst;
+pub mod decomp;
+pub mod opt;
+pub mod infer;
@@ -0,0 +1,449 @@
+use std::{cell::RefCell, collections::HashSet, fmt::Display, rc::Rc};
+
+use crate::{
+    decomp::{self, BinOp, Cfg, Expr, Operand, Target},
+    inst::{Error, ExecutionOutcome, Executor, Instruction, MacroOp, Program, RegisterGroup},
+};
+
+// Loop of program with ticks spent in it while profiling.
+#[derive(Debug, Clone)]
+pub struct HotLoop {
+    header: usize,
+    addrs: Vec<usize>,
+    ticks: usize,
+}
+
+impl Display for HotLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "loop at {} ({} instructions): {} ticks", self.header, self.addrs.len(), self.ticks)
+    }
+}
+
+impl HotLoop {
+    pub fn header(&self) -> usize {
+        self.header
+    }
+
+    // Addresses of instructions in loop, in ascending order.
+    pub fn addrs(&self) -> &[usize] {
+        &self.addrs
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+}
+
+// Run program at most given steps from given registers, and rank its loops by ticks spent in them.
+// Loops are natural loops of control flow graph, nested loops are counted in outer loops too.
+pub fn hot_loops(program: &Program, regs: &RegisterGroup, step_limit: usize) -> Result<(Vec<HotLoop>, ExecutionOutcome), Error> {
+    let counts = Rc::new(RefCell::new(vec![0usize; program.insts().len()]));
+    let mut executor = Executor::with_regs(regs);
+    executor.set_step_limit(Some(step_limit));
+    let trace_counts = counts.clone();
+    executor.set_trace(move |_tick: usize, _inst: &Instruction, regs: &RegisterGroup| trace_counts.borrow_mut()[regs.ip()] += 1);
+    let outcome = executor.execute(program)?;
+
+    let counts = counts.borrow();
+    let cfg = Cfg::from_program(program)?;
+    let blocks = cfg.blocks();
+    let mut loops: Vec<HotLoop> = Vec::new();
+    for (header, block) in blocks.iter().enumerate() {
+        let latches = (header..blocks.len())
+            .filter(|&l| blocks[l].term().targets().contains(&Target::Block(header)))
+            .collect::<Vec<_>>();
+        if latches.is_empty() {
+            continue;
+        }
+
+        // Blocks reaching latches without passing header.
+        let mut body = HashSet::from([header]);
+        let mut stack = latches;
+        while let Some(id) = stack.pop() {
+            if body.insert(id) {
+                stack.extend((0..blocks.len()).filter(|&p| blocks[p].term().targets().contains(&Target::Block(id))));
+            }
+        }
+        let mut addrs = body.iter().flat_map(|id| blocks[*id].addrs()).collect::<Vec<_>>();
+        addrs.sort_unstable();
+        let ticks = addrs.iter().map(|addr| counts[*addr]).sum();
+        loops.push(HotLoop { header: block.addrs().start, addrs, ticks });
+    }
+    loops.sort_by(|a, b| b.ticks.cmp(&a.ticks).then(a.header.cmp(&b.header)));
+
+    Ok((loops, outcome))
+}
+
+// Idiom replaced by macro operation.
+#[derive(Debug, Clone)]
+pub struct Rewrite {
+    addr: usize,
+    len: usize,
+    desc: String,
+}
+
+impl Display for Rewrite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}..{}) => {}", self.addr, self.addr + self.len, self.desc)
+    }
+}
+
+impl Rewrite {
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    // Instructions covered by macro operation.
+    pub fn inst_n(&self) -> usize {
+        self.len
+    }
+}
+
+// Install macro operations at headers of loops matching known idioms. Instructions are kept,
+// so jumps into loop bodies still run them, breakpoints inside replaced loops are passed over.
+pub fn optimize(program: &mut Program) -> Result<Vec<Rewrite>, Error> {
+    let ip_reg = program.ip_reg();
+    let code = program.insts().iter().enumerate()
+        .map(|(addr, inst)| decomp::lift(inst, addr, ip_reg))
+        .collect::<Result<Vec<_>, _>>()?;
+    let code = Code { code, ip_reg };
+
+    let mut rewrites = Vec::new();
+    for addr in 0..code.code.len() {
+        let matched = DivisorSum::match_at(&code, addr).map(|op| (DivisorSum::LEN, Box::new(op) as Box<dyn MacroOp>))
+            .or_else(|| FactorScan::match_at(&code, addr).map(|op| (FactorScan::LEN, Box::new(op) as Box<dyn MacroOp>)))
+            .or_else(|| DivScan::match_at(&code, addr).map(|op| (DivScan::LEN, Box::new(op) as Box<dyn MacroOp>)));
+        if let Some((len, op)) = matched {
+            rewrites.push(Rewrite { addr, len, desc: op.to_string() });
+            program.set_macro_op(addr, op);
+        }
+    }
+
+    Ok(rewrites)
+}
+
+// Lifted instructions to match idioms on.
+struct Code {
+    code: Vec<(usize, Expr)>,
+    ip_reg: Option<usize>,
+}
+
+impl Code {
+    fn at(&self, addr: usize) -> Option<(usize, Expr)> {
+        self.code.get(addr).copied().filter(|(dst, _)| Some(*dst) != self.ip_reg)
+    }
+
+    // Register assigned at address with the sum of itself and something else.
+    fn add_to(&self, addr: usize) -> Option<(usize, Operand)> {
+        match self.at(addr)? {
+            (dst, Expr::Binary(BinOp::Add, Operand::Reg(a), other))
+            | (dst, Expr::Binary(BinOp::Add, other, Operand::Reg(a))) if a == dst => Some((dst, other)),
+            _ => None,
+        }
+    }
+
+    // Destination and operands of binary operation at address, commutative ones are given in both orders.
+    fn binary(&self, addr: usize, op: BinOp) -> Vec<(usize, Operand, Operand)> {
+        match self.at(addr) {
+            Some((dst, Expr::Binary(o, a, b))) if o == op => if matches!(op, BinOp::Add | BinOp::Mul | BinOp::Eq) {
+                vec![(dst, a, b), (dst, b, a)]
+            } else {
+                vec![(dst, a, b)]
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    // Target of unconditional jump at address.
+    fn jump(&self, addr: usize) -> Option<usize> {
+        match self.code.get(addr)? {
+            (dst, expr) if Some(*dst) == self.ip_reg => expr.constant().map(|value| value + 1),
+            _ => None,
+        }
+    }
+
+    // Instruction at address skips the next one when given flag is 1.
+    fn skip_if(&self, addr: usize, flag: usize) -> bool {
+        matches!(self.code.get(addr), Some((dst, Expr::Binary(BinOp::Add, a, b)))
+            if Some(*dst) == self.ip_reg && [*a, *b].contains(&Operand::Reg(flag)) && [*a, *b].contains(&Operand::Imm(addr)))
+    }
+}
+
+fn distinct(regs: &[usize]) -> bool {
+    regs.iter().collect::<HashSet<_>>().len() == regs.len()
+}
+
+fn get(regs: &RegisterGroup, ind: usize) -> Result<usize, Error> {
+    regs.reg(ind).copied()
+}
+
+fn set(regs: &mut RegisterGroup, ind: usize, value: usize) -> Result<(), Error> {
+    *regs.reg_mut(ind)? = value;
+    Ok(())
+}
+
+// Loop scanning factor(f) from current value up to n, adding a to sum(s) when a * f == n:
+//   do { t = a * f; if t == n { s += a }; f += 1; t = f > n } while t == 0
+struct FactorScan {
+    a: usize,
+    f: usize,
+    n: usize,
+    s: usize,
+    t: usize,
+    exit: usize,
+}
+
+impl Display for FactorScan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "if r{a} divides r{n} with quotient in [r{f}, r{n}] {{ r{s} += r{a} }}; r{f} = max(r{f}, r{n}) + 1; r{t} = 1; goto {exit}",
+            a = self.a, n = self.n, f = self.f, s = self.s, t = self.t, exit = self.exit)
+    }
+}
+
+impl FactorScan {
+    const LEN: usize = 9;
+
+    fn match_at(code: &Code, h: usize) -> Option<Self> {
+        for (t, a, f) in code.binary(h, BinOp::Mul) {
+            let (Operand::Reg(a), Operand::Reg(f)) = (a, f) else { continue };
+            for (t_eq, lhs, n) in code.binary(h + 1, BinOp::Eq) {
+                let Operand::Reg(n) = n else { continue };
+                if t_eq != t || lhs != Operand::Reg(t) || !code.skip_if(h + 2, t) || code.jump(h + 3) != Some(h + 5) {
+                    continue;
+                }
+                let Some((s, Operand::Reg(a_add))) = code.add_to(h + 4) else { continue };
+                if a_add != a || code.add_to(h + 5) != Some((f, Operand::Imm(1))) {
+                    continue;
+                }
+                if code.binary(h + 6, BinOp::Gt) != vec![(t, Operand::Reg(f), Operand::Reg(n))]
+                    || !code.skip_if(h + 7, t) || code.jump(h + 8) != Some(h) || !distinct(&[a, f, n, s, t]) {
+                    continue;
+                }
+                return Some(FactorScan { a, f, n, s, t, exit: h + Self::LEN });
+            }
+        }
+
+        None
+    }
+
+    fn scan(a: usize, f: usize, n: usize) -> (bool, usize) {
+        let last = f.max(n);
+        let divides = a != 0 && n.is_multiple_of(a) && (f..=last).contains(&(n / a));
+        (divides, last + 1)
+    }
+}
+
+impl MacroOp for FactorScan {
+    fn execute(&self, regs: &mut RegisterGroup) -> Result<(), Error> {
+        let (a, n) = (get(regs, self.a)?, get(regs, self.n)?);
+        let (divides, f) = Self::scan(a, get(regs, self.f)?, n);
+        if divides {
+            set(regs, self.s, get(regs, self.s)? + a)?;
+        }
+        set(regs, self.f, f)?;
+        set(regs, self.t, 1)?;
+        *regs.ip_mut() = self.exit;
+        Ok(())
+    }
+}
+
+// Loop over every a up to n, scanning factors from 1, which sums divisors of n:
+//   do { f = 1; <factor scan>; a += 1; t = a > n } while t == 0
+struct DivisorSum {
+    scan: FactorScan,
+}
+
+impl Display for DivisorSum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "r{s} += sum of divisors of r{n} in [r{a}, r{n}]; r{a} = max(r{a}, r{n}) + 1; r{f} = max(1, r{n}) + 1; r{t} = 1; goto {exit}",
+            s = self.scan.s, n = self.scan.n, a = self.scan.a, f = self.scan.f, t = self.scan.t, exit = self.scan.exit)
+    }
+}
+
+impl DivisorSum {
+    const LEN: usize = FactorScan::LEN + 5;
+
+    fn match_at(code: &Code, g: usize) -> Option<Self> {
+        let (f, init) = code.at(g)?;
+        if init != Expr::Value(Operand::Imm(1)) {
+            return None;
+        }
+        let scan = FactorScan::match_at(code, g + 1).filter(|scan| scan.f == f)?;
+        let (a, n, t) = (scan.a, scan.n, scan.t);
+        if code.add_to(g + 10) != Some((a, Operand::Imm(1))) || code.binary(g + 11, BinOp::Gt) != vec![(t, Operand::Reg(a), Operand::Reg(n))]
+            || !code.skip_if(g + 12, t) || code.jump(g + 13) != Some(g) {
+            return None;
+        }
+
+        Some(DivisorSum { scan: FactorScan { exit: g + Self::LEN, ..scan } })
+    }
+}
+
+impl MacroOp for DivisorSum {
+    fn execute(&self, regs: &mut RegisterGroup) -> Result<(), Error> {
+        let scan = &self.scan;
+        let (a, n) = (get(regs, scan.a)?, get(regs, scan.n)?);
+        let last = a.max(n);
+        let mut sum = 0;
+        if n != 0 {
+            let mut d = 1;
+            while d * d <= n {
+                if n % d == 0 {
+                    let e = n / d;
+                    sum += if d >= a { d } else { 0 } + if e != d && e >= a { e } else { 0 };
+                }
+                d += 1;
+            }
+        }
+        set(regs, scan.s, get(regs, scan.s)? + sum)?;
+        set(regs, scan.a, last + 1)?;
+        set(regs, scan.f, FactorScan::scan(last, 1, n).1)?;
+        set(regs, scan.t, 1)?;
+        *regs.ip_mut() = scan.exit;
+        Ok(())
+    }
+}
+
+// Loop finding quotient(q) of x divided by constant k, by counting up from current value:
+//   loop { t = q + 1; t *= k; t = t > x; if t != 0 { goto exit }; q += 1 }
+struct DivScan {
+    q: usize,
+    t: usize,
+    x: usize,
+    k: usize,
+    exit: usize,
+}
+
+impl Display for DivScan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "r{q} = max(r{q}, r{x} / {k}); r{t} = 1; goto {exit}", q = self.q, x = self.x, k = self.k, t = self.t, exit = self.exit)
+    }
+}
+
+impl DivScan {
+    const LEN: usize = 8;
+
+    fn match_at(code: &Code, h: usize) -> Option<Self> {
+        let (t, q) = match code.at(h)? {
+            (t, Expr::Binary(BinOp::Add, Operand::Reg(q), Operand::Imm(1)))
+            | (t, Expr::Binary(BinOp::Add, Operand::Imm(1), Operand::Reg(q))) => (t, q),
+            _ => return None,
+        };
+        let k = code.binary(h + 1, BinOp::Mul).into_iter().find_map(|m| match m {
+            (dst, Operand::Reg(src), Operand::Imm(k)) if dst == t && src == t && k > 0 => Some(k),
+            _ => None,
+        })?;
+        let x = match code.binary(h + 2, BinOp::Gt)[..] {
+            [(dst, Operand::Reg(src), Operand::Reg(x))] if dst == t && src == t => x,
+            _ => return None,
+        };
+        let exit = code.jump(h + 5)?;
+        if !code.skip_if(h + 3, t) || code.jump(h + 4) != Some(h + 6) || code.add_to(h + 6) != Some((q, Operand::Imm(1)))
+            || code.jump(h + 7) != Some(h) || !distinct(&[q, t, x]) {
+            return None;
+        }
+
+        Some(DivScan { q, t, x, k, exit })
+    }
+}
+
+impl MacroOp for DivScan {
+    fn execute(&self, regs: &mut RegisterGroup) -> Result<(), Error> {
+        let q = get(regs, self.q)?.max(get(regs, self.x)? / self.k);
+        set(regs, self.q, q)?;
+        set(regs, self.t, 1)?;
+        *regs.ip_mut() = self.exit;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn run_to_halt(program: &Program, regs: &RegisterGroup) -> (RegisterGroup, usize) {
+    let mut executor = Executor::with_regs(regs);
+    executor.set_step_limit(Some(1_000_000));
+    let outcome = executor.execute(program).unwrap();
+    assert!(matches!(outcome, ExecutionOutcome::Halted { .. }), "{} from {}", outcome, regs);
+    (*executor.regs(), outcome.ip())
+}
+
+// Optimize program text, check rewrites at given addresses, then run it with and without macro operations from every registers.
+#[cfg(test)]
+fn check_macro_ops(text: &str, addrs: &[usize], grid: &[[usize; 6]]) {
+    let plain = Program::try_from(text).unwrap();
+    let mut optimized = Program::try_from(text).unwrap();
+    let rewrites = optimize(&mut optimized).unwrap();
+    assert_eq!(rewrites.iter().map(|r| r.addr()).collect::<Vec<_>>(), addrs);
+    for arr in grid {
+        let regs = RegisterGroup::from_arr(arr);
+        assert_eq!(run_to_halt(&optimized, &regs), run_to_halt(&plain, &regs), "from {}", regs);
+    }
+}
+
+#[test]
+fn test_factor_scan() {
+    // Jump at 0 only moves to 1, so the loop can jump back to its header.
+    let text = "#ip 4\naddi 4 0 4\nmulr 1 2 3\neqrr 3 5 3\naddr 3 4 4\naddi 4 1 4\naddr 1 0 0\naddi 2 1 2\ngtrr 2 5 3\naddr 4 3 4\nseti 0 0 4";
+    let mut grid = Vec::new();
+    for a in 0..=6 {
+        for f in 0..=8 {
+            for n in 0..=12 {
+                grid.push([2, a, f, 5, 0, n]);
+            }
+        }
+    }
+    check_macro_ops(text, &[1], &grid);
+}
+
+#[test]
+fn test_divisor_sum() {
+    let text = "#ip 4\naddi 4 0 4\nseti 1 0 2\nmulr 1 2 3\neqrr 3 5 3\naddr 3 4 4\naddi 4 1 4\naddr 1 0 0\naddi 2 1 2\ngtrr 2 5 3\n\
+        addr 4 3 4\nseti 1 0 4\naddi 1 1 1\ngtrr 1 5 3\naddr 3 4 4\nseti 0 0 4";
+    let mut grid = Vec::new();
+    for s in 0..=2 {
+        for a in 0..=8 {
+            for f in [0, 3] {
+                for n in 0..=36 {
+                    grid.push([s, a, f, 7, 0, n]);
+                }
+            }
+        }
+    }
+    check_macro_ops(text, &[1, 2], &grid);
+}
+
+#[test]
+fn test_div_scan() {
+    let text = "#ip 5\naddi 5 0 5\naddi 4 1 3\nmuli 3 7 3\ngtrr 3 2 3\naddr 3 5 5\naddi 5 1 5\nseti 8 0 5\naddi 4 1 4\nseti 0 0 5";
+    let mut grid = Vec::new();
+    for q in 0..=12 {
+        for x in 0..=60 {
+            grid.push([1, 0, x, 9, q, 0]);
+        }
+    }
+    check_macro_ops(text, &[1], &grid);
+}
+
+#[test]
+fn test_optimize_rejects_near_idioms() {
+    // Sum register is the factor register, so the scan can't be replaced.
+    let text = "#ip 4\naddi 4 0 4\nmulr 1 2 3\neqrr 3 5 3\naddr 3 4 4\naddi 4 1 4\naddr 1 2 2\naddi 2 1 2\ngtrr 2 5 3\naddr 4 3 4\nseti 0 0 4";
+    assert!(optimize(&mut Program::try_from(text).unwrap()).unwrap().is_empty());
+    // Constant multiplier 0 never leaves the loop.
+    let text = "#ip 5\naddi 5 0 5\naddi 4 1 3\nmuli 3 0 3\ngtrr 3 2 3\naddr 3 5 5\naddi 5 1 5\nseti 8 0 5\naddi 4 1 4\nseti 0 0 5";
+    assert!(optimize(&mut Program::try_from(text).unwrap()).unwrap().is_empty());
+}
+
+#[test]
+fn test_optimize_puzzle_inputs() {
+    let mut program = crate::inst::load_program("day19_input.txt").unwrap();
+    let rewrites = optimize(&mut program).unwrap();
+    assert_eq!(rewrites.iter().map(|r| (r.addr(), r.inst_n())).collect::<Vec<_>>(), vec![(2, DivisorSum::LEN), (3, FactorScan::LEN)]);
+    // Setup code with r0 = 1 builds 4 * 19 * 11 + 22 + 18 + (27 * 28 + 29) * 30 * 14 * 32 in r5.
+    let n = 10551276;
+    let (regs, _) = run_to_halt(&program, &RegisterGroup::from_arr(&[1]));
+    assert_eq!(*regs.reg(0).unwrap(), (1..=n).filter(|d| n % d == 0).sum::<usize>());
+
+    let mut program = crate::inst::load_program("day21_input.txt").unwrap();
+    let rewrites = optimize(&mut program).unwrap();
+    assert_eq!(rewrites.iter().map(|r| (r.addr(), r.inst_n())).collect::<Vec<_>>(), vec![(18, DivScan::LEN)]);
+}
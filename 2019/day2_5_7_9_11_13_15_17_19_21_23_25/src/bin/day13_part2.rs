@@ -1,16 +1,27 @@
 use std::sync::Arc;
 
 use day2_5_7_9_11_13_15_17_19_21_23_25::{
-    day13::{ArcadeCabinet, AutoPlayer, Screen, TileId},
+    day13::{
+        self, ArcadeCabinet, AutoPlayer, GameMode, GreedyPlayer, HeadlessOptions, LookaheadPlayer,
+        Player, Screen, TileId,
+    },
     int_code::{
-        com::ParaIntCodeComputer,
-        io::{Channel, ParaIODevice, ParaInputDevice, ParaOutputDevice},
+        com::{ParaIntCodeComputer, SeqIntCodeComputer},
+        io::{Channel, ParaIODevice, ParaInputDevice, ParaOutputDevice, SeqIODevice},
         read_int_code,
     },
 };
 use rayon::ThreadPoolBuilder;
 
 fn main() {
+    let mode = day13::parse_args()
+        .inspect_err(|e| {
+            eprintln!(
+                "Failed to read given arguments, get error({}). Usage: day13_part2 [--compare | --headless [--text {{path}}] [--ppm {{dir}}] [--gif {{path}}] [--step {{n}}] [--scale {{n}}]]",
+                e
+            )
+        })
+        .unwrap();
     let input_path = "day13_inputs.txt";
     let mut int_code = match read_int_code(input_path) {
         Ok(ic) => ic,
@@ -23,6 +34,22 @@ fn main() {
         }
     };
 
+    if let GameMode::Compare = mode {
+        let players: Vec<(String, Box<dyn Player>)> = vec![
+            ("auto".to_string(), Box::new(AutoPlayer::new())),
+            ("greedy".to_string(), Box::new(GreedyPlayer)),
+            (
+                "lookahead".to_string(),
+                Box::new(LookaheadPlayer::new(&day13::insert_coins(&int_code))),
+            ),
+        ];
+        match day13::compete(&int_code, players) {
+            Ok(reports) => print!("{}", day13::report_table(&reports)),
+            Err(e) => eprintln!("Failed to compare players, get error({}).", e),
+        }
+        return;
+    }
+
     // Run arcade program to init settings(screen).
     let init_input = ParaInputDevice::new(Channel::new(&[]));
     let init_output = ParaOutputDevice::new(Channel::new(&[]));
@@ -37,6 +64,11 @@ fn main() {
     // Run actual game in arcade program.
     // Insert coins
     int_code[0] = 2;
+    if let GameMode::Headless(options) = mode {
+        run_headless(&int_code, screen, &options);
+        return;
+    }
+
     let fps = 30;
     let thread_pool = ThreadPoolBuilder::new()
         .num_threads(4)
@@ -74,3 +106,63 @@ fn main() {
         });
     });
 }
+
+fn run_headless(int_code: &[i64], screen: Screen, options: &HeadlessOptions) {
+    let mut cabinet = ArcadeCabinet::headless(screen, AutoPlayer::new());
+    cabinet.start().unwrap();
+    let io_device = SeqIODevice::new(cabinet);
+    let mut computer = SeqIntCodeComputer::new(false);
+    let res = computer.execute_with_io(
+        int_code,
+        io_device.input_device(),
+        io_device.output_device(),
+    );
+    io_device.tweak(|cab| {
+        cab.stop().unwrap();
+        match res {
+            Ok(res) => {
+                let (block_n, score) = cab.check_screen(|s| (s.count_id(TileId::Block), s.score()));
+                println!(
+                    "After {} steps, game ends, {} blocks remained, the final score is {}.",
+                    res.step_count(),
+                    block_n,
+                    score
+                );
+            }
+            Err(e) => eprintln!("Failed to run game, get error({})", e),
+        }
+
+        let recording = cab.recording().unwrap();
+        println!("{} frame(s) recorded.", recording.frames().len());
+        if let Some(path) = &options.text_path {
+            match recording.save_text(path, options.step) {
+                Ok(()) => println!("Frames are saved as text into file({}).", path),
+                Err(e) => eprintln!(
+                    "Failed to save frames into file({}), get error({}).",
+                    path, e
+                ),
+            }
+        }
+        if let Some(dir) = &options.ppm_dir {
+            match recording.save_ppm_sequence(dir, options.scale, options.step) {
+                Ok(n) => println!(
+                    "{} frame(s) are saved as PPM images into directory({}).",
+                    n, dir
+                ),
+                Err(e) => eprintln!(
+                    "Failed to save frames into directory({}), get error({}).",
+                    dir, e
+                ),
+            }
+        }
+        if let Some(path) = &options.gif_path {
+            match recording.save_gif(path, options.scale, options.step, 2) {
+                Ok(()) => println!("Frames are saved as GIF animation into file({}).", path),
+                Err(e) => eprintln!(
+                    "Failed to save frames into file({}), get error({}).",
+                    path, e
+                ),
+            }
+        }
+    });
+}
@@ -1,5 +1,5 @@
 use day2_5_7_9_11_13_15_17_19_21_23_25::{
-    day15::Autopilot,
+    day15::{Autopilot, MazeMap, Position},
     int_code::{
         com::{ProcessState, SeqIntCodeComputer},
         io::SeqIODevice,
@@ -8,6 +8,14 @@ use day2_5_7_9_11_13_15_17_19_21_23_25::{
 };
 
 fn main() {
+    // Optional exports of the whole map: --ppm {path} --frames {path}
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let arg_value = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1).cloned())
+    };
+    let (ppm_path, frames_path) = (arg_value("--ppm"), arg_value("--frames"));
     let input_path = "day15_inputs.txt";
     let int_code = match read_int_code(input_path) {
         Ok(ic) => ic,
@@ -78,6 +86,8 @@ fn main() {
                         let steps_from_oxygen_sys = ap.moves_from_origin(&final_pos).len();
                         println!("Final position searched is {}, {} steps from oxygen system, so it takes {} minutes for oxygen spreading out the whole room.", final_pos, steps_from_oxygen_sys, steps_from_oxygen_sys);
                     });
+                    let map = io_dev.check(|ap| ap.map());
+                    export_map(&map, ppm_path.as_deref(), frames_path.as_deref());
                 }
                 ProcessState::Halt => eprintln!("Remote control system halt unexpectedly."),
                 _ => eprintln!(
@@ -89,3 +99,43 @@ fn main() {
         Err(e) => eprintln!("Failed to run arcade program, get error({})", e),
     }
 }
+
+fn export_map(map: &MazeMap, ppm_path: Option<&str>, frames_path: Option<&str>) {
+    let (width, height) = map.size();
+    println!("Whole room({}x{}) mapped:\n{}", width, height, map);
+    let oxygen_sys_pos = map.oxygen_sys_pos().unwrap();
+    if let Some(path) = map.shortest_path(&Position::origin(), &oxygen_sys_pos) {
+        println!(
+            "Shortest path from start position to oxygen system takes {} moves.",
+            path.len()
+        );
+    }
+    let flood = map.flood(&oxygen_sys_pos);
+    println!(
+        "Simulated oxygen flood fills the whole room in {} minutes.",
+        flood.minutes()
+    );
+
+    if let Some(path) = ppm_path {
+        match map.save_ppm(path, 8) {
+            Ok(()) => println!("Map is saved as PPM image into file({}).", path),
+            Err(e) => eprintln!("Failed to save map into file({}), get error({}).", path, e),
+        }
+    }
+    if let Some(path) = frames_path {
+        let text = flood
+            .frames(map)
+            .iter()
+            .enumerate()
+            .map(|(minute, frame)| format!("Minute {}:\n{}", minute, frame))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match std::fs::write(path, text) {
+            Ok(()) => println!("Flood frames are saved into file({}).", path),
+            Err(e) => eprintln!(
+                "Failed to save frames into file({}), get error({}).",
+                path, e
+            ),
+        }
+    }
+}
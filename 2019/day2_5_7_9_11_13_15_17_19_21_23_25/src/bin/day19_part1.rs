@@ -1,5 +1,5 @@
 use day2_5_7_9_11_13_15_17_19_21_23_25::{
-    day19::{Error, PointType, Scanner},
+    day19::{BeamTracer, Error, PointType, Scanner},
     int_code::read_int_code,
 };
 
@@ -7,10 +7,18 @@ fn main() -> Result<(), Error> {
     let input_path = "day19_inputs.txt";
     let int_code = read_int_code(input_path).map_err(Error::IntCodeError)?;
 
-    let mut scanner = Scanner::new(int_code);
     let scan_area_width = 50;
     let scan_area_hight = 50;
-    let area = scanner.scan_area(0..scan_area_width, 0..scan_area_hight)?;
+    // Trace beam edges instead of scanning every point: --trace
+    let area = if std::env::args().any(|a| a == "--trace") {
+        let mut tracer = BeamTracer::new(int_code);
+        let area = tracer.area(0..scan_area_width, 0..scan_area_hight)?;
+        println!("{}.", tracer.stats());
+        area
+    } else {
+        let mut scanner = Scanner::new(int_code);
+        scanner.scan_area(0..scan_area_width, 0..scan_area_hight)?
+    };
     println!(
         "Area((0, 0), ({}, {})) scaned:\n{}",
         scan_area_width - 1,
@@ -1,12 +1,29 @@
 use day2_5_7_9_11_13_15_17_19_21_23_25::{
-    day19::{Error, Scanner},
+    day19::{self, BeamTracer, Error, Scanner},
     int_code::read_int_code,
 };
 
 fn main() -> Result<(), Error> {
+    let square_size = day19::parse_args().inspect_err(|e| {
+        eprintln!(
+            "Failed to read given arguments, get error({}). Usage: day19_part2 [--square n]",
+            e
+        )
+    })?;
     let input_path = "day19_inputs.txt";
     let int_code = read_int_code(input_path).map_err(Error::IntCodeError)?;
 
+    if let Some(size) = square_size {
+        let mut tracer = BeamTracer::new(int_code);
+        let (top_left_p, bottom_right_p) = tracer.first_square(size)?;
+        println!(
+            "The first square({} x {}) in beam is at ({}, {}).",
+            size, size, top_left_p, bottom_right_p
+        );
+        println!("{}.", tracer.stats());
+        return Ok(());
+    }
+
     let mut scanner = Scanner::new(int_code);
     let block_width = 100;
     let block_height = 100;
@@ -1,5 +1,7 @@
+use std::env;
+
 use day2_5_7_9_11_13_15_17_19_21_23_25::{
-    day21::{DetectMode, HullDetector, RRegister, SSInstruction, WRegister},
+    day21::{self, DetectMode, Formula, HullDetector},
     int_code::{com::SeqIntCodeComputer, io::SeqIODevice, read_int_code},
 };
 
@@ -8,15 +10,19 @@ fn main() {
     let int_code = read_int_code(input_path)
         .inspect_err(|e| eprintln!("Failed to read intcode from given input, get error({}).", e))
         .unwrap();
-    // (!A || !B || !C) && D
-    let script = vec![
-        SSInstruction::not(RRegister::A, WRegister::T),
-        SSInstruction::not(RRegister::B, WRegister::J),
-        SSInstruction::or(RRegister::T, WRegister::J),
-        SSInstruction::not(RRegister::C, WRegister::T),
-        SSInstruction::or(RRegister::T, WRegister::J),
-        SSInstruction::and(RRegister::D, WRegister::J),
-    ];
+    if env::args().skip(1).any(|arg| arg == "--synthesize") {
+        match day21::synthesize(&int_code, DetectMode::Walk) {
+            Ok(synthesis) => print!("{}", synthesis),
+            Err(e) => eprintln!("Failed to synthesize script, get error({}).", e),
+        }
+        return;
+    }
+
+    let script = "(!A || !B || !C) && D"
+        .parse::<Formula>()
+        .and_then(|formula| formula.compile(DetectMode::Walk))
+        .inspect_err(|e| eprintln!("Failed to compile script, get error({}).", e))
+        .unwrap();
     let io_dev = SeqIODevice::new(HullDetector::new(&script, DetectMode::Walk));
     let mut computer = SeqIntCodeComputer::new(false);
     match computer.execute_with_io(&int_code, io_dev.input_device(), io_dev.output_device()) {
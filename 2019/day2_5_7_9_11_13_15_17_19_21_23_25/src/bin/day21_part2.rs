@@ -1,5 +1,7 @@
+use std::env;
+
 use day2_5_7_9_11_13_15_17_19_21_23_25::{
-    day21::{DetectMode, HullDetector, RRegister, SSInstruction, WRegister},
+    day21::{self, DetectMode, Formula, HullDetector},
     int_code::{com::SeqIntCodeComputer, io::SeqIODevice, read_int_code},
 };
 
@@ -8,20 +10,19 @@ fn main() {
     let int_code = read_int_code(input_path)
         .inspect_err(|e| eprintln!("Failed to read intcode from given input, get error({}).", e))
         .unwrap();
-    // (!A || !B || (A && B && !C && (E || H))) && D
-    let script = vec![
-        SSInstruction::or(RRegister::E, WRegister::T),
-        SSInstruction::or(RRegister::H, WRegister::T),
-        SSInstruction::not(RRegister::C, WRegister::J),
-        SSInstruction::and(RRegister::T, WRegister::J),
-        SSInstruction::and(RRegister::A, WRegister::J),
-        SSInstruction::and(RRegister::B, WRegister::J),
-        SSInstruction::not(RRegister::A, WRegister::T),
-        SSInstruction::or(RRegister::T, WRegister::J),
-        SSInstruction::not(RRegister::B, WRegister::T),
-        SSInstruction::or(RRegister::T, WRegister::J),
-        SSInstruction::and(RRegister::D, WRegister::J),
-    ];
+    if env::args().skip(1).any(|arg| arg == "--synthesize") {
+        match day21::synthesize(&int_code, DetectMode::Run) {
+            Ok(synthesis) => print!("{}", synthesis),
+            Err(e) => eprintln!("Failed to synthesize script, get error({}).", e),
+        }
+        return;
+    }
+
+    let script = "(!A || !B || (A && B && !C && (E || H))) && D"
+        .parse::<Formula>()
+        .and_then(|formula| formula.compile(DetectMode::Run))
+        .inspect_err(|e| eprintln!("Failed to compile script, get error({}).", e))
+        .unwrap();
     let io_dev = SeqIODevice::new(HullDetector::new(&script, DetectMode::Run));
     let mut computer = SeqIntCodeComputer::new(false);
     match computer.execute_with_io(&int_code, io_dev.input_device(), io_dev.output_device()) {
@@ -12,11 +12,25 @@ fn main() {
             )
         })
         .unwrap();
+    let trace_options = day23::TraceOptions::from_args()
+        .inspect_err(|e| {
+            eprintln!(
+                "Failed to read trace options, get error({}). Usage: day23_part1 {{input path}} [--json {{path}}] [--mermaid {{path}}] [--dot {{path}}] [--max-events {{n}}]",
+                e
+            )
+        })
+        .unwrap();
     let host_n = 50;
     match day23::run_network(host_n, &intcode) {
         Ok(hub) => {
+            if !trace_options.is_empty() {
+                match hub.trace().export(&trace_options) {
+                    Ok(()) => println!("Network trace is exported."),
+                    Err(e) => eprintln!("Failed to export network trace, get error({}).", e),
+                }
+            }
             let check_addr = 255;
-            if let Some(packet) = hub.recv_pac_log(check_addr, 0) {
+            if let Some(packet) = hub.trace().first_pac_to(check_addr) {
                 println!(
                     "The first packet sent to address({}) is {}",
                     check_addr, packet
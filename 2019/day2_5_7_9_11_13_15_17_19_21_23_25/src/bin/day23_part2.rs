@@ -12,11 +12,25 @@ fn main() {
             )
         })
         .unwrap();
+    let trace_options = day23::TraceOptions::from_args()
+        .inspect_err(|e| {
+            eprintln!(
+                "Failed to read trace options, get error({}). Usage: day23_part2 {{input path}} [--json {{path}}] [--mermaid {{path}}] [--dot {{path}}] [--max-events {{n}}]",
+                e
+            )
+        })
+        .unwrap();
     let host_n = 50;
     let nat_addr = 255;
     let nat_send_addr = 0;
     match day23::run_network_nat(host_n, &intcode, nat_addr, nat_send_addr) {
         Ok(hub) => {
+            if !trace_options.is_empty() {
+                match hub.trace().export(&trace_options) {
+                    Ok(()) => println!("Network trace is exported."),
+                    Err(e) => eprintln!("Failed to export network trace, get error({}).", e),
+                }
+            }
             let nat_sent_pacs = hub.nat().unwrap().sent_pacs();
             let nat_sent_pacs_n = nat_sent_pacs.len();
             assert!(nat_sent_pacs_n > 2);
@@ -26,6 +40,11 @@ fn main() {
                 "NAT has sent two packets with this same y({}) to address {} in a row.",
                 nat_last_sent_y, nat_send_addr
             );
+            println!(
+                "Network became idle {} times, NAT woke it up {} times.",
+                hub.trace().idle_rounds().len(),
+                hub.trace().nat_wakeups().count()
+            );
         }
         Err(e) => {
             eprintln!("Failed to run the whole network to end, get error({}).", e);
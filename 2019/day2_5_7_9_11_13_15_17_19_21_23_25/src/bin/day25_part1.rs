@@ -1,5 +1,5 @@
 use day2_5_7_9_11_13_15_17_19_21_23_25::{
-    day25::{self, DroidConsole},
+    day25::{self, ConsoleMode, DroidConsole},
     int_code::{
         self,
         com::{ProcessState, SeqIntCodeComputer},
@@ -8,8 +8,13 @@ use day2_5_7_9_11_13_15_17_19_21_23_25::{
 };
 
 fn main() {
-    let input_path = day25::check_args()
-        .inspect_err(|e| eprintln!("Failed to read given input path, get error({}).", e))
+    let (input_path, mode) = day25::parse_args()
+        .inspect_err(|e| {
+            eprintln!(
+                "Failed to read given arguments, get error({}). Usage: day25_part1 {{input path}} [--script {{script path}} | --auto]",
+                e
+            )
+        })
         .unwrap();
     let intcode = int_code::read_int_code(&input_path)
         .inspect_err(|e| {
@@ -20,7 +25,26 @@ fn main() {
         })
         .unwrap();
 
-    let io_dev = SeqIODevice::new(DroidConsole::new());
+    let console = match mode {
+        ConsoleMode::Interactive => DroidConsole::new(),
+        ConsoleMode::Script(script_path) => DroidConsole::load_script(&script_path)
+            .inspect_err(|e| {
+                eprintln!(
+                    "Failed to load script from file({}), get error({}).",
+                    script_path, e
+                )
+            })
+            .unwrap(),
+        ConsoleMode::Auto => {
+            match day25::explore(&intcode) {
+                Ok(report) => println!("{}", report),
+                Err(e) => eprintln!("Failed to explore the ship, get error({}).", e),
+            }
+            return;
+        }
+    };
+
+    let io_dev = SeqIODevice::new(console);
     let mut computer = SeqIntCodeComputer::new(false);
     match computer.execute_with_io(&intcode, io_dev.input_device(), io_dev.output_device()) {
         Ok(res) => {
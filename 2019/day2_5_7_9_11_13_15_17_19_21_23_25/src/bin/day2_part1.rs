@@ -17,7 +17,7 @@ fn main() {
         Ok(res) => println!(
             "After {} steps, program halt, code[0] = {}",
             res.step_count(),
-            res.image()[0]
+            res.mem_at(0)
         ),
         Err(e) => eprintln!("Failed to run int code, get error({})", e),
     }
@@ -23,7 +23,7 @@ fn main() {
                     code1,
                     code2,
                     res.step_count(),
-                    res.image()[0]
+                    res.mem_at(0)
                 ),
                 Err(e) => println!("Failed to run int code, get error({})", e),
             }
@@ -0,0 +1,109 @@
+use std::{env, fs, process::ExitCode};
+
+use day2_5_7_9_11_13_15_17_19_21_23_25::int_code::{
+    asm::{assemble, disassemble},
+    read_int_code, to_code_str, write_int_code,
+};
+
+fn print_usage() {
+    eprintln!("Usage: intcode_asm dis {{image path}}");
+    eprintln!("       intcode_asm asm {{source path}} [{{output image path}}]");
+    eprintln!("       intcode_asm roundtrip {{image path}}...");
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.len() < 2 {
+        print_usage();
+        return ExitCode::FAILURE;
+    }
+
+    match args[0].as_str() {
+        "dis" => match read_int_code(&args[1]) {
+            Ok(image) => print!("{}", disassemble(&image)),
+            Err(e) => {
+                eprintln!(
+                    "Failed to read image from file({}), get error({}).",
+                    args[1], e
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+        "asm" => {
+            let image = match fs::read_to_string(&args[1]) {
+                Ok(source) => match assemble(&source) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        eprintln!("Failed to assemble file({}), get error({}).", args[1], e);
+                        return ExitCode::FAILURE;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Failed to read source file({}), get error({}).", args[1], e);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            match args.get(2) {
+                Some(output_path) => {
+                    if let Err(e) = write_int_code(output_path, &image) {
+                        eprintln!(
+                            "Failed to write image to file({}), get error({}).",
+                            output_path, e
+                        );
+                        return ExitCode::FAILURE;
+                    }
+                }
+                None => println!("{}", to_code_str(&image)),
+            }
+        }
+        "roundtrip" => {
+            let mut is_all_same = true;
+            for path in &args[1..] {
+                let image = match read_int_code(path) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        eprintln!(
+                            "Failed to read image from file({}), get error({}).",
+                            path, e
+                        );
+                        is_all_same = false;
+                        continue;
+                    }
+                };
+                let listing = disassemble(&image);
+                match assemble(&listing.to_string()) {
+                    Ok(asm_image) if asm_image == image => println!(
+                        "{}: {} value(s) in {} statement(s), round trip matches.",
+                        path,
+                        image.len(),
+                        listing.stmts().len()
+                    ),
+                    Ok(asm_image) => {
+                        let diff_ind = image
+                            .iter()
+                            .zip(asm_image.iter())
+                            .position(|(a, b)| a != b)
+                            .unwrap_or(image.len().min(asm_image.len()));
+                        println!("{}: round trip mismatches from address {}.", path, diff_ind);
+                        is_all_same = false;
+                    }
+                    Err(e) => {
+                        println!("{}: failed to assemble listing, get error({}).", path, e);
+                        is_all_same = false;
+                    }
+                }
+            }
+
+            if !is_all_same {
+                return ExitCode::FAILURE;
+            }
+        }
+        _ => {
+            print_usage();
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
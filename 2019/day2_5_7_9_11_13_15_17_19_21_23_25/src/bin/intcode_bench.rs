@@ -0,0 +1,162 @@
+use std::time::{Duration, Instant};
+
+use day2_5_7_9_11_13_15_17_19_21_23_25::{
+    day23,
+    day7::{amp_loop, AmpSettings},
+    int_code::{
+        com::{ExecutionEngine, SeqIntCodeComputer},
+        io::{Channel, SeqIODevice, SeqInputDevice, SeqOutputDevice},
+        read_int_code,
+    },
+    Error,
+};
+
+type Workload = fn(&mut SeqIntCodeComputer, &[i64]) -> Result<i64, String>;
+
+fn run_with_inputs(
+    computer: &mut SeqIntCodeComputer,
+    image: &[i64],
+    inputs: &[i64],
+) -> Result<(Vec<i64>, Vec<i64>), Error> {
+    let input_dev = SeqInputDevice::new(Channel::new(inputs));
+    let output_dev = SeqOutputDevice::new(Channel::new(&[]));
+    computer
+        .execute_with_io(image, input_dev, output_dev.clone())
+        .and_then(|res| {
+            Ok((
+                res.image()?.into_owned(),
+                output_dev.check(|c| c.data().iter().copied().collect()),
+            ))
+        })
+}
+
+// Search noun and verb of gravity assist program.
+fn day2(computer: &mut SeqIntCodeComputer, image: &[i64]) -> Result<i64, String> {
+    let mut checksum = 0;
+    for noun in 0..100 {
+        for verb in 0..100 {
+            let mut image = Vec::from(image);
+            image[1] = noun;
+            image[2] = verb;
+            let (image, _) = run_with_inputs(computer, &image, &[]).map_err(|e| e.to_string())?;
+            checksum ^= image[0];
+        }
+    }
+
+    Ok(checksum)
+}
+
+// Diagnostic program with thermal radiator controller ID.
+fn day5(computer: &mut SeqIntCodeComputer, image: &[i64]) -> Result<i64, String> {
+    run_with_inputs(computer, image, &[5])
+        .map_err(|e| e.to_string())
+        .and_then(|(_, outputs)| outputs.last().copied().ok_or("empty output".to_string()))
+}
+
+// Amplifier loops with all settings.
+fn day7(computer: &mut SeqIntCodeComputer, image: &[i64]) -> Result<i64, String> {
+    let settings = AmpSettings::from((5..10).collect::<Vec<_>>().as_slice());
+    let mut max_signal = i64::MIN;
+    for setting in settings.iter() {
+        max_signal = max_signal.max(amp_loop(computer, image, setting).map_err(|e| e.to_string())?);
+    }
+
+    Ok(max_signal)
+}
+
+// Boost program in sensor boost mode.
+fn day9(computer: &mut SeqIntCodeComputer, image: &[i64]) -> Result<i64, String> {
+    run_with_inputs(computer, image, &[2])
+        .map_err(|e| e.to_string())
+        .and_then(|(_, outputs)| outputs.last().copied().ok_or("empty output".to_string()))
+}
+
+// Beam scan of the 50 x 50 area, each point is probed by one run.
+fn day19(computer: &mut SeqIntCodeComputer, image: &[i64]) -> Result<i64, String> {
+    let io_dev = SeqIODevice::new(Channel::new(&[]));
+    let mut pulled_n = 0;
+    for y in 0..50 {
+        for x in 0..50 {
+            io_dev.tweak(|c| c.data_mut().extend([x, y]));
+            computer
+                .execute_with_io(image, io_dev.input_device(), io_dev.output_device())
+                .map_err(|e| e.to_string())?;
+            pulled_n += io_dev.tweak(|c| c.data_mut().pop_front().unwrap_or(0));
+        }
+    }
+
+    Ok(pulled_n)
+}
+
+// Network of 50 computers, until the first packet sent to address 255.
+fn day23(computer: &mut SeqIntCodeComputer, image: &[i64]) -> Result<i64, String> {
+    day23::run_network_on(computer, 50, image)
+        .map_err(|e| e.to_string())
+        .and_then(|hub| {
+            hub.recv_pac_log(255, 0)
+                .map(|p| p.y())
+                .ok_or("no packet to 255".to_string())
+        })
+}
+
+fn main() {
+    let workloads: [(&str, &str, Workload); 6] = [
+        ("day2", "day2_inputs.txt", day2),
+        ("day5", "day5_inputs.txt", day5),
+        ("day7", "day7_inputs.txt", day7),
+        ("day9", "day9_inputs.txt", day9),
+        ("day19", "day19_inputs.txt", day19),
+        ("day23", "day23_inputs.txt", day23),
+    ];
+    let engines = [
+        ExecutionEngine::Boxed,
+        ExecutionEngine::Decoded,
+        ExecutionEngine::Cached,
+    ];
+
+    let mut table = Vec::new();
+    for (name, input_path, workload) in workloads {
+        let image = match read_int_code(input_path) {
+            Ok(image) => image,
+            Err(e) => {
+                eprintln!(
+                    "Failed to read int code from file({}), get error({}).",
+                    input_path, e
+                );
+                continue;
+            }
+        };
+
+        let mut row = Vec::new();
+        for engine in engines {
+            let mut computer = SeqIntCodeComputer::new(false);
+            computer.engine = engine;
+            let start = Instant::now();
+            let res = workload(&mut computer, &image);
+            row.push((res, start.elapsed()));
+        }
+
+        if row.iter().any(|(res, _)| res != &row[0].0) {
+            eprintln!("Engines get different results in {}: {:?}", name, row);
+        }
+        table.push((name, row));
+    }
+
+    let speedup = |base: Duration, d: Duration| base.as_secs_f64() / d.as_secs_f64().max(1e-9);
+    println!(
+        "{:<8}{:>14}{:>14}{:>10}{:>14}{:>10}",
+        "workload", "boxed", "decoded", "speedup", "cached", "speedup"
+    );
+    for (name, row) in table {
+        let base = row[0].1;
+        println!(
+            "{:<8}{:>12.1}ms{:>12.1}ms{:>9.2}x{:>12.1}ms{:>9.2}x",
+            name,
+            base.as_secs_f64() * 1000.0,
+            row[1].1.as_secs_f64() * 1000.0,
+            speedup(base, row[1].1),
+            row[2].1.as_secs_f64() * 1000.0,
+            speedup(base, row[2].1)
+        );
+    }
+}
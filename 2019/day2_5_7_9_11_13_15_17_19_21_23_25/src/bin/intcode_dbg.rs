@@ -0,0 +1,269 @@
+use std::{
+    env,
+    io::{stdin, stdout, Write},
+    process::ExitCode,
+};
+
+use day2_5_7_9_11_13_15_17_19_21_23_25::int_code::{
+    asm::disassemble_mem_at,
+    com::{ProcessSnapshot, SeqIntCodeComputer},
+    dbg::StopReason,
+    io::{Channel, SeqInputDevice, SeqOutputDevice},
+    read_int_code,
+};
+
+fn print_usage() {
+    println!("Commands:");
+    println!("  s [n]          execute n(default 1) instruction(s)");
+    println!("  c              continue until breakpoint, watchpoint, halt or block");
+    println!("  b [addr]       add breakpoint at addr, or list breakpoints");
+    println!("  db addr        delete breakpoint at addr");
+    println!("  w [addr]       add watchpoint at addr, or list watchpoints");
+    println!("  dw addr        delete watchpoint at addr");
+    println!("  r              show registers");
+    println!("  x addr [n]     examine n(default 1) memory cell(s) from addr");
+    println!("  set addr value write value into memory at addr");
+    println!(
+        "  l [addr] [n]   list n(default 8) statement(s) from addr(default current instruction)"
+    );
+    println!("  i value...     append integer(s) to input");
+    println!("  ia [text]      append ASCII text and a newline to input");
+    println!("  o              print and clear output");
+    println!("  save path      save snapshot of process to file");
+    println!("  load path      replace process with snapshot loaded from file");
+    println!("  h              show this help");
+    println!("  q              quit");
+}
+
+fn parse_arg<T: std::str::FromStr>(args: &[&str], ind: usize, default: Option<T>) -> Option<T> {
+    match args.get(ind) {
+        Some(s) => s.parse::<T>().ok(),
+        None => default,
+    }
+}
+
+fn main() -> ExitCode {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() != 2 {
+        eprintln!("Usage: intcode_dbg {{image path}}");
+        return ExitCode::FAILURE;
+    }
+    let image = match read_int_code(&args[1]) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!(
+                "Failed to read image from file({}), get error({}).",
+                args[1], e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut computer = SeqIntCodeComputer::new(false);
+    let input_dev = SeqInputDevice::new(Channel::new(&[]));
+    let output_dev = SeqOutputDevice::new(Channel::new(&[]));
+    let mut proc_id = computer.new_proc(&image, input_dev.clone(), output_dev.clone());
+    let print_cur_inst = |computer: &SeqIntCodeComputer, proc_id: usize| {
+        let inst_p = computer.proc_inst_p(proc_id).unwrap();
+        match disassemble_mem_at(computer.proc_mem(proc_id).unwrap(), inst_p) {
+            Some(stmt) => println!("=> {}", stmt),
+            None => println!("=> {:>5}: <out of memory>", inst_p),
+        }
+    };
+
+    print_cur_inst(&computer, proc_id);
+    let mut buf = String::new();
+    loop {
+        buf.clear();
+        print!("DBG> ");
+        stdout().flush().unwrap();
+        match stdin().read_line(&mut buf) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("Failed to read command from console, get error({}).", e);
+                return ExitCode::FAILURE;
+            }
+        }
+
+        let line = buf.trim();
+        let (com, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let com_args = rest.split_whitespace().collect::<Vec<_>>();
+        match com {
+            "" => continue,
+            "s" | "c" => {
+                let res = if com == "c" {
+                    computer.cont(proc_id)
+                } else if let Some(step_n) = parse_arg(&com_args, 0, Some(1usize)) {
+                    let mut res = Ok(StopReason::StepLimit);
+                    for _ in 0..step_n {
+                        res = computer.step(proc_id);
+                        if !matches!(res, Ok(StopReason::StepLimit)) {
+                            break;
+                        }
+                    }
+                    res
+                } else {
+                    print_usage();
+                    continue;
+                };
+
+                match res {
+                    Ok(reason) => println!("Process {}.", reason),
+                    Err(e) => println!("Process failed, get error({}).", e),
+                }
+                output_dev.check(|c| {
+                    if !c.data().is_empty() {
+                        println!("{} value(s) in output.", c.data().len());
+                    }
+                });
+                print_cur_inst(&computer, proc_id);
+            }
+            "b" | "w" => match parse_arg::<usize>(&com_args, 0, None) {
+                Some(addr) => {
+                    let debugger = computer.debugger_mut();
+                    if com == "b" {
+                        debugger.add_breakpoint(addr);
+                    } else {
+                        debugger.add_watchpoint(addr);
+                    }
+                }
+                None if com_args.is_empty() => {
+                    let debugger = computer.debugger();
+                    let addrs = if com == "b" {
+                        debugger.breakpoints().collect::<Vec<_>>()
+                    } else {
+                        debugger.watchpoints().collect::<Vec<_>>()
+                    };
+                    println!("{:?}", addrs);
+                }
+                None => print_usage(),
+            },
+            "db" | "dw" => match parse_arg::<usize>(&com_args, 0, None) {
+                Some(addr) => {
+                    let debugger = computer.debugger_mut();
+                    let is_removed = if com == "db" {
+                        debugger.remove_breakpoint(addr)
+                    } else {
+                        debugger.remove_watchpoint(addr)
+                    };
+                    if !is_removed {
+                        println!("Nothing to delete at {}.", addr);
+                    }
+                }
+                None => print_usage(),
+            },
+            "r" => println!(
+                "ip = {}, rb = {}, steps = {}, state = {:?}",
+                computer.proc_inst_p(proc_id).unwrap(),
+                computer.proc_rel_base(proc_id).unwrap(),
+                computer.proc_step_count(proc_id).unwrap(),
+                computer.proc_state(proc_id).unwrap()
+            ),
+            "x" => match (
+                parse_arg::<usize>(&com_args, 0, None),
+                parse_arg(&com_args, 1, Some(1usize)),
+            ) {
+                (Some(addr), Some(n)) => {
+                    let mem = computer.proc_mem(proc_id).unwrap();
+                    for ind in addr..(addr + n) {
+                        println!("{:>5}: {}", ind, mem.get(ind));
+                    }
+                }
+                _ => print_usage(),
+            },
+            "set" => match (
+                parse_arg::<usize>(&com_args, 0, None),
+                parse_arg::<i64>(&com_args, 1, None),
+            ) {
+                (Some(addr), Some(value)) => {
+                    if let Err(e) = computer.set_proc_mem(proc_id, addr, value) {
+                        println!("Failed to write memory, get error({}).", e);
+                    }
+                }
+                _ => print_usage(),
+            },
+            "l" => {
+                let inst_p = computer.proc_inst_p(proc_id).unwrap();
+                match (
+                    parse_arg(&com_args, 0, Some(inst_p)),
+                    parse_arg(&com_args, 1, Some(8usize)),
+                ) {
+                    (Some(mut addr), Some(n)) => {
+                        let mem = computer.proc_mem(proc_id).unwrap();
+                        for _ in 0..n {
+                            match disassemble_mem_at(mem, addr) {
+                                Some(stmt) => {
+                                    let mark = if addr == inst_p { "=>" } else { "  " };
+                                    println!("{} {}", mark, stmt);
+                                    addr += stmt.length();
+                                }
+                                None => break,
+                            }
+                        }
+                    }
+                    _ => print_usage(),
+                }
+            }
+            "i" => match com_args
+                .iter()
+                .map(|s| s.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(values) => input_dev.tweak(|c| c.data_mut().extend(values)),
+                Err(_) => print_usage(),
+            },
+            "ia" => input_dev.tweak(|c| {
+                c.data_mut()
+                    .extend(rest.chars().chain(['\n']).map(|c| c as i64))
+            }),
+            "o" => output_dev.tweak(|c| {
+                let values = c.data_mut().drain(..).collect::<Vec<_>>();
+                if values.iter().all(|v| (0..128).contains(v)) {
+                    print!(
+                        "{}",
+                        values.iter().map(|&v| v as u8 as char).collect::<String>()
+                    );
+                    if values.last().is_some_and(|&v| v != '\n' as i64) {
+                        println!();
+                    }
+                } else {
+                    println!("{:?}", values);
+                }
+            }),
+            "save" if !rest.is_empty() => {
+                match computer.snapshot_proc(proc_id).unwrap().save(rest) {
+                    Ok(_) => println!("Snapshot saved to {}.", rest),
+                    Err(e) => println!("Failed to save snapshot, get error({}).", e),
+                }
+            }
+            "load" if !rest.is_empty() => match ProcessSnapshot::load(rest) {
+                Ok(snapshot) => {
+                    computer.take_proc_result(proc_id);
+                    input_dev.tweak(|c| {
+                        *c.data_mut() = snapshot.pending_input().iter().copied().collect()
+                    });
+                    output_dev.tweak(|c| {
+                        *c.data_mut() = snapshot.pending_output().iter().copied().collect()
+                    });
+                    match computer.restore_proc(&snapshot, input_dev.clone(), output_dev.clone()) {
+                        Ok(id) => {
+                            proc_id = id;
+                            print_cur_inst(&computer, proc_id);
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to restore snapshot, get error({}).", e);
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                Err(e) => println!("Failed to load snapshot, get error({}).", e),
+            },
+            "h" => print_usage(),
+            "q" => break,
+            _ => print_usage(),
+        }
+    }
+
+    ExitCode::SUCCESS
+}
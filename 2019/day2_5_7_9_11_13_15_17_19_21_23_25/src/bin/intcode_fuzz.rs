@@ -0,0 +1,69 @@
+use std::{collections::BTreeMap, env, process};
+
+use day2_5_7_9_11_13_15_17_19_21_23_25::int_code::fuzz::{
+    CaseKind, DiffTester, ImageGenerator, Target,
+};
+
+// Run random images on every computer, engine and memory model, and report cases with different outcomes.
+fn main() {
+    // Usage: intcode_fuzz [--seed {n}] [--cases {n}] [--steps {n}]
+    let args = env::args().collect::<Vec<_>>();
+    let arg_value = |name: &str, default: u64| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .map_or(default, |v| {
+                v.parse()
+                    .unwrap_or_else(|_| panic!("Invalid value({}) of {}", v, name))
+            })
+    };
+    let seed = arg_value("--seed", 2019);
+    let case_n = arg_value("--cases", 1000) as usize;
+    let step_limit = arg_value("--steps", 1000) as usize;
+
+    let mut generator = ImageGenerator::new(seed);
+    let tester = DiffTester::new(Target::all(), step_limit);
+    let mut histogram = BTreeMap::new();
+    let mut divergences = Vec::new();
+    for _ in 0..case_n {
+        let case = generator.next_case();
+        match tester.check(&case) {
+            Ok(outcome) => {
+                *histogram
+                    .entry((
+                        format!("{:?}", case.kind()),
+                        outcome.end().name().to_string(),
+                    ))
+                    .or_insert(0) += 1
+            }
+            Err(divergence) => divergences.push(divergence),
+        }
+    }
+
+    println!(
+        "Ran {} case(s) with seed {} on {} target(s), at most {} steps each.",
+        case_n,
+        seed,
+        Target::all().len(),
+        step_limit
+    );
+    for kind in CaseKind::all() {
+        let kind = format!("{:?}", kind);
+        let ends = histogram
+            .iter()
+            .filter(|((k, _), _)| *k == kind)
+            .map(|((_, end), n)| format!("{} {}", end, n))
+            .collect::<Vec<_>>();
+        println!("{:<16}{}", kind, ends.join(", "));
+    }
+
+    if divergences.is_empty() {
+        println!("All targets agree on every case.");
+    } else {
+        println!("{} case(s) diverge:", divergences.len());
+        for divergence in divergences.iter().take(5) {
+            println!("{}", divergence);
+        }
+        process::exit(1);
+    }
+}
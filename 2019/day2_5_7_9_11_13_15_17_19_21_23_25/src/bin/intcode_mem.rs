@@ -0,0 +1,72 @@
+use std::{env, process::ExitCode, time::Instant};
+
+use day2_5_7_9_11_13_15_17_19_21_23_25::int_code::{
+    com::SeqIntCodeComputer,
+    io::{Channel, SeqInputDevice, SeqOutputDevice},
+    mem::MemoryModel,
+    read_int_code,
+};
+
+fn parse_model(s: &str) -> Option<MemoryModel> {
+    match s.split_once(':') {
+        None if s == "dense" => Some(MemoryModel::Dense(None)),
+        None if s == "sparse" => Some(MemoryModel::Sparse),
+        Some(("dense", cap)) => cap.parse().ok().map(|cap| MemoryModel::Dense(Some(cap))),
+        _ => None,
+    }
+}
+
+// Run image with given memory model.
+fn main() -> ExitCode {
+    let args = env::args().collect::<Vec<_>>();
+    let model = args.get(2).and_then(|s| parse_model(s));
+    let inputs = args
+        .iter()
+        .skip(3)
+        .map(|s| s.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>();
+    let (model, inputs) = match (model, inputs) {
+        (Some(model), Ok(inputs)) => (model, inputs),
+        _ => {
+            eprintln!(
+                "Usage: intcode_mem {{image path}} {{dense[:cap] | sparse}} [input value...]"
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let image = match read_int_code(&args[1]) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!(
+                "Failed to read image from file({}), get error({}).",
+                args[1], e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut computer = SeqIntCodeComputer::new(false);
+    computer.memory_model = model;
+    let output_dev = SeqOutputDevice::new(Channel::new(&[]));
+    let start = Instant::now();
+    match computer.execute_with_io(
+        &image,
+        SeqInputDevice::new(Channel::new(&inputs)),
+        output_dev.clone(),
+    ) {
+        Ok(res) => {
+            println!(
+                "Process stopped with state({:?}) after {} step(s) in {:.1}ms, output: {:?}.",
+                res.state(),
+                res.step_count(),
+                start.elapsed().as_secs_f64() * 1000.0,
+                output_dev.check(|c| c.data().clone())
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to execute image, get error({}).", e);
+            ExitCode::FAILURE
+        }
+    }
+}
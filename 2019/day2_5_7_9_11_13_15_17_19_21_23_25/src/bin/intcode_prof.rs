@@ -0,0 +1,63 @@
+use std::{env, process::ExitCode};
+
+use day2_5_7_9_11_13_15_17_19_21_23_25::int_code::{
+    com::SeqIntCodeComputer,
+    io::{Channel, SeqInputDevice, SeqOutputDevice},
+    read_int_code,
+};
+
+fn main() -> ExitCode {
+    let args = env::args().collect::<Vec<_>>();
+    let (is_json, rest_args) = match args.get(1).map(|s| s.as_str()) {
+        Some("--json") => (true, &args[2..]),
+        _ => (false, &args[1..]),
+    };
+    if rest_args.is_empty() {
+        eprintln!("Usage: intcode_prof [--json] {{image path}} [input value...]");
+        return ExitCode::FAILURE;
+    }
+
+    let image = match read_int_code(&rest_args[0]) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!(
+                "Failed to read image from file({}), get error({}).",
+                rest_args[0], e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let inputs = match rest_args[1..]
+        .iter()
+        .map(|s| s.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("Failed to parse input values, get error({}).", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut computer = SeqIntCodeComputer::new(false);
+    computer.enable_profiler();
+    let output_dev = SeqOutputDevice::new(Channel::new(&[]));
+    if let Err(e) = computer.execute_with_io(
+        &image,
+        SeqInputDevice::new(Channel::new(&inputs)),
+        output_dev.clone(),
+    ) {
+        eprintln!("Failed to execute image, get error({}).", e);
+        return ExitCode::FAILURE;
+    }
+
+    let profiler = computer.disable_profiler().unwrap();
+    if is_json {
+        println!("{}", profiler.json_report(&image));
+    } else {
+        println!("Output: {:?}", output_dev.check(|c| c.data().clone()));
+        print!("{}", profiler.text_report(&image));
+    }
+
+    ExitCode::SUCCESS
+}
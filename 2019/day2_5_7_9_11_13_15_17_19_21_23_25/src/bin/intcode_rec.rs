@@ -0,0 +1,126 @@
+use std::{env, process::ExitCode};
+
+use day2_5_7_9_11_13_15_17_19_21_23_25::{
+    day25::DroidConsole,
+    int_code::{
+        com::SeqIntCodeComputer,
+        io::{Channel, IOPort, PortPair, SeqIODevice},
+        read_int_code,
+        rec::{Recorder, Replayer, Transcript},
+    },
+};
+
+fn print_usage() {
+    eprintln!("Usage:");
+    eprintln!("  intcode_rec record {{image path}} {{transcript path}} [input value...]");
+    eprintln!("  intcode_rec console {{image path}} {{transcript path}}");
+    eprintln!("  intcode_rec replay {{image path}} {{transcript path}}");
+}
+
+fn record<P: IOPort + 'static>(image: &[i64], port: P, transcript_path: &str) -> ExitCode {
+    let io_dev = SeqIODevice::new(Recorder::new(port));
+    let mut computer = SeqIntCodeComputer::new(false);
+    let res = computer.execute_with_io(image, io_dev.input_device(), io_dev.output_device());
+    let transcript = io_dev.check(|r| r.transcript().clone());
+    match res {
+        Ok(res) => println!(
+            "Process stopped with state({:?}) after {} step(s), {} event(s) recorded.",
+            res.state(),
+            res.step_count(),
+            transcript.events().len()
+        ),
+        Err(e) => eprintln!("Process failed, get error({}).", e),
+    }
+
+    match transcript.save(transcript_path) {
+        Ok(_) => {
+            println!("Transcript saved to {}.", transcript_path);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to save transcript, get error({}).", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn replay(image: &[i64], transcript_path: &str) -> ExitCode {
+    let transcript = match Transcript::load(transcript_path) {
+        Ok(transcript) => transcript,
+        Err(e) => {
+            eprintln!(
+                "Failed to load transcript from file({}), get error({}).",
+                transcript_path, e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let event_n = transcript.events().len();
+    let io_dev = SeqIODevice::new(Replayer::new(transcript));
+    let mut computer = SeqIntCodeComputer::new(false);
+    let res = computer.execute_with_io(image, io_dev.input_device(), io_dev.output_device());
+    match res.map_err(|e| e.to_string()).and_then(|res| {
+        io_dev
+            .check(|r| r.verify())
+            .map(|_| res)
+            .map_err(|e| e.to_string())
+    }) {
+        Ok(res) => {
+            println!(
+                "Replayed {} event(s) without divergence, process stopped with state({:?}) after {} step(s).",
+                event_n,
+                res.state(),
+                res.step_count()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to replay transcript, get error({}).", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() < 4 {
+        print_usage();
+        return ExitCode::FAILURE;
+    }
+
+    let image = match read_int_code(&args[2]) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!(
+                "Failed to read image from file({}), get error({}).",
+                args[2], e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match args[1].as_str() {
+        "record" => match args[4..]
+            .iter()
+            .map(|s| s.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(inputs) => record(
+                &image,
+                PortPair::new(Channel::new(&inputs), Channel::new(&[])),
+                &args[3],
+            ),
+            Err(e) => {
+                eprintln!("Failed to parse input values, get error({}).", e);
+                ExitCode::FAILURE
+            }
+        },
+        "console" if args.len() == 4 => record(&image, DroidConsole::new(), &args[3]),
+        "replay" if args.len() == 4 => replay(&image, &args[3]),
+        _ => {
+            print_usage();
+            ExitCode::FAILURE
+        }
+    }
+}
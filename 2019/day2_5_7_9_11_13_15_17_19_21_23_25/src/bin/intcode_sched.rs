@@ -0,0 +1,74 @@
+use std::time::Instant;
+
+use day2_5_7_9_11_13_15_17_19_21_23_25::{
+    day23,
+    int_code::{com::SeqIntCodeComputer, read_int_code, sched::SchedulePolicy},
+};
+
+// Run day 23 network under each schedule policy, and compare their results and fairness.
+fn main() {
+    let input_path = "day23_inputs.txt";
+    let intcode = match read_int_code(input_path) {
+        Ok(ic) => ic,
+        Err(e) => {
+            eprintln!(
+                "Failed to read int code from file({}), get error({})",
+                input_path, e
+            );
+            return;
+        }
+    };
+
+    let host_n = 50;
+    let (nat_addr, nat_send_addr) = (255, 0);
+    let max_slice_n = 200_000;
+    let policies = [
+        SchedulePolicy::RoundRobin(100),
+        SchedulePolicy::RoundRobin(1),
+        SchedulePolicy::RoundRobin(10000),
+        SchedulePolicy::Priority((0..host_n as u32).collect(), 100),
+        SchedulePolicy::Priority(vec![1; host_n], 100),
+        SchedulePolicy::Random {
+            seed: 2019,
+            quantum: 100,
+        },
+        SchedulePolicy::UntilOutput(1000),
+    ];
+    for policy in policies {
+        let mut computer = SeqIntCodeComputer::new(false);
+        computer.schedule_policy = policy.clone();
+        let start = Instant::now();
+        let res = day23::run_network_sched(
+            &mut computer,
+            host_n,
+            &intcode,
+            Some((nat_addr, nat_send_addr)),
+            max_slice_n,
+        );
+        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+        match res {
+            Ok((hub, stats)) => {
+                let first_y = hub.trace().first_pac_to(nat_addr).map(|p| p.y());
+                let nat_pacs = hub.nat().unwrap().sent_pacs();
+                let repeated_y = match nat_pacs {
+                    [.., p0, p1] if p0.y() == p1.y() => Some(p1.y()),
+                    _ => None,
+                };
+                println!(
+                    "Policy {}: first y to NAT {:?}, repeated y from NAT {:?}, {} time slice(s), {} step(s), fairness {:.3}, {:.1}ms.",
+                    policy,
+                    first_y,
+                    repeated_y,
+                    stats.total_slices(),
+                    stats.total_steps(),
+                    stats.fairness(),
+                    elapsed
+                );
+            }
+            Err(e) => eprintln!(
+                "Policy {}: failed to run network, get error({}).",
+                policy, e
+            ),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use day2_5_7_9_11_13_15_17_19_21_23_25::{
+    day23,
+    int_code::{com::SeqIntCodeComputer, read_int_code},
+};
+
+// Compare the first packet sent out of day 23 network, run by network hub and by topology.
+fn main() {
+    let input_path = "day23_inputs.txt";
+    let network_code = match read_int_code(input_path) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!(
+                "Failed to read int code from file({}), get error({}).",
+                input_path, e
+            );
+            return;
+        }
+    };
+
+    let host_n = 50;
+    let check_addr = 255;
+    let hub_pac = day23::run_network(host_n, &network_code)
+        .map(|hub| hub.recv_pac_log(check_addr, 0).map(|p| p.to_string()))
+        .map_err(|e| e.to_string());
+    let mut computer = SeqIntCodeComputer::new(false);
+    let topo_pac = day23::first_packet_to(&mut computer, host_n, &network_code, check_addr)
+        .map(|op| op.map(|p| p.to_string()))
+        .map_err(|e| e.to_string());
+    println!(
+        "First packet sent to address({}), hub: {:?}, topology: {:?}.",
+        check_addr, hub_pac, topo_pac
+    );
+    if hub_pac == topo_pac {
+        println!("Network hub and topology get the same result.");
+    } else {
+        println!("Network hub and topology get different results.");
+    }
+}
@@ -1,10 +1,12 @@
 use std::{
     cmp,
     collections::HashMap,
-    error,
+    env, error,
     fmt::Display,
+    fs,
     io::{self, Stdout, Write},
     iter,
+    path::Path,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, Mutex,
@@ -23,7 +25,13 @@ use int_enum::IntEnum;
 use once_cell::sync::Lazy;
 use rayon::ThreadPool;
 
-use crate::int_code::io::{InputPort, OutputPort};
+use crate::{
+    int_code::{
+        com::SeqIntCodeComputer,
+        io::{Channel, InputPort, OutputPort, SeqIODevice, SeqInputDevice, SeqOutputDevice},
+    },
+    ppm,
+};
 
 #[derive(Debug)]
 pub enum Error {
@@ -33,6 +41,8 @@ pub enum Error {
     InvalidScore(i64),
     TerminalError(io::Error),
     NotEnoughTerminalSpace(u32, u32, u32, u32),
+    InvalidArgs(String),
+    ExecutionError(crate::Error),
 }
 
 impl Display for Error {
@@ -44,6 +54,8 @@ impl Display for Error {
             Error::InvalidScore(s) => write!(f, "Given invalid game score({})", s),
             Error::TerminalError(ioe) => write!(f, "Failed to control terminal(stdout), get error({})", ioe),
             Error::NotEnoughTerminalSpace(real_row_n, real_col_n, expect_row_n, expect_col_n) => write!(f, "Terminal({} x {}) hasn't enough space to render, expect {} rows x {} columns", real_row_n, real_col_n, expect_row_n, expect_col_n),
+            Error::InvalidArgs(s) => write!(f, "Invalid arguments({})", s),
+            Error::ExecutionError(ee) => write!(f, "Get error({}) in execution of arcade program", ee),
         }
     }
 }
@@ -160,6 +172,7 @@ pub struct Screen {
     score: u32,
     term: Stdout,
     state: ScreenState,
+    is_headless: bool,
 }
 
 impl Screen {
@@ -195,6 +208,7 @@ impl Screen {
             score: 0,
             term: io::stdout(),
             state: ScreenState::Rendering,
+            is_headless: false,
         })
     }
 
@@ -242,7 +256,7 @@ impl Screen {
     }
 
     fn render(&mut self) -> io::Result<()> {
-        if self.state != ScreenState::Rendering {
+        if self.is_headless || self.state != ScreenState::Rendering {
             return Ok(());
         }
 
@@ -515,6 +529,395 @@ impl Player for AutoPlayer {
     }
 }
 
+impl<P: Player + ?Sized> Player for Box<P> {
+    fn prepare(&mut self) {
+        (**self).prepare()
+    }
+
+    fn finish(&mut self) {
+        (**self).finish()
+    }
+
+    fn action(&mut self, buffer: &TileBuffer) -> JoystickState {
+        (**self).action(buffer)
+    }
+}
+
+fn move_toward(from_x: u32, to_x: u32) -> JoystickState {
+    JoystickState::from_int((to_x as i64 - from_x as i64).signum() as i8).unwrap()
+}
+
+// Player which always moves paddle toward the current column of ball.
+pub struct GreedyPlayer;
+
+impl Player for GreedyPlayer {
+    fn prepare(&mut self) {}
+
+    fn finish(&mut self) {}
+
+    fn action(&mut self, buffer: &TileBuffer) -> JoystickState {
+        match (
+            buffer.first_pos(TileId::Ball),
+            buffer.first_pos(TileId::HorizontalPaddle),
+        ) {
+            (Some(ball_pos), Some(paddle_pos)) => move_toward(paddle_pos.0, ball_pos.0),
+            _ => JoystickState::Neutral,
+        }
+    }
+}
+
+// Maximum steps of game simulated ahead for one forecast.
+const FORECAST_STEP_LIMIT: usize = 1_000_000;
+
+// Ports of forked game, which keeps joystick neutral until ball reaches the row right above paddle.
+struct Forecast {
+    game_info_buffer: GameInfoBuffer,
+    paddle_y: u32,
+    ball_x: Option<u32>,
+}
+
+impl InputPort for Forecast {
+    fn get(&mut self) -> Option<i64> {
+        // Fork blocks when forecast is done.
+        match self.ball_x {
+            Some(_) => None,
+            None => Some(JoystickState::Neutral.int_value().into()),
+        }
+    }
+
+    fn reg_proc(&mut self, _proc_id: usize) {}
+}
+
+impl OutputPort for Forecast {
+    fn put(&mut self, value: i64) -> Result<(), crate::Error> {
+        if let Some(Ok(Tile { x, y, id })) = self
+            .game_info_buffer
+            .push_and_get(value)
+            .map(|info| Tile::from_info(&info))
+        {
+            if id == TileId::Ball && y + 1 == self.paddle_y && self.ball_x.is_none() {
+                self.ball_x = Some(x);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn wait_proc_id(&self) -> Option<usize> {
+        None
+    }
+}
+
+// Player which runs its own copy of the game in step with the real one, and forks it to simulate ahead where ball
+// will come down to paddle.
+pub struct LookaheadPlayer {
+    image: Vec<i64>,
+    computer: SeqIntCodeComputer,
+    proc_id: Option<usize>,
+    input_dev: SeqInputDevice<Channel>,
+    output_dev: SeqOutputDevice<Channel>,
+    target_x: Option<u32>,
+}
+
+impl LookaheadPlayer {
+    // Given image should be the same one played by cabinet, with coins inserted.
+    pub fn new(image: &[i64]) -> Self {
+        Self {
+            image: Vec::from(image),
+            computer: SeqIntCodeComputer::new(false),
+            proc_id: None,
+            input_dev: SeqInputDevice::new(Channel::new(&[])),
+            output_dev: SeqOutputDevice::new(Channel::new(&[])),
+            target_x: None,
+        }
+    }
+
+    // Run own copy of game until it polls joystick.
+    fn sync(&mut self) {
+        if let Some(proc_id) = self.proc_id {
+            self.computer
+                .run_proc(proc_id, None)
+                .expect("Failed to run game copy of lookahead player");
+            self.output_dev.tweak(|c| c.data_mut().clear());
+        }
+    }
+
+    // Column where ball will be in the row right above paddle.
+    fn forecast(&mut self, paddle_y: u32) -> Option<u32> {
+        let snapshot = self.computer.snapshot_proc(self.proc_id?)?;
+        let io_dev = SeqIODevice::new(Forecast {
+            game_info_buffer: GameInfoBuffer::new(),
+            paddle_y,
+            ball_x: None,
+        });
+        let fork_id = self
+            .computer
+            .restore_proc(&snapshot, io_dev.input_device(), io_dev.output_device())
+            .ok()?;
+        let res = self.computer.run_proc(fork_id, Some(FORECAST_STEP_LIMIT));
+        self.computer.take_proc_result(fork_id);
+
+        res.ok().and_then(|_| io_dev.check(|f| f.ball_x))
+    }
+}
+
+impl Player for LookaheadPlayer {
+    fn prepare(&mut self) {
+        if let Some(proc_id) = self.proc_id.take() {
+            self.computer.take_proc_result(proc_id);
+        }
+        self.input_dev.tweak(|c| c.data_mut().clear());
+        self.output_dev.tweak(|c| c.data_mut().clear());
+        self.proc_id = Some(self.computer.new_proc(
+            &self.image,
+            self.input_dev.clone(),
+            self.output_dev.clone(),
+        ));
+        self.target_x = None;
+        self.sync();
+    }
+
+    fn finish(&mut self) {}
+
+    fn action(&mut self, buffer: &TileBuffer) -> JoystickState {
+        let action = match (
+            buffer.first_pos(TileId::Ball),
+            buffer.first_pos(TileId::HorizontalPaddle),
+        ) {
+            // Ball comes down to paddle now, forecast where it comes down next time.
+            (Some(ball_pos), Some(paddle_pos)) if ball_pos.1 + 1 == paddle_pos.1 => {
+                self.target_x = None;
+                move_toward(paddle_pos.0, ball_pos.0)
+            }
+            (Some(ball_pos), Some(paddle_pos)) => {
+                if self.target_x.is_none() {
+                    self.target_x = self.forecast(paddle_pos.1);
+                }
+                move_toward(paddle_pos.0, self.target_x.unwrap_or(ball_pos.0))
+            }
+            _ => JoystickState::Neutral,
+        };
+
+        self.input_dev
+            .tweak(|c| c.data_mut().push_back(action.int_value().into()));
+        self.sync();
+        action
+    }
+}
+
+// Tile colors in exported images.
+static TILE_COLOR_MAP: Lazy<HashMap<TileId, [u8; 3]>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    m.insert(TileId::Empty, [0, 0, 0]);
+    m.insert(TileId::Wall, [128, 128, 128]);
+    m.insert(TileId::Block, [200, 80, 40]);
+    m.insert(TileId::HorizontalPaddle, [40, 160, 220]);
+    m.insert(TileId::Ball, [240, 240, 240]);
+
+    m
+});
+
+// Screen when game polls joystick, and the action player takes, the last frame(after game ends) has no action.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    buffer: TileBuffer,
+    score: u32,
+    joystick: Option<JoystickState>,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let joystick = self
+            .joystick
+            .map_or(String::from("-"), |j| format!("{:?}", j));
+        writeln!(f, "score: {}, joystick: {}", self.score, joystick)?;
+        let (row_n, col_n) = self.buffer.size();
+        for y in 0..row_n {
+            let line = (0..col_n)
+                .map(|x| TILE_CHAR_MAP[self.buffer.tile(x, y)])
+                .collect::<String>();
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Frame {
+    pub fn buffer(&self) -> &TileBuffer {
+        &self.buffer
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn joystick(&self) -> Option<JoystickState> {
+        self.joystick
+    }
+
+    // Color of every pixel, row by row, each tile is drawn as a square of `scale` pixels.
+    fn pixels(&self, scale: u32) -> impl Iterator<Item = TileId> + '_ {
+        let (row_n, col_n) = self.buffer.size();
+        (0..(row_n * scale)).flat_map(move |py| {
+            (0..(col_n * scale)).map(move |px| *self.buffer.tile(px / scale, py / scale))
+        })
+    }
+}
+
+// All frames of a headless game.
+#[derive(Debug, Default, Clone)]
+pub struct Recording {
+    frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    fn push(&mut self, screen: &Screen, joystick: Option<JoystickState>) {
+        self.frames.push(Frame {
+            buffer: screen.buffer.clone(),
+            score: screen.score,
+            joystick,
+        });
+    }
+
+    // Every `step`-th frame(and the last one) with its index.
+    fn sampled(&self, step: usize) -> impl Iterator<Item = (usize, &Frame)> {
+        let last_ind = self.frames.len().saturating_sub(1);
+        self.frames
+            .iter()
+            .enumerate()
+            .filter(move |(ind, _)| ind % step.max(1) == 0 || *ind == last_ind)
+    }
+
+    // Frames in text one after another, like a flip book.
+    pub fn text_log(&self, step: usize) -> String {
+        self.sampled(step)
+            .map(|(ind, frame)| format!("== Frame {} ==\n{}\n", ind, frame))
+            .collect()
+    }
+
+    pub fn save_text<P: AsRef<Path>>(&self, path: P, step: usize) -> io::Result<()> {
+        fs::write(path, self.text_log(step))
+    }
+
+    // Save frames as binary PPM images "frame_{index}.ppm" in given directory.
+    pub fn save_ppm_sequence<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        scale: u32,
+        step: usize,
+    ) -> io::Result<usize> {
+        fs::create_dir_all(&dir)?;
+        let mut saved_n = 0;
+        for (ind, frame) in self.sampled(step) {
+            let (row_n, col_n) = frame.buffer.size();
+            let data = ppm::encode(
+                (col_n * scale) as usize,
+                (row_n * scale) as usize,
+                frame.pixels(scale).map(|id| TILE_COLOR_MAP[&id]),
+            );
+            fs::write(dir.as_ref().join(format!("frame_{:06}.ppm", ind)), data)?;
+            saved_n += 1;
+        }
+
+        Ok(saved_n)
+    }
+
+    // Save frames as one looping animated GIF, showing each frame for `delay` hundredths of a second.
+    pub fn save_gif<P: AsRef<Path>>(
+        &self,
+        path: P,
+        scale: u32,
+        step: usize,
+        delay: u16,
+    ) -> io::Result<()> {
+        let (row_n, col_n) = self.frames.first().map_or((0, 0), |f| f.buffer.size());
+        // Size of GIF is limited to 16 bits, so it's checked instead of being truncated.
+        let gif_size = |n: u32| {
+            n.checked_mul(scale)
+                .and_then(|n| u16::try_from(n).ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "frame size({} x {}) at scale {} is too large for GIF",
+                            col_n, row_n, scale
+                        ),
+                    )
+                })
+        };
+        let (width, height) = (gif_size(col_n)?, gif_size(row_n)?);
+        let mut data = Vec::from(*b"GIF89a");
+        data.extend(width.to_le_bytes());
+        data.extend(height.to_le_bytes());
+        // Global color table of 8 colors, indexed by tile id.
+        data.extend([0xa2, 0, 0]);
+        for ind in 0..8 {
+            let color = TileId::from_int(ind).map_or([0; 3], |id| TILE_COLOR_MAP[&id]);
+            data.extend(color);
+        }
+        // Loop forever.
+        data.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+        for (_, frame) in self.sampled(step) {
+            data.extend([0x21, 0xf9, 0x04, 0x00]);
+            data.extend(delay.to_le_bytes());
+            data.extend([0x00, 0x00, 0x2c, 0, 0, 0, 0]);
+            data.extend(width.to_le_bytes());
+            data.extend(height.to_le_bytes());
+            data.extend([0x00, GIF_MIN_CODE_SIZE]);
+            let codes = gif_codes(frame.pixels(scale).map(|id| id.int_value()));
+            for block in codes.chunks(255) {
+                data.push(block.len() as u8);
+                data.extend(block);
+            }
+            data.push(0x00);
+        }
+        data.push(0x3b);
+
+        fs::write(path, data)
+    }
+}
+
+const GIF_MIN_CODE_SIZE: u8 = 3;
+
+// LZW data of GIF image without compression, every pixel is sent as a literal code, and dictionary is cleared before
+// it grows enough to widen codes, so all codes keep the same width.
+fn gif_codes<I: Iterator<Item = u8>>(pixels: I) -> Vec<u8> {
+    let clear_code = 1_u16 << GIF_MIN_CODE_SIZE;
+    let code_width = GIF_MIN_CODE_SIZE + 1;
+    // Codes which can be sent after clear code, before the dictionary reaches the next code width.
+    let run_len = (1_usize << code_width) - clear_code as usize - 2;
+    let mut bytes = Vec::new();
+    let (mut acc, mut acc_len) = (0_u32, 0_u8);
+    let mut push_code = |code: u16| {
+        acc |= (code as u32) << acc_len;
+        acc_len += code_width;
+        while acc_len >= 8 {
+            bytes.push(acc as u8);
+            acc >>= 8;
+            acc_len -= 8;
+        }
+    };
+
+    for (ind, pixel) in pixels.enumerate() {
+        if ind % run_len == 0 {
+            push_code(clear_code);
+        }
+        push_code(pixel as u16);
+    }
+    push_code(clear_code + 1);
+    if acc_len > 0 {
+        bytes.push(acc as u8);
+    }
+
+    bytes
+}
+
 struct GameInfoBuffer {
     buffer: GameInfo,
     cur_ind: u8,
@@ -547,7 +950,8 @@ pub struct ArcadeCabinet<P: Player> {
     game_info_buffer: GameInfoBuffer,
     player: P,
     screen: Arc<Mutex<Screen>>,
-    context: Arc<ThreadPool>,
+    context: Option<Arc<ThreadPool>>, // None if headless
+    recording: Option<Recording>,
 }
 
 impl<P: Player> ArcadeCabinet<P> {
@@ -559,12 +963,33 @@ impl<P: Player> ArcadeCabinet<P> {
             game_info_buffer: GameInfoBuffer::new(),
             player,
             screen: Arc::new(Mutex::new(screen)),
-            context,
+            context: Some(context),
+            recording: None,
+        }
+    }
+
+    // Cabinet without terminal, which runs game as fast as possible and records every frame.
+    pub fn headless(mut screen: Screen, player: P) -> Self {
+        screen.is_headless = true;
+        ArcadeCabinet {
+            frame_interval: Duration::ZERO,
+            last_refresh_time: None,
+            game_info_buffer: GameInfoBuffer::new(),
+            player,
+            screen: Arc::new(Mutex::new(screen)),
+            context: None,
+            recording: Some(Recording::default()),
         }
     }
 
+    pub fn is_headless(&self) -> bool {
+        self.context.is_none()
+    }
+
     pub fn start(&mut self) -> Result<(), Error> {
-        Self::configure_term(io::stdout()).map_err(Error::TerminalError)?;
+        if !self.is_headless() {
+            Self::configure_term(io::stdout()).map_err(Error::TerminalError)?;
+        }
         self.player.prepare();
         let mut screen = self.screen.lock().unwrap();
         screen.refresh()?;
@@ -575,10 +1000,20 @@ impl<P: Player> ArcadeCabinet<P> {
         self.player.finish();
         let mut screen = self.screen.lock().unwrap();
         screen.refresh()?;
-        Self::restore_term(io::stdout()).map_err(Error::TerminalError)?;
+        if let Some(recording) = &mut self.recording {
+            recording.push(&screen, None);
+        }
+        if !self.is_headless() {
+            Self::restore_term(io::stdout()).map_err(Error::TerminalError)?;
+        }
         Ok(())
     }
 
+    // Frames recorded in headless mode.
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
+
     pub fn check_screen<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&Screen) -> R,
@@ -620,14 +1055,16 @@ impl<P: Player> ArcadeCabinet<P> {
 
 impl<P: Player> InputPort for ArcadeCabinet<P> {
     fn get(&mut self) -> Option<i64> {
-        let screen = self.screen.clone();
-        self.context.spawn(move || {
-            screen
-                .lock()
-                .unwrap()
-                .refresh()
-                .expect("Failed to refresh screen when poll input");
-        });
+        if let Some(context) = &self.context {
+            let screen = self.screen.clone();
+            context.spawn(move || {
+                screen
+                    .lock()
+                    .unwrap()
+                    .refresh()
+                    .expect("Failed to refresh screen when poll input");
+            });
+        }
 
         if let Some(last_refresh_time) = self.last_refresh_time {
             let now = Instant::now();
@@ -641,8 +1078,11 @@ impl<P: Player> InputPort for ArcadeCabinet<P> {
             self.last_refresh_time = Some(Instant::now());
         }
 
-        let screen = self.screen.clone();
-        let action = self.player.action(&screen.lock().unwrap().buffer);
+        let screen = self.screen.lock().unwrap();
+        let action = self.player.action(&screen.buffer);
+        if let Some(recording) = &mut self.recording {
+            recording.push(&screen, Some(action));
+        }
         Some(action.int_value().into())
     }
 
@@ -659,3 +1099,216 @@ impl<P: Player> OutputPort for ArcadeCabinet<P> {
         None
     }
 }
+
+// Where and how frames of headless game are exported.
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub text_path: Option<String>,
+    pub ppm_dir: Option<String>,
+    pub gif_path: Option<String>,
+    pub step: usize, // export every n-th frame
+    pub scale: u32,  // pixels per tile in images
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            text_path: None,
+            ppm_dir: None,
+            gif_path: None,
+            step: 1,
+            scale: 4,
+        }
+    }
+}
+
+// How game is played.
+pub enum GameMode {
+    Terminal,
+    Headless(HeadlessOptions),
+    Compare, // all built-in automatic players in headless cabinets
+}
+
+// Parse arguments as "[--compare | --headless [--text {path}] [--ppm {dir}] [--gif {path}] [--step {n}] [--scale {n}]]".
+pub fn parse_args() -> Result<GameMode, Error> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let invalid = || Error::InvalidArgs(args.join(" "));
+    match args.first() {
+        None => return Ok(GameMode::Terminal),
+        Some(flag) if flag == "--compare" && args.len() == 1 => return Ok(GameMode::Compare),
+        Some(flag) if flag == "--headless" => (),
+        _ => return Err(invalid()),
+    }
+
+    let mut options = HeadlessOptions::default();
+    for pair in args[1..].chunks(2) {
+        match pair {
+            [flag, value] if flag == "--text" => options.text_path = Some(value.clone()),
+            [flag, value] if flag == "--ppm" => options.ppm_dir = Some(value.clone()),
+            [flag, value] if flag == "--gif" => options.gif_path = Some(value.clone()),
+            [flag, value] if flag == "--step" => {
+                options.step = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?
+            }
+            [flag, value] if flag == "--scale" => {
+                options.scale = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(GameMode::Headless(options))
+}
+
+// Screen drawn by arcade program without coins.
+pub fn init_screen(image: &[i64]) -> Result<Screen, Error> {
+    let output_dev = SeqOutputDevice::new(Channel::new(&[]));
+    SeqIntCodeComputer::new(false)
+        .execute_with_io(
+            image,
+            SeqInputDevice::new(Channel::new(&[])),
+            output_dev.clone(),
+        )
+        .map_err(Error::ExecutionError)?;
+
+    output_dev.check(|c| Screen::from_ints(c.data().iter().copied()))
+}
+
+// Image of arcade program which can be played for free.
+pub fn insert_coins(image: &[i64]) -> Vec<i64> {
+    let mut image = Vec::from(image);
+    image[0] = 2;
+    image
+}
+
+// How one player did in a headless game.
+#[derive(Debug, Clone)]
+pub struct PlayerReport {
+    name: String,
+    score: u32,
+    step_count: usize,
+    frame_n: usize,
+    lives_lost: usize,
+    block_n: usize, // blocks remained
+}
+
+impl Display for PlayerReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<12}{:>8}{:>12}{:>8}{:>12}{:>8}",
+            self.name, self.score, self.step_count, self.frame_n, self.lives_lost, self.block_n
+        )
+    }
+}
+
+impl PlayerReport {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.step_count
+    }
+
+    pub fn frame_n(&self) -> usize {
+        self.frame_n
+    }
+
+    pub fn lives_lost(&self) -> usize {
+        self.lives_lost
+    }
+
+    pub fn block_n(&self) -> usize {
+        self.block_n
+    }
+}
+
+// Times ball got to or below the row of paddle in recorded frames.
+fn count_lives_lost(recording: &Recording) -> usize {
+    let mut lost_n = 0;
+    let mut is_lost = false;
+    for frame in recording.frames() {
+        if let (Some(ball_pos), Some(paddle_pos)) = (
+            frame.buffer.first_pos(TileId::Ball),
+            frame.buffer.first_pos(TileId::HorizontalPaddle),
+        ) {
+            let is_below = ball_pos.1 >= paddle_pos.1;
+            if is_below && !is_lost {
+                lost_n += 1;
+            }
+            is_lost = is_below;
+        }
+    }
+
+    lost_n
+}
+
+// Play given image(without coins) with every player in headless cabinet, reports are in the same order as players.
+pub fn compete(
+    image: &[i64],
+    players: Vec<(String, Box<dyn Player>)>,
+) -> Result<Vec<PlayerReport>, Error> {
+    let coined_image = insert_coins(image);
+    let mut reports = Vec::new();
+    for (name, player) in players {
+        let mut cabinet = ArcadeCabinet::headless(init_screen(image)?, player);
+        cabinet.start()?;
+        let io_dev = SeqIODevice::new(cabinet);
+        let res = SeqIntCodeComputer::new(false)
+            .execute_with_io(&coined_image, io_dev.input_device(), io_dev.output_device())
+            .map_err(Error::ExecutionError)?;
+        let report = io_dev.tweak(|cab| {
+            cab.stop()?;
+            let recording = cab.recording().unwrap();
+            let (score, block_n) = cab.check_screen(|s| (s.score(), s.count_id(TileId::Block)));
+            Ok::<_, Error>(PlayerReport {
+                name,
+                score,
+                step_count: res.step_count(),
+                frame_n: recording.frames().len(),
+                lives_lost: count_lives_lost(recording),
+                block_n,
+            })
+        })?;
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
+
+// Reports in one table, the best score first.
+pub fn report_table(reports: &[PlayerReport]) -> String {
+    let mut reports = reports.iter().collect::<Vec<_>>();
+    reports.sort_by_key(|r| cmp::Reverse(r.score));
+    let mut table = format!(
+        "{:<12}{:>8}{:>12}{:>8}{:>12}{:>8}\n",
+        "player", "score", "steps", "frames", "lives lost", "blocks"
+    );
+    for report in reports {
+        table.push_str(&report.to_string());
+        table.push('\n');
+    }
+
+    table
+}
+
+#[test]
+fn test_save_gif_size_limit() {
+    let screen = Screen::from_ints([0, 0, 1, 3, 1, 2].into_iter()).unwrap();
+    let mut recording = Recording::default();
+    recording.push(&screen, None);
+
+    let path = std::env::temp_dir().join(format!("day13_test_{}.gif", std::process::id()));
+    let err = recording.save_gif(&path, 20000, 1, 10).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(!path.exists());
+
+    recording.save_gif(&path, 2, 1, 10).unwrap();
+    let data = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(&data[6..10], &[8, 0, 4, 0]);
+}
@@ -1,12 +1,17 @@
 use std::{
-    collections::{HashMap, LinkedList},
+    collections::{HashMap, HashSet, LinkedList, VecDeque},
     error,
     fmt::Display,
+    fs, io,
+    path::Path,
 };
 
 use int_enum::IntEnum;
 
-use crate::int_code::io::{InputPort, OutputPort};
+use crate::{
+    int_code::io::{InputPort, OutputPort},
+    ppm,
+};
 
 #[derive(Debug)]
 pub enum Error {
@@ -43,7 +48,7 @@ impl error::Error for Error {}
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Default, IntEnum, PartialEq, Eq)]
-enum TileType {
+pub enum TileType {
     #[default]
     Empty = 1,
     Wall = 0,
@@ -109,6 +114,14 @@ impl Position {
         Self { x, y }
     }
 
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
     pub fn origin() -> Self {
         Self::new(0, 0)
     }
@@ -132,6 +145,244 @@ impl Position {
     }
 }
 
+// Grid covering all observed positions, unobserved positions in it have no tile. Rows are ordered from north to
+// south, so it's drawn as seen from above.
+#[derive(Debug, Clone)]
+pub struct MazeMap {
+    min_x: i32,
+    max_y: i32,
+    width: usize,
+    height: usize,
+    tiles: Vec<Option<TileType>>,
+}
+
+impl Display for MazeMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render_with(|_| None))
+    }
+}
+
+impl MazeMap {
+    pub fn from_tiles<I: IntoIterator<Item = (Position, TileType)>>(tiles: I) -> Self {
+        let tiles = tiles.into_iter().collect::<Vec<_>>();
+        let (min_x, max_x, min_y, max_y) = tiles.iter().fold(
+            (i32::MAX, i32::MIN, i32::MAX, i32::MIN),
+            |(min_x, max_x, min_y, max_y), (pos, _)| {
+                (
+                    min_x.min(pos.x),
+                    max_x.max(pos.x),
+                    min_y.min(pos.y),
+                    max_y.max(pos.y),
+                )
+            },
+        );
+        let (width, height) = if tiles.is_empty() {
+            (0, 0)
+        } else {
+            ((max_x - min_x + 1) as usize, (max_y - min_y + 1) as usize)
+        };
+        let mut map = Self {
+            min_x,
+            max_y,
+            width,
+            height,
+            tiles: vec![None; width * height],
+        };
+        for (pos, tile) in tiles {
+            let ind = map.tile_ind(&pos).unwrap();
+            map.tiles[ind] = Some(tile);
+        }
+
+        map
+    }
+
+    // (width, height)
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    // Position of the north-west corner.
+    pub fn top_left(&self) -> Position {
+        Position::new(self.min_x, self.max_y)
+    }
+
+    fn tile_ind(&self, pos: &Position) -> Option<usize> {
+        let col = usize::try_from(pos.x - self.min_x).ok()?;
+        let row = usize::try_from(self.max_y - pos.y).ok()?;
+        if col < self.width && row < self.height {
+            Some(row * self.width + col)
+        } else {
+            None
+        }
+    }
+
+    fn pos_at(&self, row: usize, col: usize) -> Position {
+        Position::new(self.min_x + col as i32, self.max_y - row as i32)
+    }
+
+    pub fn tile(&self, pos: &Position) -> Option<TileType> {
+        self.tile_ind(pos).and_then(|ind| self.tiles[ind])
+    }
+
+    pub fn is_open(&self, pos: &Position) -> bool {
+        matches!(
+            self.tile(pos),
+            Some(TileType::Empty | TileType::OxygenSystem)
+        )
+    }
+
+    pub fn positions_of(&self, tile: TileType) -> Vec<Position> {
+        (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |col| (row, col)))
+            .filter(|&(row, col)| self.tiles[row * self.width + col] == Some(tile))
+            .map(|(row, col)| self.pos_at(row, col))
+            .collect()
+    }
+
+    pub fn oxygen_sys_pos(&self) -> Option<Position> {
+        self.positions_of(TileType::OxygenSystem).first().copied()
+    }
+
+    // Distance of every open position reachable from given one, with the direction of the last move to it.
+    fn search_from(&self, from: &Position) -> HashMap<Position, (usize, Option<Direction>)> {
+        let mut visited = HashMap::new();
+        if !self.is_open(from) {
+            return visited;
+        }
+
+        let mut queue = VecDeque::from([*from]);
+        visited.insert(*from, (0, None));
+        while let Some(pos) = queue.pop_front() {
+            let dist = visited[&pos].0;
+            for (next_pos, dir) in pos.neighbors_and_dir() {
+                if self.is_open(&next_pos) && !visited.contains_key(&next_pos) {
+                    visited.insert(next_pos, (dist + 1, Some(dir)));
+                    queue.push_back(next_pos);
+                }
+            }
+        }
+
+        visited
+    }
+
+    // Moves along one of the shortest paths between given positions through known open tiles.
+    pub fn shortest_path(&self, from: &Position, to: &Position) -> Option<Vec<Direction>> {
+        let visited = self.search_from(from);
+        visited.get(to)?;
+
+        let mut moves = Vec::new();
+        let mut cur_pos = *to;
+        while let Some(&(_, Some(dir))) = visited.get(&cur_pos) {
+            moves.push(dir);
+            cur_pos = cur_pos.move_along(dir.reverse());
+        }
+        moves.reverse();
+
+        Some(moves)
+    }
+
+    // Oxygen spreading from given position into adjacent open tiles, one step per minute.
+    pub fn flood(&self, from: &Position) -> OxygenFlood {
+        let mut fronts = Vec::<Vec<Position>>::new();
+        for (pos, (dist, _)) in self.search_from(from) {
+            if fronts.len() <= dist {
+                fronts.resize(dist + 1, Vec::new());
+            }
+            fronts[dist].push(pos);
+        }
+        for front in &mut fronts {
+            front.sort_by_key(|p| (-p.y, p.x));
+        }
+
+        OxygenFlood { fronts }
+    }
+
+    // ASCII drawing of map, '#' for wall, '.' for open tile, 'O' for oxygen system, ' ' for unknown, given function
+    // can draw other characters over tiles.
+    pub fn render_with<F: Fn(&Position) -> Option<char>>(&self, overlay: F) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let pos = self.pos_at(row, col);
+                text.push(
+                    overlay(&pos).unwrap_or(match self.tiles[row * self.width + col] {
+                        Some(TileType::Wall) => '#',
+                        Some(TileType::Empty) => '.',
+                        Some(TileType::OxygenSystem) => 'O',
+                        None => ' ',
+                    }),
+                );
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+
+    // Binary PPM image of map, each tile is drawn as a square of `scale` pixels, given function can color tiles.
+    pub fn to_ppm<F: Fn(&Position) -> Option<[u8; 3]>>(&self, scale: usize, overlay: F) -> Vec<u8> {
+        let (width, height) = (self.width * scale, self.height * scale);
+        let pixels = (0..height).flat_map(|py| (0..width).map(move |px| (py / scale, px / scale)));
+        ppm::encode(
+            width,
+            height,
+            pixels.map(|(row, col)| {
+                overlay(&self.pos_at(row, col)).unwrap_or(
+                    match self.tiles[row * self.width + col] {
+                        Some(TileType::Wall) => [96, 96, 96],
+                        Some(TileType::Empty) => [224, 224, 224],
+                        Some(TileType::OxygenSystem) => [40, 120, 240],
+                        None => [0, 0, 0],
+                    },
+                )
+            }),
+        )
+    }
+
+    pub fn save_ppm<P: AsRef<Path>>(&self, path: P, scale: usize) -> io::Result<()> {
+        fs::write(path, self.to_ppm(scale, |_| None))
+    }
+}
+
+// Result of oxygen flood, positions filled in each minute.
+#[derive(Debug, Clone)]
+pub struct OxygenFlood {
+    fronts: Vec<Vec<Position>>,
+}
+
+impl OxygenFlood {
+    // Minutes to fill all reachable tiles.
+    pub fn minutes(&self) -> usize {
+        self.fronts.len().saturating_sub(1)
+    }
+
+    // Positions newly filled in given minute.
+    pub fn front(&self, minute: usize) -> &[Position] {
+        self.fronts.get(minute).map_or(&[], |f| f.as_slice())
+    }
+
+    // Positions filled by the end of given minute.
+    pub fn filled(&self, minute: usize) -> HashSet<Position> {
+        self.fronts
+            .iter()
+            .take(minute + 1)
+            .flat_map(|f| f.iter().copied())
+            .collect()
+    }
+
+    // ASCII drawing of map in every minute, filled tiles are drawn as '~'.
+    pub fn frames(&self, map: &MazeMap) -> Vec<String> {
+        let mut filled = HashSet::new();
+        self.fronts
+            .iter()
+            .map(|front| {
+                filled.extend(front.iter().copied());
+                map.render_with(|p| filled.contains(p).then_some('~'))
+            })
+            .collect()
+    }
+}
+
 pub struct Autopilot {
     map: HashMap<Position, (Option<Direction>, TileType)>,
     search_org: Option<Position>,
@@ -176,6 +427,11 @@ impl Autopilot {
         self.oxygen_sys_pos
     }
 
+    // Map of all positions observed so far.
+    pub fn map(&self) -> MazeMap {
+        MazeMap::from_tiles(self.map.iter().map(|(&pos, &(_, tile))| (pos, tile)))
+    }
+
     pub fn moves_from_origin(&self, pos: &Position) -> Vec<Direction> {
         let mut moves = Vec::new();
         let mut cur_pos = *pos;
@@ -1,11 +1,12 @@
 use std::{
-    collections::{HashMap, HashSet, LinkedList},
-    error,
+    cmp::Ordering,
+    collections::{HashSet, LinkedList},
+    env, error,
     fmt::Display,
     ops::{Index, Range},
 };
 
-use crate::int_code::io::{InputPort, OutputPort};
+use crate::int_code::io::{AsciiTerminal, InputPort, OutputPort};
 
 #[derive(Debug)]
 pub enum Error {
@@ -14,6 +15,7 @@ pub enum Error {
     InvalidScaffoldMapValue(i64),
     MultipleRobotInMap(usize, usize),
     RobotNotFoundInMap,
+    InvalidArgs(String),
 }
 
 impl Display for Error {
@@ -24,6 +26,7 @@ impl Display for Error {
             Error::InvalidScaffoldMapValue(v) => write!(f, "Invalid value({}) in scaffold map", v),
             Error::MultipleRobotInMap(last_ind, ind) => write!(f, "Found multiple robots(last one at {}, this one at {}) in given scaffold map", last_ind, ind),
             Error::RobotNotFoundInMap => write!(f, "Can't find robot in scaffold map, expect one"),
+            Error::InvalidArgs(s) => write!(f, "Invalid arguments({})", s),
         }
     }
 }
@@ -142,185 +145,251 @@ impl RobotPath {
     }
 }
 
-#[derive(Debug)]
-pub struct ZipRobotPath {
-    path: Vec<usize>,
-    subpaths: Vec<RobotPath>,
+// What makes one encoding better than another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    FewestFunctions,
+    ShortestMain,
 }
 
-impl ZipRobotPath {
-    pub fn new<F: Fn(&[RobotCommand]) -> bool>(src_path: &RobotPath, is_valid: F) -> Self {
-        let mut path_ranges = LinkedList::new();
-        path_ranges.push_back(0..src_path.path().len());
-        let subpath_ranges = Self::min_subpaths(src_path, path_ranges, &is_valid, 0, None);
-        let path = Self::path_from_sub_paths(&src_path, &subpath_ranges);
-        let subpaths = subpath_ranges
+// Parse arguments as "[--objective functions|main]", encodings with fewest functions are preferred by default.
+pub fn parse_args() -> Result<Objective, Error> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.as_slice() {
+        [] => Ok(Objective::FewestFunctions),
+        [flag, value] if flag == "--objective" && value == "functions" => {
+            Ok(Objective::FewestFunctions)
+        }
+        [flag, value] if flag == "--objective" && value == "main" => Ok(Objective::ShortestMain),
+        _ => Err(Error::InvalidArgs(args.join(" "))),
+    }
+}
+
+// Token sequence encoded as calls(main routine) of functions, each function is a subsequence of tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoding<T> {
+    main: Vec<usize>,
+    functions: Vec<Vec<T>>,
+}
+
+impl<T: Display> Display for Encoding<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let main = self
+            .main
             .iter()
-            .map(|sp_range| {
-                RobotPath(
-                    src_path.path()[sp_range.clone()]
-                        .iter()
-                        .cloned()
-                        .collect::<Vec<_>>(),
-                )
-            })
+            .map(|&ind| function_name(ind).to_string())
             .collect::<Vec<_>>();
+        write!(f, "Main: {}", main.join(","))?;
+        for (ind, function) in self.functions.iter().enumerate() {
+            let tokens = function.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            write!(f, "\n{}: {}", function_name(ind), tokens.join(","))?;
+        }
 
-        Self { path, subpaths }
+        Ok(())
     }
+}
 
-    pub fn path(&self) -> &[usize] {
-        &self.path
+impl<T> Encoding<T> {
+    pub fn main(&self) -> &[usize] {
+        &self.main
     }
 
-    pub fn sub_paths(&self) -> &[RobotPath] {
-        &self.subpaths
+    pub fn functions(&self) -> &[Vec<T>] {
+        &self.functions
     }
 
-    fn min_subpaths<F: Fn(&[RobotCommand]) -> bool>(
-        path: &RobotPath,
-        mut path_ranges: LinkedList<Range<usize>>,
-        is_valid: &F,
-        level: usize,
-        min_subpath_n: Option<usize>,
-    ) -> LinkedList<Range<usize>> {
-        let mut subpath_ranges = LinkedList::new();
-        // Clone first nonempty range
-        while let Some(first_path_range) = path_ranges.front().cloned() {
-            if first_path_range.is_empty() {
-                path_ranges.pop_front();
-                continue;
-            }
+    // Tokens sequence encoded.
+    pub fn decode(&self) -> Vec<&T> {
+        self.main
+            .iter()
+            .flat_map(|&ind| self.functions[ind].iter())
+            .collect()
+    }
+}
 
-            if path_ranges.len() == 1 && is_valid(&path[first_path_range.clone()]) {
-                // If only one valid subpath is left, return that one.
-                subpath_ranges.push_back(first_path_range);
-            } else {
-                // Cut off remained recursion branches, if number of subpaths has exceeds known minimium number of subpaths.
-                if min_subpath_n.is_some_and(|n| level + 1 >= n) {
-                    let mut command_map = HashMap::new();
-                    path_ranges.iter().flat_map(|r| r.clone()).for_each(|ind| {
-                        command_map.entry(path.path()[ind].clone()).or_insert(ind);
-                    });
-                    subpath_ranges.extend(command_map.iter().map(|(_, v)| *v..(*v + 1)));
-                    break;
-                }
+// Functions are named by letters from 'A'.
+fn function_name(ind: usize) -> char {
+    char::from(b'A' + (ind % 26) as u8)
+}
 
-                let mut min_subpath_ranges = LinkedList::new();
-                let mut min_subpath_n = usize::MAX;
-                for i in 1..=first_path_range.len() {
-                    let first_subpath_range = first_path_range.start..(first_path_range.start + i);
-                    if !is_valid(&path[first_subpath_range.clone()]) {
-                        break;
-                    }
+// Length of tokens after encoded.
+pub type EncodedLen<T> = Box<dyn Fn(&[T]) -> usize>;
+
+// Searches every way to encode token sequence with at most `max_fn_n` functions, each one no longer than `max_fn_len`
+// measured by `encoded_len`, which is assumed to grow with tokens, and at most `max_main_n` calls in main routine.
+pub struct PathCompressor<T> {
+    max_fn_n: usize,
+    max_main_n: usize,
+    max_fn_len: usize,
+    encoded_len: EncodedLen<T>,
+    objective: Objective,
+}
 
-                    let mut this_subpath_ranges = LinkedList::new();
-                    this_subpath_ranges.push_back(first_subpath_range.clone());
-
-                    // Recursion, find minimium subpaths in path ranges which have this_subpath removed.
-                    let left_path_ranges =
-                        Self::remove_subpath_range(path, &first_subpath_range, &path_ranges);
-                    let mut left_min_subpath_ranges = Self::min_subpaths(
-                        path,
-                        left_path_ranges,
-                        is_valid,
-                        level + 1,
-                        Some(min_subpath_n),
-                    );
-
-                    let this_subpath_n = left_min_subpath_ranges.len() + 1;
-                    if this_subpath_n < min_subpath_n {
-                        // Found less subpaths to construct original path.
-                        this_subpath_ranges.append(&mut left_min_subpath_ranges);
-                        min_subpath_ranges = this_subpath_ranges;
-                        min_subpath_n = this_subpath_n;
-                    }
-                }
+impl<T: Clone + Eq> PathCompressor<T> {
+    pub fn new(
+        max_fn_n: usize,
+        max_main_n: usize,
+        max_fn_len: usize,
+        encoded_len: EncodedLen<T>,
+        objective: Objective,
+    ) -> Self {
+        Self {
+            max_fn_n,
+            max_main_n,
+            max_fn_len,
+            encoded_len,
+            objective,
+        }
+    }
 
-                subpath_ranges = min_subpath_ranges;
-            }
-            break;
-        }
-
-        subpath_ranges
-    }
-
-    fn remove_subpath_range(
-        path: &RobotPath,
-        remove_range: &Range<usize>,
-        path_ranges: &LinkedList<Range<usize>>,
-    ) -> LinkedList<Range<usize>> {
-        let mut left_path_ranges = LinkedList::new();
-        for range in path_ranges.iter() {
-            let mut last_unequal_ind = range.start;
-            let mut remove_range_ind = remove_range.start;
-            for i in range.clone() {
-                if path.path()[i] == path.path()[remove_range_ind] {
-                    remove_range_ind += 1;
-                    if !remove_range.contains(&remove_range_ind) {
-                        // Found a repeat subrange, reset index in remove_range, and insert left range.
-                        remove_range_ind = remove_range.start;
-
-                        let last_left_range = last_unequal_ind..(i - (remove_range.len() - 1));
-                        if !last_left_range.is_empty() {
-                            left_path_ranges.push_back(last_left_range);
-                        }
-                        last_unequal_ind = i + 1;
-                    }
-                } else {
-                    remove_range_ind = remove_range.start;
-                }
-            }
+    pub fn objective(&self) -> Objective {
+        self.objective
+    }
+
+    // Order encodings by objective, the better one is the less one.
+    pub fn compare(&self, a: &Encoding<T>, b: &Encoding<T>) -> Ordering {
+        let fn_len =
+            |e: &Encoding<T>| -> usize { e.functions.iter().map(|f| (self.encoded_len)(f)).sum() };
+        match self.objective {
+            Objective::FewestFunctions => (a.functions.len(), a.main.len(), fn_len(a)).cmp(&(
+                b.functions.len(),
+                b.main.len(),
+                fn_len(b),
+            )),
+            Objective::ShortestMain => (a.main.len(), a.functions.len(), fn_len(a)).cmp(&(
+                b.main.len(),
+                b.functions.len(),
+                fn_len(b),
+            )),
+        }
+    }
 
-            if last_unequal_ind < range.end {
-                // Insert final left range.
-                left_path_ranges.push_back(last_unequal_ind..range.end);
+    // All valid encodings, the best one first.
+    pub fn encodings(&self, tokens: &[T]) -> Vec<Encoding<T>> {
+        let mut found = Vec::new();
+        self.search(tokens, &mut Vec::new(), &mut Vec::new(), &mut found);
+        found.sort_by(|a, b| self.compare(a, b));
+
+        found
+    }
+
+    pub fn best(&self, tokens: &[T]) -> Option<Encoding<T>> {
+        self.encodings(tokens).into_iter().next()
+    }
+
+    // Best encoding among alternative token sequences, with index of sequence it encodes, and number of valid
+    // encodings found for all sequences.
+    pub fn best_among<'a, I: IntoIterator<Item = &'a [T]>>(
+        &self,
+        alternatives: I,
+    ) -> Option<(usize, Encoding<T>, usize)>
+    where
+        T: 'a,
+    {
+        let mut encoding_n = 0;
+        let mut best: Option<(usize, Encoding<T>)> = None;
+        for (ind, tokens) in alternatives.into_iter().enumerate() {
+            let encodings = self.encodings(tokens);
+            encoding_n += encodings.len();
+            if let Some(encoding) = encodings.into_iter().next() {
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, e)| self.compare(&encoding, e).is_lt())
+                {
+                    best = Some((ind, encoding));
+                }
             }
         }
 
-        left_path_ranges
+        best.map(|(ind, encoding)| (ind, encoding, encoding_n))
     }
 
-    fn path_from_sub_paths(path: &RobotPath, path_ranges: &LinkedList<Range<usize>>) -> Vec<usize> {
-        let mut inds = Vec::new();
-        let mut path_ind = 0;
-        let path = path.path();
-        while path_ind < path.len() {
-            let mut sub_path_found = None;
-            // Check each subpath in order.
-            for (subpath_ind, range) in path_ranges.iter().enumerate() {
-                if path[range.clone()]
-                    .iter()
-                    .enumerate()
-                    .all(|(ind, command)| *command == path[path_ind + ind])
-                {
-                    // Found one matched subpath, record its index.
-                    sub_path_found = Some(subpath_ind);
-                    path_ind += range.len();
+    // Encode tokens left, with either a defined function or a new one starting here. Functions are numbered by their
+    // first call, so each encoding is found once.
+    fn search(
+        &self,
+        tokens: &[T],
+        main: &mut Vec<usize>,
+        functions: &mut Vec<Vec<T>>,
+        found: &mut Vec<Encoding<T>>,
+    ) {
+        if tokens.is_empty() {
+            found.push(Encoding {
+                main: main.clone(),
+                functions: functions.clone(),
+            });
+            return;
+        }
+        if main.len() >= self.max_main_n {
+            return;
+        }
+
+        for ind in 0..functions.len() {
+            if tokens.starts_with(&functions[ind]) {
+                main.push(ind);
+                let left_tokens = &tokens[functions[ind].len()..];
+                self.search(left_tokens, main, functions, found);
+                main.pop();
+            }
+        }
+
+        if functions.len() < self.max_fn_n {
+            for len in 1..=tokens.len() {
+                let function = &tokens[..len];
+                if (self.encoded_len)(function) > self.max_fn_len {
                     break;
                 }
+                if functions.iter().any(|f| f == function) {
+                    continue;
+                }
+
+                main.push(functions.len());
+                functions.push(function.to_vec());
+                self.search(&tokens[len..], main, functions, found);
+                functions.pop();
+                main.pop();
             }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZipRobotPath {
+    path: Vec<usize>,
+    subpaths: Vec<RobotPath>,
+}
 
-            inds.push(sub_path_found.expect(&format!(
-                "Failed to find a subpath match elements in path at {}.",
-                path_ind
-            )));
+impl From<Encoding<RobotCommand>> for ZipRobotPath {
+    fn from(encoding: Encoding<RobotCommand>) -> Self {
+        Self {
+            path: encoding.main,
+            subpaths: encoding.functions.into_iter().map(RobotPath).collect(),
         }
+    }
+}
+
+impl ZipRobotPath {
+    // Compressor for movement functions accepted by ASCII program, at most 3 functions, and at most 20 characters
+    // in main routine and each function.
+    pub fn compressor(objective: Objective) -> PathCompressor<RobotCommand> {
+        PathCompressor::new(3, 10, 20, Box::new(text_len), objective)
+    }
+
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
 
-        inds
+    pub fn sub_paths(&self) -> &[RobotPath] {
+        &self.subpaths
     }
 }
 
 pub struct ZipPathPilot {
-    input_buf: Vec<char>,
-    input_ind: usize,
-    dust_n: usize,
-    need_video: bool,
+    term: AsciiTerminal,
 }
 
 impl ZipPathPilot {
     pub fn new(path: &ZipRobotPath, need_video: bool) -> Self {
-        let mut input_buf = String::new();
         // Push main move function.
         let move_fn_codes = ["A", "B", "C"];
         let main_move_fn_str = path
@@ -332,38 +401,30 @@ impl ZipPathPilot {
             })
             .collect::<Vec<_>>()
             .join(",");
-        input_buf.push_str(&main_move_fn_str);
-        input_buf.push('\n');
+        let mut term = AsciiTerminal::with_lines([main_move_fn_str]);
 
         // Push sub move functions.
         for move_fn in path.sub_paths() {
-            input_buf.push_str(&move_fn.text());
-            input_buf.push('\n');
+            term.push_line(&move_fn.text());
         }
 
-        // Push answer for video feed
-        input_buf.push(if need_video { 'y' } else { 'n' });
-        input_buf.push('\n');
-        let input_buf = input_buf.chars().collect::<Vec<_>>();
+        // Push answer for video feed, playing video isn't supported yet.
+        term.push_line(if need_video { "y" } else { "n" });
 
-        Self {
-            input_buf,
-            input_ind: 0,
-            dust_n: 0,
-            need_video,
-        }
+        Self { term }
     }
 
+    // Dust amount is reported as the last value out of ASCII range.
     pub fn dust_n(&self) -> usize {
-        self.dust_n
+        self.term.values().last().map_or(0, |&v| {
+            usize::try_from(v).expect(&format!("Invalid dust amount({})", v))
+        })
     }
 }
 
 impl InputPort for ZipPathPilot {
     fn get(&mut self) -> Option<i64> {
-        let ind = self.input_ind;
-        self.input_ind += 1;
-        self.input_buf.get(ind).map(|c| u32::from(*c).into())
+        self.term.get()
     }
 
     fn reg_proc(&mut self, _proc_id: usize) {}
@@ -371,12 +432,7 @@ impl InputPort for ZipPathPilot {
 
 impl OutputPort for ZipPathPilot {
     fn put(&mut self, value: i64) -> Result<(), crate::Error> {
-        if !self.need_video || (self.need_video && value >= 0) {
-            // Playing video isn't supported yet.
-            self.dust_n = usize::try_from(value).expect(&format!("Invalid dust amount({})", value));
-        }
-
-        Ok(())
+        self.term.put(value)
     }
 
     fn wait_proc_id(&self) -> Option<usize> {
@@ -770,3 +826,131 @@ impl ScaffoldMap {
         Position(ind % self.col_n, ind / self.col_n)
     }
 }
+
+#[cfg(test)]
+fn char_compressor(
+    max_fn_n: usize,
+    max_main_n: usize,
+    max_fn_len: usize,
+    objective: Objective,
+) -> PathCompressor<char> {
+    PathCompressor::new(
+        max_fn_n,
+        max_main_n,
+        max_fn_len,
+        Box::new(|t: &[char]| t.len()),
+        objective,
+    )
+}
+
+// Every split of tokens into calls within limits, as (main, functions) in order.
+#[cfg(test)]
+fn split_all(
+    tokens: &[char],
+    max_fn_n: usize,
+    max_main_n: usize,
+    max_fn_len: usize,
+) -> Vec<(Vec<usize>, Vec<Vec<char>>)> {
+    let mut splits = Vec::new();
+    for cuts in 0..(1_usize << (tokens.len() - 1)) {
+        let (mut main, mut functions) = (Vec::new(), Vec::<Vec<char>>::new());
+        let mut start = 0;
+        for end in 1..=tokens.len() {
+            if end < tokens.len() && cuts & (1 << (end - 1)) == 0 {
+                continue;
+            }
+            let piece = tokens[start..end].to_vec();
+            let ind = functions
+                .iter()
+                .position(|f| *f == piece)
+                .unwrap_or_else(|| {
+                    functions.push(piece);
+                    functions.len() - 1
+                });
+            main.push(ind);
+            start = end;
+        }
+        if functions.len() <= max_fn_n
+            && main.len() <= max_main_n
+            && functions.iter().all(|f| f.len() <= max_fn_len)
+        {
+            splits.push((main, functions));
+        }
+    }
+
+    splits.sort();
+    splits
+}
+
+#[test]
+fn test_compressor_encodings() {
+    let tokens = "abcabcab".chars().collect::<Vec<_>>();
+    for (max_fn_n, max_main_n, max_fn_len) in [(3, 10, 8), (2, 4, 3), (3, 3, 3), (1, 8, 8)] {
+        let compressor =
+            char_compressor(max_fn_n, max_main_n, max_fn_len, Objective::FewestFunctions);
+        let encodings = compressor.encodings(&tokens);
+        for encoding in &encodings {
+            assert_eq!(
+                encoding.decode().into_iter().copied().collect::<Vec<_>>(),
+                tokens
+            );
+            assert!(encoding.functions().len() <= max_fn_n);
+            assert!(encoding.main().len() <= max_main_n);
+            assert!(encoding.functions().iter().all(|f| f.len() <= max_fn_len));
+            for (i, f) in encoding.functions().iter().enumerate() {
+                assert!(!encoding.functions()[..i].contains(f), "{}", encoding);
+            }
+        }
+        for pair in encodings.windows(2) {
+            assert!(compressor.compare(&pair[0], &pair[1]).is_le());
+        }
+
+        let mut found = encodings
+            .into_iter()
+            .map(|e| (e.main, e.functions))
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, split_all(&tokens, max_fn_n, max_main_n, max_fn_len));
+    }
+
+    // One function shorter than "abcabcab" can't cover it, and three calls of at most 2 tokens can't cover it either.
+    assert!(char_compressor(1, 8, 7, Objective::FewestFunctions)
+        .best(&tokens)
+        .is_none());
+    assert!(char_compressor(3, 3, 2, Objective::FewestFunctions)
+        .best(&tokens)
+        .is_none());
+}
+
+#[test]
+fn test_compressor_objectives() {
+    let tokens = "aaa".chars().collect::<Vec<_>>();
+    let fewest = char_compressor(2, 3, 2, Objective::FewestFunctions);
+    let shortest = char_compressor(2, 3, 2, Objective::ShortestMain);
+    let mut by_fewest = fewest.encodings(&tokens);
+    let mut by_shortest = shortest.encodings(&tokens);
+    assert_eq!(by_fewest.len(), by_shortest.len());
+    by_shortest.sort_by(|a, b| fewest.compare(a, b));
+    assert_eq!(by_shortest, by_fewest);
+
+    by_fewest = fewest.encodings(&tokens);
+    by_shortest = shortest.encodings(&tokens);
+    assert_eq!(by_fewest[0].functions(), &[vec!['a']]);
+    assert_eq!(by_fewest[0].main(), &[0, 0, 0]);
+    // "aa" then "a", or "a" then "aa".
+    assert_eq!(by_shortest[0].functions().len(), 2);
+    assert_eq!(by_shortest[0].main(), &[0, 1]);
+
+    let alternatives = ["ab".chars().collect::<Vec<_>>(), tokens.clone()];
+    let (ind, encoding, encoding_n) = shortest
+        .best_among(alternatives.iter().map(|a| a.as_slice()))
+        .unwrap();
+    assert_eq!((ind, encoding.main()), (0, &[0][..]));
+    assert_eq!(
+        encoding_n,
+        alternatives
+            .iter()
+            .map(|a| shortest.encodings(a).len())
+            .sum::<usize>()
+    );
+}
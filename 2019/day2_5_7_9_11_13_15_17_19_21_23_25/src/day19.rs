@@ -1,4 +1,4 @@
-use std::{fmt::Display, ops::Range};
+use std::{collections::HashMap, env, fmt::Display, ops::Range};
 
 use int_enum::IntEnum;
 
@@ -13,6 +13,9 @@ pub enum Error {
     InvalidDroneState(ProcessState),
     EmptyDroneResult(Point),
     InvalidDroneResult(i64),
+    InvalidSquareSize(usize),
+    SquareNotFound(usize, usize), // (size of square, count of rows searched)
+    InvalidArgs(String),
 }
 
 impl Display for Error {
@@ -28,6 +31,13 @@ impl Display for Error {
             Error::InvalidDroneResult(i) => {
                 write!(f, "Get invalid drone result({}), expect 0 or 1", i)
             }
+            Error::InvalidSquareSize(s) => write!(f, "Invalid square size({}), expect positive", s),
+            Error::SquareNotFound(s, n) => write!(
+                f,
+                "Can't find square({} x {}) in beam within the first {} row(s)",
+                s, s, n
+            ),
+            Error::InvalidArgs(s) => write!(f, "Invalid arguments({})", s),
         }
     }
 }
@@ -369,3 +379,233 @@ impl Scanner {
             .unwrap_or(Ok(default))
     }
 }
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProbeStats {
+    probe_n: usize,
+    cache_hit_n: usize,
+    row_n: usize,
+}
+
+impl Display for ProbeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} probe(s) run drone system, {} probe(s) hit cache, {} row(s) traced",
+            self.probe_n, self.cache_hit_n, self.row_n
+        )
+    }
+}
+
+impl ProbeStats {
+    // Probes actually running drone system.
+    pub fn probe_n(&self) -> usize {
+        self.probe_n
+    }
+
+    pub fn cache_hit_n(&self) -> usize {
+        self.cache_hit_n
+    }
+
+    pub fn row_n(&self) -> usize {
+        self.row_n
+    }
+}
+
+// Rows searched for square by default, beam is assumed to be too narrow if no square is found in them.
+const DEFAULT_MAX_SQUARE_ROW_N: usize = 100_000;
+
+// Traces beam row by row following its left and right edges, each row is started from edges of the last non-empty
+// row, so only a few probes are needed per row.
+pub struct BeamTracer {
+    drone_sys: DroneSystem,
+    probes: HashMap<(usize, usize), PointType>,
+    rows: Vec<Range<usize>>,
+    stats: ProbeStats,
+    max_square_row_n: usize,
+}
+
+impl BeamTracer {
+    pub fn new(drone_sys_code: Vec<i64>) -> Self {
+        Self {
+            drone_sys: DroneSystem::new(drone_sys_code),
+            probes: HashMap::new(),
+            rows: Vec::new(),
+            stats: ProbeStats::default(),
+            max_square_row_n: DEFAULT_MAX_SQUARE_ROW_N,
+        }
+    }
+
+    // Search for square fails if it isn't found in given count of rows.
+    pub fn with_max_square_row_n(mut self, row_n: usize) -> Self {
+        self.max_square_row_n = row_n;
+        self
+    }
+
+    pub fn stats(&self) -> ProbeStats {
+        self.stats
+    }
+
+    // Column range of beam in each row traced so far, empty range for row without beam.
+    pub fn rows(&self) -> &[Range<usize>] {
+        &self.rows
+    }
+
+    pub fn probe(&mut self, p: &Point) -> Result<PointType, Error> {
+        if let Some(pt) = self.probes.get(&(p.x(), p.y())) {
+            self.stats.cache_hit_n += 1;
+            return Ok(*pt);
+        }
+
+        let pt = self.drone_sys.place_drone(p)?;
+        self.stats.probe_n += 1;
+        self.probes.insert((p.x(), p.y()), pt);
+        Ok(pt)
+    }
+
+    fn is_pulled(&mut self, x: usize, y: usize) -> Result<bool, Error> {
+        self.probe(&Point::new(x, y))
+            .map(|pt| pt == PointType::Pulled)
+    }
+
+    // Column range of beam in given row, rows before it are traced too.
+    pub fn row(&mut self, y: usize) -> Result<Range<usize>, Error> {
+        while self.rows.len() <= y {
+            let row = self.trace_row(self.rows.len())?;
+            self.rows.push(row);
+            self.stats.row_n += 1;
+        }
+
+        Ok(self.rows[y].clone())
+    }
+
+    pub fn contains(&mut self, p: &Point) -> Result<bool, Error> {
+        self.row(p.y()).map(|r| r.contains(&p.x()))
+    }
+
+    // Area filled from traced rows, without probing each point.
+    pub fn area(&mut self, x_range: Range<usize>, y_range: Range<usize>) -> Result<Area, Error> {
+        let mut area = Area::new(x_range.clone(), y_range.clone());
+        for y in y_range {
+            let row = self.row(y)?;
+            for x in x_range.clone().filter(|x| row.contains(x)) {
+                *area.p_mut(&Point::new(x, y)) = PointType::Pulled;
+            }
+        }
+
+        Ok(area)
+    }
+
+    fn trace_row(&mut self, y: usize) -> Result<Range<usize>, Error> {
+        let last_row = self.rows.iter().rev().find(|r| !r.is_empty()).cloned();
+        let start = match last_row {
+            Some(ref last_row) if self.is_pulled(last_row.start, y)? => {
+                // Inside of beam, move to left for start of this row.
+                let mut x = last_row.start;
+                while x > 0 && self.is_pulled(x - 1, y)? {
+                    x -= 1;
+                }
+                Some(x)
+            }
+            // Outside of beam, move to right for start of this row, it can't be far from the last row, unless rows
+            // between are empty near origin.
+            Some(ref last_row) => self.first_pulled(
+                (last_row.start + 1)..(last_row.end + last_row.len() + 2).max(y * 2 + 1),
+                y,
+            )?,
+            // No beam found yet, assume it's not wider than twice of row index near origin.
+            None => self.first_pulled(0..(y * 2 + 1), y)?,
+        };
+        let Some(start) = start else {
+            return Ok(0..0);
+        };
+
+        let mut end = last_row.map_or(start, |r| r.end).max(start + 1);
+        if self.is_pulled(end - 1, y)? {
+            // Inside of beam, move to right for end of this row.
+            while self.is_pulled(end, y)? {
+                end += 1;
+            }
+        } else {
+            // Outside of beam, move to left for end of this row.
+            while end - 1 > start && !self.is_pulled(end - 1, y)? {
+                end -= 1;
+            }
+        }
+
+        Ok(start..end)
+    }
+
+    fn first_pulled(&mut self, x_range: Range<usize>, y: usize) -> Result<Option<usize>, Error> {
+        for x in x_range {
+            if self.is_pulled(x, y)? {
+                return Ok(Some(x));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Top left and bottom right point of the first(closest to emitter) square of given size fitting in beam.
+    pub fn first_square(&mut self, size: usize) -> Result<(Point, Point), Error> {
+        if size == 0 {
+            return Err(Error::InvalidSquareSize(size));
+        }
+
+        for y in (size - 1)..self.max_square_row_n {
+            // Square is placed at start of its bottom row, as the beam leans to right.
+            let bottom_row = self.row(y)?;
+            if bottom_row.len() >= size {
+                let top_y = y + 1 - size;
+                let top_row = self.row(top_y)?;
+                if top_row.start <= bottom_row.start && top_row.end >= bottom_row.start + size {
+                    return Ok((
+                        Point::new(bottom_row.start, top_y),
+                        Point::new(bottom_row.start + size - 1, y),
+                    ));
+                }
+            }
+        }
+
+        Err(Error::SquareNotFound(size, self.max_square_row_n))
+    }
+}
+
+// Parse arguments as "[--square n]", giving size of square for beam tracer, or None for scanner.
+pub fn parse_args() -> Result<Option<usize>, Error> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.as_slice() {
+        [] => Ok(None),
+        [flag, value] if flag == "--square" => value
+            .parse::<usize>()
+            .map(Some)
+            .map_err(|_| Error::InvalidArgs(args.join(" "))),
+        _ => Err(Error::InvalidArgs(args.join(" "))),
+    }
+}
+
+#[test]
+fn test_first_square() {
+    let int_code = crate::int_code::read_int_code("day19_inputs.txt").unwrap();
+    let mut tracer = BeamTracer::new(int_code.clone());
+    assert!(matches!(
+        tracer.first_square(0),
+        Err(Error::InvalidSquareSize(0))
+    ));
+
+    let (top_left, bottom_right) = tracer.first_square(10).unwrap();
+    for p in [
+        top_left,
+        bottom_right,
+        Point::new(top_left.x(), bottom_right.y()),
+        Point::new(bottom_right.x(), top_left.y()),
+    ] {
+        assert!(tracer.contains(&p).unwrap(), "{}", p);
+    }
+
+    let mut tracer = BeamTracer::new(int_code).with_max_square_row_n(50);
+    assert!(matches!(
+        tracer.first_square(100),
+        Err(Error::SquareNotFound(100, 50))
+    ));
+}
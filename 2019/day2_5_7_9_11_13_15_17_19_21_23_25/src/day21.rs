@@ -1,11 +1,23 @@
-use std::{fmt::Display, iter};
+use std::{fmt::Display, iter, str::FromStr};
 
-use crate::int_code::io::{InputPort, OutputPort};
+use crate::int_code::{
+    com::SeqIntCodeComputer,
+    io::{AsciiTerminal, InputPort, OutputPort, SeqIODevice},
+};
+
+// Most instructions springdroid can remember.
+const MAX_SCRIPT_LENGTH: usize = 15;
 
 #[derive(Debug)]
 pub enum Error {
     MultipleDamageInDetection(usize, i64), // (earlier damage, current value)
     InvalidOutputInDetection(i64),
+    InvalidFormula(String),
+    UnavailableSensor(RRegister, DetectMode),
+    UncompilableFormula(String),
+    ScriptTooLong(usize),
+    ExecutionError(crate::Error),
+    NoScriptFound(DetectMode, usize), // (mode, number of tried candidates)
 }
 
 impl Display for Error {
@@ -19,10 +31,37 @@ impl Display for Error {
             Error::InvalidOutputInDetection(v) => {
                 write!(f, "Invalid output value({}) in hull detection program.", v)
             }
+            Error::InvalidFormula(s) => write!(f, "Invalid sensor formula({})", s),
+            Error::UnavailableSensor(r, m) => {
+                write!(f, "Sensor({}) isn't available in mode({})", r, m)
+            }
+            Error::UncompilableFormula(s) => write!(
+                f,
+                "Formula({}) can't be computed with only registers T and J",
+                s
+            ),
+            Error::ScriptTooLong(n) => write!(
+                f,
+                "Script has {} instructions, more than springdroid can remember({})",
+                n, MAX_SCRIPT_LENGTH
+            ),
+            Error::ExecutionError(ee) => {
+                write!(
+                    f,
+                    "Get error({}) in execution of hull detection program",
+                    ee
+                )
+            }
+            Error::NoScriptFound(m, n) => write!(
+                f,
+                "None of {} candidate script(s) made springdroid across hull in mode({})",
+                n, m
+            ),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RRegister {
     A, // ground detector(1 tiles ahead)
     B, // ground detector(2 tiles ahead)
@@ -57,11 +96,21 @@ impl Display for RRegister {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WRegister {
     T, // Temporary
     J, // Jump
 }
 
+impl From<WRegister> for RRegister {
+    fn from(wr: WRegister) -> Self {
+        match wr {
+            WRegister::T => RRegister::T,
+            WRegister::J => RRegister::J,
+        }
+    }
+}
+
 impl Display for WRegister {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let name = match self {
@@ -73,6 +122,7 @@ impl Display for WRegister {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SSInstruction {
     Or(RRegister, WRegister),
     And(RRegister, WRegister),
@@ -118,29 +168,415 @@ impl Display for DetectMode {
     }
 }
 
+impl DetectMode {
+    // Ground sensors springdroid can read in this mode.
+    pub fn sensors(&self) -> &'static [RRegister] {
+        const SENSORS: [RRegister; 9] = [
+            RRegister::A,
+            RRegister::B,
+            RRegister::C,
+            RRegister::D,
+            RRegister::E,
+            RRegister::F,
+            RRegister::G,
+            RRegister::H,
+            RRegister::I,
+        ];
+        match self {
+            DetectMode::Walk => &SENSORS[..4],
+            DetectMode::Run => &SENSORS,
+        }
+    }
+}
+
+// Boolean expression over ground sensors, deciding whether springdroid jumps, e.g. "(!A || !B || !C) && D".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Formula {
+    Sensor(RRegister),
+    Not(Box<Formula>),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+}
+
+impl Display for Formula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn join(
+            f: &mut std::fmt::Formatter<'_>,
+            operands: &[Formula],
+            op: &str,
+            is_grouped: fn(&Formula) -> bool,
+        ) -> std::fmt::Result {
+            for (i, operand) in operands.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", op)?;
+                }
+                if is_grouped(operand) {
+                    write!(f, "({})", operand)?;
+                } else {
+                    write!(f, "{}", operand)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        match self {
+            Formula::Sensor(r) => write!(f, "{}", r),
+            Formula::Not(operand) => match operand.as_ref() {
+                Formula::Sensor(_) | Formula::Not(_) => write!(f, "!{}", operand),
+                _ => write!(f, "!({})", operand),
+            },
+            Formula::And(operands) => join(f, operands, "&&", |o| matches!(o, Formula::Or(_))),
+            Formula::Or(operands) => join(f, operands, "||", |_| false),
+        }
+    }
+}
+
+impl FromStr for Formula {
+    type Err = Error;
+
+    // '!' binds tighter than "&&", which binds tighter than "||".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = s
+            .replace("&&", "&")
+            .replace("||", "|")
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<Vec<_>>();
+        let mut ind = 0;
+        let formula = Self::parse_or(&tokens, &mut ind);
+        match formula {
+            Some(formula) if ind == tokens.len() => Ok(formula),
+            _ => Err(Error::InvalidFormula(s.to_string())),
+        }
+    }
+}
+
+impl Formula {
+    pub fn sensor(r: RRegister) -> Self {
+        Self::Sensor(r)
+    }
+
+    pub fn negate(operand: Formula) -> Self {
+        Self::Not(Box::new(operand))
+    }
+
+    pub fn and(operands: Vec<Formula>) -> Self {
+        Self::And(operands)
+    }
+
+    pub fn or(operands: Vec<Formula>) -> Self {
+        Self::Or(operands)
+    }
+
+    fn parse_or(tokens: &[char], ind: &mut usize) -> Option<Self> {
+        let mut operands = vec![Self::parse_and(tokens, ind)?];
+        while tokens.get(*ind) == Some(&'|') {
+            *ind += 1;
+            operands.push(Self::parse_and(tokens, ind)?);
+        }
+
+        Some(if operands.len() == 1 {
+            operands.pop().unwrap()
+        } else {
+            Self::Or(operands)
+        })
+    }
+
+    fn parse_and(tokens: &[char], ind: &mut usize) -> Option<Self> {
+        let mut operands = vec![Self::parse_unary(tokens, ind)?];
+        while tokens.get(*ind) == Some(&'&') {
+            *ind += 1;
+            operands.push(Self::parse_unary(tokens, ind)?);
+        }
+
+        Some(if operands.len() == 1 {
+            operands.pop().unwrap()
+        } else {
+            Self::And(operands)
+        })
+    }
+
+    fn parse_unary(tokens: &[char], ind: &mut usize) -> Option<Self> {
+        let token = *tokens.get(*ind)?;
+        *ind += 1;
+        match token {
+            '!' => Self::parse_unary(tokens, ind).map(Self::negate),
+            '(' => {
+                let formula = Self::parse_or(tokens, ind)?;
+                if tokens.get(*ind) == Some(&')') {
+                    *ind += 1;
+                    Some(formula)
+                } else {
+                    None
+                }
+            }
+            c => DetectMode::Run
+                .sensors()
+                .iter()
+                .find(|r| r.to_string() == c.to_string())
+                .map(|&r| Self::Sensor(r)),
+        }
+    }
+
+    fn sensors(&self) -> Vec<RRegister> {
+        match self {
+            Formula::Sensor(r) => vec![*r],
+            Formula::Not(operand) => operand.sensors(),
+            Formula::And(operands) | Formula::Or(operands) => {
+                operands.iter().flat_map(|o| o.sensors()).collect()
+            }
+        }
+    }
+
+    // Same formula with negations pushed down to sensors, and nested operations of the same kind merged.
+    fn normalize(&self, negated: bool) -> Self {
+        fn merge(operands: &[Formula], negated: bool, is_and: bool) -> Formula {
+            let mut merged = Vec::new();
+            for operand in operands {
+                match (operand.normalize(negated), is_and) {
+                    (Formula::And(inner), true) | (Formula::Or(inner), false) => {
+                        merged.extend(inner)
+                    }
+                    (operand, _) => merged.push(operand),
+                }
+            }
+
+            match merged.len() {
+                1 => merged.pop().unwrap(),
+                _ if is_and => Formula::And(merged),
+                _ => Formula::Or(merged),
+            }
+        }
+
+        match self {
+            Formula::Sensor(r) if negated => Self::negate(Self::Sensor(*r)),
+            Formula::Sensor(r) => Self::Sensor(*r),
+            Formula::Not(operand) => operand.normalize(!negated),
+            Formula::And(operands) => merge(operands, negated, !negated),
+            Formula::Or(operands) => merge(operands, negated, negated),
+        }
+    }
+
+    // (sensor, whether it's negated) if normalized formula is a single sensor.
+    fn literal(&self) -> Option<(RRegister, bool)> {
+        match self {
+            Formula::Sensor(r) => Some((*r, false)),
+            Formula::Not(operand) => match operand.as_ref() {
+                Formula::Sensor(r) => Some((*r, true)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // Normalized formula with literals shared by every operand taken out, e.g. "A && D || B && D" becomes
+    // "D && (A || B)". None if operands share no literal, or one operand has nothing else.
+    fn factored(&self) -> Option<Self> {
+        let (operands, is_and) = match self {
+            Formula::And(operands) => (operands, true),
+            Formula::Or(operands) => (operands, false),
+            _ => return None,
+        };
+        let inners = operands
+            .iter()
+            .map(|o| match (o, is_and) {
+                (Formula::Or(inner), true) | (Formula::And(inner), false) => Some(inner),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let common = inners[0]
+            .iter()
+            .filter(|o| o.literal().is_some() && inners[1..].iter().all(|inner| inner.contains(o)))
+            .cloned()
+            .collect::<Vec<_>>();
+        if common.is_empty() {
+            return None;
+        }
+
+        let rests = inners
+            .iter()
+            .map(|inner| {
+                let mut rest = inner
+                    .iter()
+                    .filter(|o| !common.contains(o))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                match rest.len() {
+                    0 => None,
+                    1 => rest.pop(),
+                    _ if is_and => Some(Formula::Or(rest)),
+                    _ => Some(Formula::And(rest)),
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut factored = common;
+        if is_and {
+            factored.push(Formula::And(rests));
+            Some(Formula::Or(factored).normalize(false))
+        } else {
+            factored.push(Formula::Or(rests));
+            Some(Formula::And(factored).normalize(false))
+        }
+    }
+
+    // Instructions computing normalized formula into target register, using scratch register(if any) for
+    // intermediate values, target is false at start if `is_clear`. The formula with and without shared literals
+    // factored out, and negation of both(followed by "NOT target target") are tried at every level, and the
+    // shortest one is taken. It's a heuristic, the script isn't guaranteed to be the shortest one.
+    fn compile_into(
+        &self,
+        target: WRegister,
+        scratch: Option<WRegister>,
+        is_clear: bool,
+    ) -> Result<Vec<SSInstruction>, Error> {
+        let mut best: Option<Vec<SSInstruction>> = None;
+        let mut error = None;
+        for form in iter::once(self.clone()).chain(self.factored()) {
+            let direct = form.compile_direct(target, scratch, is_clear);
+            let negated = form
+                .normalize(true)
+                .compile_direct(target, scratch, is_clear)
+                .map(|mut code| {
+                    code.push(SSInstruction::not(target.into(), target));
+                    code
+                });
+            for code in [direct, negated] {
+                match code {
+                    Ok(code) if best.as_ref().is_none_or(|best| code.len() < best.len()) => {
+                        best = Some(code)
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                }
+            }
+        }
+
+        best.ok_or_else(|| error.unwrap())
+    }
+
+    fn compile_direct(
+        &self,
+        target: WRegister,
+        scratch: Option<WRegister>,
+        is_clear: bool,
+    ) -> Result<Vec<SSInstruction>, Error> {
+        let uncompilable = || Error::UncompilableFormula(self.to_string());
+        if let Some((r, is_negated)) = self.literal() {
+            return Ok(match (is_negated, is_clear) {
+                (true, _) => vec![SSInstruction::not(r, target)],
+                (false, true) => vec![SSInstruction::or(r, target)],
+                (false, false) => vec![
+                    SSInstruction::not(r, target),
+                    SSInstruction::not(target.into(), target),
+                ],
+            });
+        }
+
+        let (operands, is_and) = match self {
+            Formula::And(operands) => (operands, true),
+            Formula::Or(operands) => (operands, false),
+            _ => return Err(uncompilable()),
+        };
+        let fold = |src: RRegister| {
+            if is_and {
+                SSInstruction::and(src, target)
+            } else {
+                SSInstruction::or(src, target)
+            }
+        };
+        let (mut literals, complexes): (Vec<_>, Vec<_>) =
+            operands.iter().partition(|o| o.literal().is_some());
+
+        // One operand is computed into target, others are computed into scratch and folded into target one by one.
+        let mut code = if complexes.is_empty() {
+            // Negated sensor costs one more instruction to fold than sensor, so it's better to start with it.
+            let start_ind = literals
+                .iter()
+                .position(|o| matches!(o, Formula::Not(_)))
+                .unwrap_or(0);
+            literals
+                .remove(start_ind)
+                .compile_direct(target, scratch, is_clear)?
+        } else {
+            let mut best: Option<Vec<SSInstruction>> = None;
+            for kept_ind in 0..complexes.len() {
+                let mut code = match complexes[kept_ind].compile_into(target, scratch, is_clear) {
+                    Ok(code) => code,
+                    Err(_) => continue,
+                };
+                let folded = complexes
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| i != kept_ind)
+                    .try_for_each(|(_, complex)| {
+                        let scratch = scratch.ok_or_else(uncompilable)?;
+                        code.extend(complex.compile_into(scratch, None, false)?);
+                        code.push(fold(scratch.into()));
+                        Ok::<(), Error>(())
+                    });
+                if folded.is_ok() && best.as_ref().is_none_or(|best| code.len() < best.len()) {
+                    best = Some(code);
+                }
+            }
+            best.ok_or_else(uncompilable)?
+        };
+        for (r, is_negated) in literals.iter().flat_map(|o| o.literal()) {
+            if is_negated {
+                let scratch = scratch.ok_or_else(uncompilable)?;
+                code.push(SSInstruction::not(r, scratch));
+                code.push(fold(scratch.into()));
+            } else {
+                code.push(fold(r));
+            }
+        }
+
+        Ok(code)
+    }
+
+    // Compile formula into script for given mode, which makes springdroid jump when formula is true.
+    // Script is kept short by heuristics in compile_into, but it isn't always minimal.
+    pub fn compile(&self, mode: DetectMode) -> Result<Vec<SSInstruction>, Error> {
+        if let Some(r) = self
+            .sensors()
+            .into_iter()
+            .find(|r| !mode.sensors().contains(r))
+        {
+            return Err(Error::UnavailableSensor(r, mode));
+        }
+
+        let script = self
+            .normalize(false)
+            .compile_into(WRegister::J, Some(WRegister::T), true)?;
+        if script.len() > MAX_SCRIPT_LENGTH {
+            return Err(Error::ScriptTooLong(script.len()));
+        }
+
+        Ok(script)
+    }
+}
+
 pub struct HullDetector {
-    script_chars: Vec<char>,
+    term: AsciiTerminal,
     mode: DetectMode,
-    input_ind: usize,
     damage: Option<usize>,
-    log: String,
 }
 
 impl HullDetector {
     pub fn new(script: &[SSInstruction], mode: DetectMode) -> Self {
-        let script_chars = script
-            .iter()
-            .map(|inst| inst.to_string())
-            .chain(iter::once(mode.to_string()))
-            .flat_map(|s| s.chars().chain(iter::once('\n')).collect::<Vec<_>>())
-            .collect::<Vec<_>>();
+        let term = AsciiTerminal::with_lines(
+            script
+                .iter()
+                .map(|inst| inst.to_string())
+                .chain(iter::once(mode.to_string())),
+        );
 
         Self {
-            script_chars,
+            term,
             mode,
-            input_ind: 0,
             damage: None,
-            log: String::new(),
         }
     }
 
@@ -148,23 +584,14 @@ impl HullDetector {
         self.damage
     }
 
-    pub fn log(&self) -> &str {
-        &self.log
+    pub fn log(&self) -> String {
+        self.term.log()
     }
 }
 
 impl InputPort for HullDetector {
     fn get(&mut self) -> Option<i64> {
-        let res = self.script_chars.get(self.input_ind).map(|c| {
-            // Echo input to log.
-            self.log.push(*c);
-            *c as i64
-        });
-        if self.input_ind < self.script_chars.len() {
-            self.input_ind += 1;
-        }
-
-        res
+        self.term.get()
     }
 
     fn reg_proc(&mut self, _proc_id: usize) {}
@@ -172,32 +599,296 @@ impl InputPort for HullDetector {
 
 impl OutputPort for HullDetector {
     fn put(&mut self, value: i64) -> Result<(), crate::Error> {
-        fn to_ascii(value: i64) -> Option<char> {
-            u32::try_from(value)
-                .ok()
-                .and_then(|v| char::from_u32(v).filter(|c| c.is_ascii()))
-        }
-
-        if let Some(c) = to_ascii(value) {
-            self.log.push(c);
-        } else if let Ok(v) = usize::try_from(value) {
-            if let Some(d) = self.damage {
-                return Err(crate::Error::IOProcessError(
-                    Error::MultipleDamageInDetection(d, value).to_string(),
-                ));
-            } else {
-                self.damage = Some(v);
+        self.term.put(value)?;
+        let e = match *self.term.values() {
+            [] => return Ok(()),
+            [d] if d >= 0 => {
+                self.damage = Some(d as usize);
+                return Ok(());
             }
-        } else {
-            return Err(crate::Error::IOProcessError(
-                Error::InvalidOutputInDetection(value).to_string(),
-            ));
-        }
+            [v] => Error::InvalidOutputInDetection(v),
+            [d, .., v] => Error::MultipleDamageInDetection(d as usize, v),
+        };
 
-        Ok(())
+        Err(crate::Error::IOProcessError(e.to_string()))
     }
 
     fn wait_proc_id(&self) -> Option<usize> {
         None
     }
 }
+
+// Run hull program with given script, and get damage reported(if springdroid made it across hull).
+pub fn detect(
+    intcode: &[i64],
+    script: &[SSInstruction],
+    mode: DetectMode,
+) -> Result<Option<usize>, Error> {
+    let io_dev = SeqIODevice::new(HullDetector::new(script, mode));
+    let mut computer = SeqIntCodeComputer::new(false);
+    computer
+        .execute_with_io(intcode, io_dev.input_device(), io_dev.output_device())
+        .map_err(Error::ExecutionError)?;
+
+    Ok(io_dev.check(|d| d.damage()))
+}
+
+// Formula which made springdroid across hull, found by `synthesize`.
+pub struct Synthesis {
+    formula: Formula,
+    script: Vec<SSInstruction>,
+    damage: usize,
+    tried_n: usize,
+}
+
+impl Display for Synthesis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Formula({}) found after {} candidate(s), reporting {} unit(s) of damage:",
+            self.formula, self.tried_n, self.damage
+        )?;
+        for inst in &self.script {
+            writeln!(f, "{}", inst)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Synthesis {
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    pub fn script(&self) -> &[SSInstruction] {
+        &self.script
+    }
+
+    pub fn damage(&self) -> usize {
+        self.damage
+    }
+
+    pub fn tried_n(&self) -> usize {
+        self.tried_n
+    }
+}
+
+// Candidate formulas in form of "(!x || ...) && D && (y || ...)", springdroid jumps when there's a hole within
+// 3 tiles, D is ground for landing, and some tile after D is ground for moving on(optional).
+fn candidates(mode: DetectMode) -> Vec<Formula> {
+    fn subsets(sensors: &[RRegister]) -> Vec<Vec<RRegister>> {
+        (0..(1_usize << sensors.len()))
+            .map(|bits| {
+                sensors
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| bits & (1 << i) != 0)
+                    .map(|(_, &r)| r)
+                    .collect()
+            })
+            .collect()
+    }
+
+    let (holes, lands) = mode.sensors().split_at(3);
+    let (land, afters) = lands.split_first().unwrap();
+    let mut formulas = Vec::new();
+    for hole_sensors in subsets(holes).into_iter().filter(|s| !s.is_empty()) {
+        for after_sensors in subsets(afters) {
+            let mut operands = vec![
+                Formula::or(
+                    hole_sensors
+                        .iter()
+                        .map(|&r| Formula::negate(Formula::sensor(r)))
+                        .collect(),
+                ),
+                Formula::sensor(*land),
+            ];
+            if !after_sensors.is_empty() {
+                operands.push(Formula::or(
+                    after_sensors.into_iter().map(Formula::sensor).collect(),
+                ));
+            }
+            formulas.push(Formula::and(operands).normalize(false));
+        }
+    }
+
+    formulas
+}
+
+// Search candidate formulas from the shortest script, until one makes springdroid across hull.
+pub fn synthesize(intcode: &[i64], mode: DetectMode) -> Result<Synthesis, Error> {
+    let mut scripts = candidates(mode)
+        .into_iter()
+        .flat_map(|formula| formula.compile(mode).map(|script| (formula, script)))
+        .collect::<Vec<_>>();
+    scripts.sort_by_key(|(_, script)| script.len());
+
+    for (tried_n, (formula, script)) in scripts.iter().enumerate() {
+        if let Some(damage) = detect(intcode, script, mode)? {
+            return Ok(Synthesis {
+                formula: formula.clone(),
+                script: script.clone(),
+                damage,
+                tried_n: tried_n + 1,
+            });
+        }
+    }
+
+    Err(Error::NoScriptFound(mode, scripts.len()))
+}
+
+// Value of formula, and value of J after running its script from T and J being false, for every sensor reading.
+#[cfg(test)]
+fn eval_all(formula: &Formula, mode: DetectMode) -> Vec<(bool, bool)> {
+    fn eval(formula: &Formula, read: &dyn Fn(RRegister) -> bool) -> bool {
+        match formula {
+            Formula::Sensor(r) => read(*r),
+            Formula::Not(operand) => !eval(operand, read),
+            Formula::And(operands) => operands.iter().all(|o| eval(o, read)),
+            Formula::Or(operands) => operands.iter().any(|o| eval(o, read)),
+        }
+    }
+
+    let script = formula.compile(mode).unwrap();
+    let sensors = mode.sensors();
+    (0..(1_usize << sensors.len()))
+        .map(|bits| {
+            let ground = |r: RRegister| {
+                let ind = sensors.iter().position(|&s| s == r).unwrap();
+                bits & (1 << ind) != 0
+            };
+            let (mut t, mut j) = (false, false);
+            for inst in &script {
+                let (src, dst) = match *inst {
+                    SSInstruction::Or(src, dst)
+                    | SSInstruction::And(src, dst)
+                    | SSInstruction::Not(src, dst) => (src, dst),
+                };
+                let src = match src {
+                    RRegister::T => t,
+                    RRegister::J => j,
+                    r => ground(r),
+                };
+                let dst = match dst {
+                    WRegister::T => &mut t,
+                    WRegister::J => &mut j,
+                };
+                *dst = match inst {
+                    SSInstruction::Or(..) => *dst || src,
+                    SSInstruction::And(..) => *dst && src,
+                    SSInstruction::Not(..) => !src,
+                };
+            }
+
+            (eval(formula, &ground), j)
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_formula() {
+    let formula = "!A || B && !(C || D)".parse::<Formula>().unwrap();
+    assert_eq!(
+        formula,
+        Formula::or(vec![
+            Formula::negate(Formula::sensor(RRegister::A)),
+            Formula::and(vec![
+                Formula::sensor(RRegister::B),
+                Formula::negate(Formula::or(vec![
+                    Formula::sensor(RRegister::C),
+                    Formula::sensor(RRegister::D),
+                ])),
+            ]),
+        ])
+    );
+    assert_eq!(formula.to_string(), "!A || B && !(C || D)");
+    assert_eq!(
+        "(A||B)&&!!C".parse::<Formula>().unwrap().to_string(),
+        "(A || B) && !!C"
+    );
+
+    for invalid in ["", "A &&", "(A || B", "A B", "T", "J || A", "A | | B"] {
+        assert!(
+            matches!(invalid.parse::<Formula>(), Err(Error::InvalidFormula(_))),
+            "{}",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn test_compile_formula() {
+    let cases = [
+        ("D", DetectMode::Walk),
+        ("!A", DetectMode::Walk),
+        ("!(A && B)", DetectMode::Walk),
+        ("(!A || !B || !C) && D", DetectMode::Walk),
+        ("!(!A || B) || C && !D", DetectMode::Walk),
+        ("(A || B) && (C || D)", DetectMode::Walk),
+        ("(!A || !B || !C) && D && (E || H)", DetectMode::Run),
+        ("!A || !B && (E || !F) || D && I", DetectMode::Run),
+    ];
+    for (s, mode) in cases {
+        let formula = s.parse::<Formula>().unwrap();
+        for (expect, jump) in eval_all(&formula, mode) {
+            assert_eq!(jump, expect, "{}", s);
+        }
+    }
+
+    for mode in [DetectMode::Walk, DetectMode::Run] {
+        for formula in candidates(mode) {
+            for (expect, jump) in eval_all(&formula, mode) {
+                assert_eq!(jump, expect, "{}", formula);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compile_factorable_formula() {
+    let cases = [
+        ("(A && D) || (B && D) || (C && D)", 4),
+        ("(!A && D) || (!B && D) || (!C && D)", 5),
+        ("(A || !D) && (B || !D) && (C || !D)", 5),
+        ("(A && B) || (A && C)", 3),
+    ];
+    for (s, len) in cases {
+        let formula = s.parse::<Formula>().unwrap();
+        assert_eq!(
+            formula.compile(DetectMode::Walk).unwrap().len(),
+            len,
+            "{}",
+            s
+        );
+        for (expect, jump) in eval_all(&formula, DetectMode::Walk) {
+            assert_eq!(jump, expect, "{}", s);
+        }
+    }
+}
+
+#[test]
+fn test_compile_formula_errors() {
+    assert!(matches!(
+        "A && E"
+            .parse::<Formula>()
+            .unwrap()
+            .compile(DetectMode::Walk),
+        Err(Error::UnavailableSensor(RRegister::E, DetectMode::Walk))
+    ));
+    // Both operands need a scratch register besides the one they are folded into.
+    assert!(matches!(
+        "(A && B || C && D) && (E && F || G && H)"
+            .parse::<Formula>()
+            .unwrap()
+            .compile(DetectMode::Run),
+        Err(Error::UncompilableFormula(_))
+    ));
+    assert!(matches!(
+        "(!A || !B) && (!C || !D) && (!E || !F) && (!G || !H) && (!I || A) && (!B || C)"
+            .parse::<Formula>()
+            .unwrap()
+            .compile(DetectMode::Run),
+        Err(Error::ScriptTooLong(_))
+    ));
+}
@@ -1,15 +1,18 @@
 use std::{
     cell::RefCell,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     env,
     fmt::Display,
-    io,
+    fs, io,
     rc::Rc,
+    sync::{Arc, Mutex},
 };
 
 use crate::int_code::{
-    com::SeqIntCodeComputer,
-    io::{InputPort, OutputPort, SeqIODevice},
+    com::{ConcIntCodeComputer, SeqIntCodeComputer},
+    io::{InputPort, OutputPort, ParaIODevice, SeqIODevice},
+    sched::ScheduleStats,
+    topo::{Routing, Topology},
 };
 
 #[derive(Debug)]
@@ -19,6 +22,7 @@ pub enum Error {
     ExecutionError(crate::Error),
     SendDataBeforeWorking(i64),
     InvalidSendAddr(i64),
+    InvalidArg(String),
 }
 
 impl Display for Error {
@@ -41,6 +45,7 @@ impl Display for Error {
             Error::InvalidSendAddr(addr) => {
                 write!(f, "Try to send packet to invalid address({})", addr)
             }
+            Error::InvalidArg(arg) => write!(f, "Invalid argument({})", arg),
         }
     }
 }
@@ -84,6 +89,230 @@ impl Packet {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum TraceEvent {
+    // Packet sent by a card or NAT.
+    Send(Packet),
+    // Packet received by a card.
+    Recv(Packet),
+    // All cards are found waiting for packets.
+    Idle,
+    // NAT sends its packet to wake up idle network.
+    NatWakeup(Packet),
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceRecord {
+    round: usize,
+    event: TraceEvent,
+}
+
+impl TraceRecord {
+    // Scheduler round in which event happens.
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn event(&self) -> &TraceEvent {
+        &self.event
+    }
+
+    pub fn to_json(&self) -> String {
+        let pac_fields = |pac: &Packet| {
+            format!(
+                r#","from":{},"to":{},"x":{},"y":{}"#,
+                pac.from(),
+                pac.to(),
+                pac.x(),
+                pac.y()
+            )
+        };
+        let (name, fields) = match &self.event {
+            TraceEvent::Send(pac) => ("send", pac_fields(pac)),
+            TraceEvent::Recv(pac) => ("recv", pac_fields(pac)),
+            TraceEvent::Idle => ("idle", String::new()),
+            TraceEvent::NatWakeup(pac) => ("nat_wakeup", pac_fields(pac)),
+        };
+
+        format!(r#"{{"round":{},"event":"{}"{}}}"#, self.round, name, fields)
+    }
+}
+
+// Every event happened in network in order.
+#[derive(Debug, Default)]
+pub struct NetworkTrace {
+    records: Vec<TraceRecord>,
+}
+
+impl NetworkTrace {
+    pub fn records(&self) -> &[TraceRecord] {
+        &self.records
+    }
+
+    fn push(&mut self, round: usize, event: TraceEvent) {
+        self.records.push(TraceRecord { round, event });
+    }
+
+    pub fn sent_pacs(&self) -> impl Iterator<Item = (usize, &Packet)> {
+        self.records.iter().filter_map(|r| match &r.event {
+            TraceEvent::Send(pac) => Some((r.round, pac)),
+            _ => None,
+        })
+    }
+
+    pub fn pacs_to(&self, addr: usize) -> impl Iterator<Item = (usize, &Packet)> {
+        self.sent_pacs().filter(move |(_, pac)| pac.to() == addr)
+    }
+
+    pub fn first_pac_to(&self, addr: usize) -> Option<&Packet> {
+        self.pacs_to(addr).next().map(|(_, pac)| pac)
+    }
+
+    pub fn pacs_from(&self, addr: usize) -> impl Iterator<Item = (usize, &Packet)> {
+        self.sent_pacs().filter(move |(_, pac)| pac.from() == addr)
+    }
+
+    pub fn idle_rounds(&self) -> Vec<usize> {
+        self.records
+            .iter()
+            .filter(|r| matches!(r.event, TraceEvent::Idle))
+            .map(|r| r.round)
+            .collect()
+    }
+
+    pub fn nat_wakeups(&self) -> impl Iterator<Item = (usize, &Packet)> {
+        self.records.iter().filter_map(|r| match &r.event {
+            TraceEvent::NatWakeup(pac) => Some((r.round, pac)),
+            _ => None,
+        })
+    }
+
+    pub fn to_json_lines(&self) -> String {
+        self.records.iter().map(|r| r.to_json() + "\n").collect()
+    }
+
+    // Mermaid sequence diagram of sent packets, idle network and NAT wakeups, at most given number of events.
+    pub fn to_mermaid(&self, max_event_n: usize) -> String {
+        let records = self
+            .records
+            .iter()
+            .filter(|r| !matches!(r.event, TraceEvent::Recv(_)))
+            .take(max_event_n)
+            .collect::<Vec<_>>();
+        let addrs = records
+            .iter()
+            .flat_map(|r| match &r.event {
+                TraceEvent::Send(pac) | TraceEvent::NatWakeup(pac) => vec![pac.from(), pac.to()],
+                _ => Vec::new(),
+            })
+            .collect::<BTreeSet<_>>();
+
+        let mut text = String::from("sequenceDiagram\n");
+        for addr in &addrs {
+            text += &format!("    participant A{} as {}\n", addr, addr);
+        }
+        for record in records {
+            match &record.event {
+                TraceEvent::Send(pac) => {
+                    text += &format!(
+                        "    A{}->>A{}: x={}, y={} @{}\n",
+                        pac.from(),
+                        pac.to(),
+                        pac.x(),
+                        pac.y(),
+                        record.round
+                    )
+                }
+                TraceEvent::Idle => {
+                    if let (Some(first), Some(last)) = (addrs.first(), addrs.last()) {
+                        text += &format!(
+                            "    Note over A{},A{}: idle @{}\n",
+                            first, last, record.round
+                        );
+                    }
+                }
+                TraceEvent::NatWakeup(pac) => {
+                    text += &format!(
+                        "    Note over A{}: wake up A{} @{}\n",
+                        pac.from(),
+                        pac.to(),
+                        record.round
+                    )
+                }
+                TraceEvent::Recv(_) => (),
+            }
+        }
+
+        text
+    }
+
+    // Graphviz graph of addresses, each edge is labeled by number of packets sent along it.
+    pub fn to_graphviz(&self) -> String {
+        let mut edges = BTreeMap::new();
+        for (_, pac) in self.sent_pacs() {
+            *edges.entry((pac.from(), pac.to())).or_insert(0) += 1;
+        }
+
+        let mut text = String::from("digraph network {\n");
+        for ((from, to), n) in edges {
+            text += &format!("    {} -> {} [label=\"{}\"];\n", from, to, n);
+        }
+        text += "}\n";
+
+        text
+    }
+
+    pub fn export(&self, options: &TraceOptions) -> Result<(), Error> {
+        let write = |path: &Option<String>, text: String| {
+            path.as_ref()
+                .map_or(Ok(()), |p| fs::write(p, text).map_err(Error::IOError))
+        };
+        write(&options.json_path, self.to_json_lines())?;
+        write(&options.mermaid_path, self.to_mermaid(options.max_event_n))?;
+        write(&options.dot_path, self.to_graphviz())
+    }
+}
+
+// Files trace is exported into.
+#[derive(Debug)]
+pub struct TraceOptions {
+    pub json_path: Option<String>,
+    pub mermaid_path: Option<String>,
+    pub dot_path: Option<String>,
+    pub max_event_n: usize,
+}
+
+impl TraceOptions {
+    // Read options from arguments after input path: [--json {path}] [--mermaid {path}] [--dot {path}] [--max-events {n}]
+    pub fn from_args() -> Result<Self, Error> {
+        let mut options = TraceOptions {
+            json_path: None,
+            mermaid_path: None,
+            dot_path: None,
+            max_event_n: 200,
+        };
+        let mut args = env::args().skip(2);
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or(Error::InvalidArg(arg.clone()))?;
+            match arg.as_str() {
+                "--json" => options.json_path = Some(value),
+                "--mermaid" => options.mermaid_path = Some(value),
+                "--dot" => options.dot_path = Some(value),
+                "--max-events" => {
+                    options.max_event_n = value.parse().map_err(|_| Error::InvalidArg(value))?
+                }
+                _ => return Err(Error::InvalidArg(arg)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.json_path.is_none() && self.mermaid_path.is_none() && self.dot_path.is_none()
+    }
+}
+
 #[derive(Debug)]
 pub struct NetworkNAT {
     addr: usize,
@@ -133,6 +362,9 @@ pub struct NetworkHub {
     ports: HashMap<usize, Port>,
     card_addrs: HashSet<usize>,
     nat_op: Option<NetworkNAT>,
+    trace: NetworkTrace,
+    slice_n: usize,
+    was_idle: bool,
 }
 
 impl NetworkHub {
@@ -141,6 +373,9 @@ impl NetworkHub {
             ports: HashMap::new(),
             card_addrs: HashSet::new(),
             nat_op: None,
+            trace: NetworkTrace::default(),
+            slice_n: 0,
+            was_idle: false,
         }
     }
 
@@ -155,6 +390,19 @@ impl NetworkHub {
         self.nat_op.as_ref()
     }
 
+    pub fn trace(&self) -> &NetworkTrace {
+        &self.trace
+    }
+
+    // Count one time slice of scheduler, every card gets one slice in a round.
+    pub fn tick(&mut self) {
+        self.slice_n += 1;
+    }
+
+    pub fn round(&self) -> usize {
+        self.slice_n / self.card_addrs.len().max(1)
+    }
+
     pub fn recv_pac_log(&self, to_addr: usize, ind: usize) -> Option<Packet> {
         self.ports
             .get(&to_addr)
@@ -171,7 +419,7 @@ impl NetworkHub {
         self.ports.is_empty()
     }
 
-    pub fn connect(&mut self, card: &NICard) {
+    pub fn connect<H: HubRef>(&mut self, card: &NICard<H>) {
         let addr = card.addr();
         if !self.card_addrs.insert(addr) {
             // Connect to a card twice.
@@ -185,6 +433,8 @@ impl NetworkHub {
 
     pub fn send(&mut self, packet: Packet) {
         println!("Send: {}", packet);
+        self.trace
+            .push(self.round(), TraceEvent::Send(packet.clone()));
         if let Some(nat) = self.nat_op.as_mut() {
             if packet.to() == nat.addr() {
                 nat.recv(packet);
@@ -199,16 +449,27 @@ impl NetworkHub {
     }
 
     pub fn recv(&mut self, addr: usize) -> Option<Packet> {
-        if !self.is_empty() && self.is_idle() {
+        let is_idle = !self.is_empty() && self.is_idle();
+        if is_idle && !self.was_idle {
+            self.trace.push(self.round(), TraceEvent::Idle);
+        }
+        self.was_idle = is_idle;
+        if is_idle {
             if let Some(pac) = self.nat_op.as_mut().and_then(|nat| nat.send()) {
+                self.trace
+                    .push(self.round(), TraceEvent::NatWakeup(pac.clone()));
                 self.send(pac);
             }
         }
 
+        let round = self.round();
         self.ports
             .get_mut(&addr)
             .and_then(|pb| pb.recv())
-            .inspect(|p| println!("Receive: {}", p))
+            .inspect(|p| {
+                println!("Receive: {}", p);
+                self.trace.push(round, TraceEvent::Recv(p.clone()));
+            })
     }
 }
 
@@ -249,6 +510,32 @@ impl Port {
 }
 
 type NetworkHubRef = Rc<RefCell<NetworkHub>>;
+type ConcNetworkHubRef = Arc<Mutex<NetworkHub>>;
+
+// Shared reference to network hub, for cards running in one thread or concurrently.
+pub trait HubRef {
+    fn with_hub<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut NetworkHub) -> R;
+}
+
+impl HubRef for NetworkHubRef {
+    fn with_hub<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut NetworkHub) -> R,
+    {
+        f(&mut self.borrow_mut())
+    }
+}
+
+impl HubRef for ConcNetworkHubRef {
+    fn with_hub<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut NetworkHub) -> R,
+    {
+        f(&mut self.lock().unwrap())
+    }
+}
 
 enum NICardState {
     InitAddr,
@@ -306,16 +593,16 @@ impl PacketAssembler {
     }
 }
 
-pub struct NICard {
+pub struct NICard<H: HubRef = NetworkHubRef> {
     addr: usize,
-    hub: NetworkHubRef,
+    hub: H,
     pac_asm: PacketAssembler,
     state: NICardState,
     recv_state: NICardRecvState,
 }
 
-impl NICard {
-    pub fn new(addr: usize, hub: NetworkHubRef) -> Self {
+impl<H: HubRef> NICard<H> {
+    pub fn new(addr: usize, hub: H) -> Self {
         Self {
             addr,
             hub,
@@ -330,7 +617,7 @@ impl NICard {
     }
 }
 
-impl InputPort for NICard {
+impl<H: HubRef> InputPort for NICard<H> {
     fn get(&mut self) -> Option<i64> {
         match self.state {
             NICardState::InitAddr => {
@@ -339,7 +626,7 @@ impl InputPort for NICard {
             }
             NICardState::Working => match self.recv_state {
                 NICardRecvState::Idle => {
-                    if let Some(packet) = self.hub.borrow_mut().recv(self.addr) {
+                    if let Some(packet) = self.hub.with_hub(|hub| hub.recv(self.addr)) {
                         self.recv_state = NICardRecvState::RecvY(packet.y());
                         Some(packet.x())
                     } else {
@@ -358,7 +645,7 @@ impl InputPort for NICard {
     fn reg_proc(&mut self, _proc_id: usize) {}
 }
 
-impl OutputPort for NICard {
+impl<H: HubRef> OutputPort for NICard<H> {
     fn put(&mut self, value: i64) -> Result<(), crate::Error> {
         match self.state {
             NICardState::InitAddr => Err(crate::Error::IOProcessError(
@@ -370,7 +657,7 @@ impl OutputPort for NICard {
                     .assemble(value)
                     .map_err(|e| crate::Error::IOProcessError(e.to_string()))?
                 {
-                    self.hub.borrow_mut().send(packet)
+                    self.hub.with_hub(|hub| hub.send(packet))
                 }
 
                 Ok(())
@@ -383,40 +670,83 @@ impl OutputPort for NICard {
     }
 }
 
+// Input path is the first argument, others are left for trace options.
 pub fn check_args() -> Result<String, Error> {
     let args = env::args();
     let args_n = args.len();
-    if args_n != 2 {
+    if args_n < 2 {
         Err(Error::WrongNumberOfArgs(args_n, 2))
     } else {
         Ok(args.skip(1).next().unwrap().to_string())
     }
 }
 
-pub fn run_network(host_n: usize, intcode: &Vec<i64>) -> Result<NetworkHub, Error> {
-    let mut computer = SeqIntCodeComputer::new(false);
+pub fn run_network(host_n: usize, intcode: &[i64]) -> Result<NetworkHub, Error> {
+    run_network_on(&mut SeqIntCodeComputer::new(false), host_n, intcode)
+}
+
+// Run network on given computer, so its configuration(e.g. execution engine) can be chosen by caller.
+pub fn run_network_on(
+    computer: &mut SeqIntCodeComputer,
+    host_n: usize,
+    intcode: &[i64],
+) -> Result<NetworkHub, Error> {
     let mut proc_ids = Vec::new();
     let hub = Rc::new(RefCell::new(NetworkHub::new()));
     for i in 0..host_n {
         let card = NICard::new(i, hub.clone());
         hub.borrow_mut().connect(&card);
         let io_dev = SeqIODevice::new(card);
-        let cur_proc_id =
-            computer.new_proc(&intcode, io_dev.input_device(), io_dev.output_device());
+        let cur_proc_id = computer.new_proc(intcode, io_dev.input_device(), io_dev.output_device());
         proc_ids.push(cur_proc_id);
     }
 
     computer
         .exe_procs_pmp_cond(&proc_ids, proc_ids[0], 100, || {
+            hub.borrow_mut().tick();
             !hub.borrow().is_empty() && hub.borrow().is_idle()
         })
         .map(|_| Rc::try_unwrap(hub).unwrap().into_inner())
         .map_err(Error::ExecutionError)
 }
 
+// Run network as a topology in which every card sends (address, x, y) packets to each other, until the first
+// packet is sent to given address outside of the network, and return it.
+pub fn first_packet_to(
+    computer: &mut SeqIntCodeComputer,
+    host_n: usize,
+    intcode: &[i64],
+    addr: usize,
+) -> Result<Option<Packet>, Error> {
+    let is_sent_to_addr =
+        |(_, to_addr, _): &(usize, i64, Vec<i64>)| usize::try_from(*to_addr) == Ok(addr);
+    let topo = (0..host_n).fold(
+        Topology::mesh(host_n)
+            .with_routing(Routing::Packet(3))
+            .with_idle_input(-1),
+        |topo, i| topo.with_input(i, &[i as i64]),
+    );
+    let res = topo
+        .run_until(computer, intcode, 100, |output| {
+            output.undelivered().iter().any(is_sent_to_addr)
+        })
+        .map_err(Error::ExecutionError)?;
+
+    Ok(res
+        .undelivered()
+        .iter()
+        .find(|pac| is_sent_to_addr(pac))
+        .map(|(from_addr, _, values)| Packet {
+            from_addr: *from_addr,
+            to_addr: addr,
+            x: values[0],
+            y: values[1],
+        }))
+}
+
 pub fn run_network_nat(
     host_n: usize,
-    intcode: &Vec<i64>,
+    intcode: &[i64],
     nat_addr: usize,
     nat_send_addr: usize,
 ) -> Result<NetworkHub, Error> {
@@ -434,13 +764,107 @@ pub fn run_network_nat(
 
     computer
         .exe_procs_pmp_cond(&proc_ids, proc_ids[0], 100, || {
-            let hub = hub.borrow();
-            let sent_pacs = hub.nat().unwrap().sent_pacs();
-            let mut rev_pac_iter = sent_pacs.iter().rev();
-            rev_pac_iter
-                .next()
-                .is_some_and(|p0| rev_pac_iter.next().is_some_and(|p1| p0.y() == p1.y()))
+            hub.borrow_mut().tick();
+            is_nat_sent_y_repeated(&hub.borrow())
         })
         .map(|_| Rc::try_unwrap(hub).unwrap().into_inner())
         .map_err(Error::ExecutionError)
 }
+
+// Run network under schedule policy of given computer, with NAT if its (address, send address) is given. Network
+// stops like run_network or run_network_nat, or after given count of time slices, as some policies may starve cards.
+pub fn run_network_sched(
+    computer: &mut SeqIntCodeComputer,
+    host_n: usize,
+    intcode: &[i64],
+    nat_op: Option<(usize, usize)>,
+    max_slice_n: usize,
+) -> Result<(NetworkHub, ScheduleStats), Error> {
+    let mut proc_ids = Vec::new();
+    let hub = match nat_op {
+        Some((nat_addr, nat_send_addr)) => NetworkHub::with_nat(nat_addr, nat_send_addr),
+        None => NetworkHub::new(),
+    };
+    let hub = Rc::new(RefCell::new(hub));
+    for i in 0..host_n {
+        let card = NICard::new(i, hub.clone());
+        hub.borrow_mut().connect(&card);
+        let io_dev = SeqIODevice::new(card);
+        let cur_proc_id = computer.new_proc(intcode, io_dev.input_device(), io_dev.output_device());
+        proc_ids.push(cur_proc_id);
+    }
+
+    let mut slice_n = 0;
+    computer
+        .exe_procs_sched(&proc_ids, || {
+            hub.borrow_mut().tick();
+            slice_n += 1;
+            let hub = hub.borrow();
+            slice_n > max_slice_n
+                || match nat_op {
+                    Some(_) => is_nat_sent_y_repeated(&hub),
+                    None => !hub.is_empty() && hub.is_idle(),
+                }
+        })
+        .map(|(_, stats)| (Rc::try_unwrap(hub).unwrap().into_inner(), stats))
+        .map_err(Error::ExecutionError)
+}
+
+fn is_nat_sent_y_repeated(hub: &NetworkHub) -> bool {
+    let sent_pacs = hub.nat().unwrap().sent_pacs();
+    let mut rev_pac_iter = sent_pacs.iter().rev();
+    rev_pac_iter
+        .next()
+        .is_some_and(|p0| rev_pac_iter.next().is_some_and(|p1| p0.y() == p1.y()))
+}
+
+// Same as run_network, but every card runs concurrently in its own thread.
+pub fn run_network_conc(host_n: usize, intcode: &[i64]) -> Result<NetworkHub, Error> {
+    run_network_conc_until(NetworkHub::new(), host_n, intcode, |hub| {
+        !hub.is_empty() && hub.is_idle()
+    })
+}
+
+// Same as run_network_nat, but every card runs concurrently in its own thread.
+pub fn run_network_nat_conc(
+    host_n: usize,
+    intcode: &[i64],
+    nat_addr: usize,
+    nat_send_addr: usize,
+) -> Result<NetworkHub, Error> {
+    run_network_conc_until(
+        NetworkHub::with_nat(nat_addr, nat_send_addr),
+        host_n,
+        intcode,
+        is_nat_sent_y_repeated,
+    )
+}
+
+fn run_network_conc_until<F>(
+    hub: NetworkHub,
+    host_n: usize,
+    intcode: &[i64],
+    stop_cond: F,
+) -> Result<NetworkHub, Error>
+where
+    F: Fn(&NetworkHub) -> bool,
+{
+    let mut computer = ConcIntCodeComputer::new();
+    let hub = Arc::new(Mutex::new(hub));
+    for i in 0..host_n {
+        let card = NICard::new(i, hub.clone());
+        hub.lock().unwrap().connect(&card);
+        let io_dev = ParaIODevice::new(card);
+        computer.spawn(intcode, io_dev.input_device(), io_dev.output_device());
+    }
+
+    computer
+        .join_until(|| {
+            // Rounds are counted by checks of stop condition, as cards aren't scheduled in turn.
+            let mut hub = hub.lock().unwrap();
+            hub.tick();
+            stop_cond(&hub)
+        })
+        .map(|_| Arc::try_unwrap(hub).unwrap().into_inner().unwrap())
+        .map_err(Error::ExecutionError)
+}
@@ -1,19 +1,31 @@
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, HashSet, VecDeque},
     env, error,
     fmt::Display,
+    fs,
     io::{self, stdin, stdout, Write},
-    iter,
+    path::Path,
 };
 
 use regex::Regex;
 
-use crate::int_code::io::{InputPort, OutputPort};
+use crate::int_code::{
+    com::{ProcessSnapshot, SeqIntCodeComputer},
+    dbg::StopReason,
+    io::{AsciiTerminal, InputPort, OutputPort, SeqIODevice},
+};
 
 #[derive(Debug)]
 pub enum Error {
     IOError(io::Error),
     WrongNumberOfArgs(usize, usize), // (number of given arguments, expected number of arguments),
+    InvalidArgs(String),
+    ExecutionError(crate::Error),
+    CommandStepLimitExceeded(String),
+    UnknownRoom(String),
+    NoPathToRoom(String, String),
+    CheckpointNotFound,
+    NoPassingItemCombination(Vec<String>),
 }
 
 impl Display for Error {
@@ -25,15 +37,119 @@ impl Display for Error {
                 "Given wrong number({}) of arguemnts, expect {}",
                 real_n, expect_n
             ),
+            Error::InvalidArgs(s) => write!(f, "Invalid arguments({})", s),
+            Error::ExecutionError(ee) => {
+                write!(f, "Get error({}) in execution of given intcode program", ee)
+            }
+            Error::CommandStepLimitExceeded(com) => {
+                write!(f, "Droid didn't finish command({}) in time", com)
+            }
+            Error::UnknownRoom(output) => {
+                write!(f, "Can't find room in output of droid({})", output)
+            }
+            Error::NoPathToRoom(from, to) => {
+                write!(f, "Can't find path from room({}) to room({})", from, to)
+            }
+            Error::CheckpointNotFound => write!(f, "Can't find security checkpoint"),
+            Error::NoPassingItemCombination(items) => write!(
+                f,
+                "None of combinations of items({:?}) can pass pressure-sensitive floor",
+                items
+            ),
         }
     }
 }
 
 impl error::Error for Error {}
+
+// Room described by droid after moving into it or looking around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Room {
+    name: String,
+    desc: String,
+    doors: Vec<String>,
+    items: Vec<String>,
+}
+
+impl Display for Room {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (doors: {}; items: {})",
+            self.name,
+            self.doors.join(", "),
+            self.items.join(", ")
+        )
+    }
+}
+
+impl Room {
+    // Parse the last room described in given output of droid.
+    pub fn parse_last(output: &str) -> Option<Room> {
+        let start = output.rfind("== ")?;
+        let mut lines = output[start..].lines();
+        let name = lines
+            .next()?
+            .trim()
+            .strip_prefix("== ")?
+            .strip_suffix(" ==")?
+            .to_string();
+        let mut room = Room {
+            name,
+            desc: String::new(),
+            doors: Vec::new(),
+            items: Vec::new(),
+        };
+        let mut list = None;
+        for line in lines.map(|l| l.trim()) {
+            match line {
+                "Doors here lead:" => list = Some(&mut room.doors),
+                "Items here:" => list = Some(&mut room.items),
+                "" => list = None,
+                "Command?" => break,
+                _ => match (line.strip_prefix("- "), list.as_mut()) {
+                    (Some(entry), Some(list)) => list.push(entry.to_string()),
+                    _ if room.desc.is_empty() => room.desc = line.to_string(),
+                    _ => (),
+                },
+            }
+        }
+
+        Some(room)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn desc(&self) -> &str {
+        &self.desc
+    }
+
+    pub fn doors(&self) -> &[String] {
+        &self.doors
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+}
+
+// Door in the opposite direction of given one.
+fn back_door(door: &str) -> &str {
+    match door {
+        "north" => "south",
+        "south" => "north",
+        "west" => "east",
+        "east" => "west",
+        _ => door,
+    }
+}
 pub struct DroidConsole {
-    input_buf: VecDeque<char>,
-    output_buf: String,
-    log: String,
+    term: AsciiTerminal,
+    script: VecDeque<String>,
+    screen: String,
+    cur_room: Option<Room>,
     com_patterns: Vec<(Regex, Option<String>)>, // <command pattern in regular expression, candidate complete command string>
     is_end: bool,
 }
@@ -42,22 +158,58 @@ impl DroidConsole {
     pub fn new() -> Self {
         let com_patterns = Self::init_coms();
         Self {
-            input_buf: VecDeque::new(),
-            output_buf: String::new(),
-            log: String::new(),
+            term: AsciiTerminal::new(),
+            script: VecDeque::new(),
+            screen: String::new(),
+            cur_room: None,
             com_patterns,
             is_end: false,
         }
     }
 
+    // Console which runs given commands first, then reads commands from stdin.
+    pub fn with_script<I: IntoIterator<Item = String>>(commands: I) -> Self {
+        let mut console = Self::new();
+        console.script.extend(commands);
+        console
+    }
+
+    // Load commands from script file, one command per line, empty lines and lines starting with '#' are ignored.
+    pub fn load_script<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let content = fs::read_to_string(path).map_err(Error::IOError)?;
+        Ok(Self::with_script(
+            content
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(|l| l.to_string()),
+        ))
+    }
+
+    pub fn log(&self) -> String {
+        self.term.log()
+    }
+
+    // The last room described by droid.
+    pub fn cur_room(&self) -> Option<&Room> {
+        self.cur_room.as_ref()
+    }
+
     fn req_command(&mut self) {
         let mut buf = String::new();
         loop {
             buf.clear();
             print!("DC> ");
             stdout().flush().unwrap();
-            if let Ok(_) = stdin()
-                .read_line(&mut buf)
+            let read_res = match self.script.pop_front() {
+                // Command is echoed when it's read by droid.
+                Some(command) => {
+                    buf.push_str(&command);
+                    Ok(command.len())
+                }
+                None => stdin().read_line(&mut buf),
+            };
+            if let Ok(_) = read_res
                 .inspect_err(|e| eprintln!("Failed to read input from console, get error({}).", e))
             {
                 let trimmed_buf = buf.trim_end();
@@ -75,8 +227,7 @@ impl DroidConsole {
                     .next()
                 {
                     // Found first matched command pattern.
-                    self.input_buf
-                        .extend(command.chars().chain(iter::once('\n')));
+                    self.term.push_line(command);
                     if command == "exit" {
                         self.is_end = true;
                     }
@@ -114,18 +265,16 @@ impl DroidConsole {
 
 impl InputPort for DroidConsole {
     fn get(&mut self) -> Option<i64> {
-        if self.input_buf.is_empty() {
+        if !self.term.has_input() {
             self.req_command();
         }
 
         if self.is_end {
             None
         } else {
-            self.input_buf.pop_front().map(|c| {
-                // Echo and log input.
-                print!("{}", c);
-                self.log.push(c);
-                c as i64
+            self.term.get().inspect(|&c| {
+                // Echo input.
+                print!("{}", c as u8 as char);
             })
         }
     }
@@ -135,12 +284,16 @@ impl InputPort for DroidConsole {
 
 impl OutputPort for DroidConsole {
     fn put(&mut self, value: i64) -> Result<(), crate::Error> {
-        if let Some(c) = u32::try_from(value).ok().and_then(|u| char::from_u32(u)) {
-            self.output_buf.push(c);
-            if c == '\n' {
-                print!("{}", self.output_buf);
-                self.log.push_str(&self.output_buf);
-                self.output_buf.clear();
+        self.term.put(value)?;
+        while let Some(line) = self.term.pop_line() {
+            println!("{}", line);
+            self.screen.push_str(&line);
+            self.screen.push('\n');
+            if line.trim_end() == "Command?" {
+                if let Some(room) = Room::parse_last(&self.screen) {
+                    self.cur_room = Some(room);
+                }
+                self.screen.clear();
             }
         }
 
@@ -152,6 +305,26 @@ impl OutputPort for DroidConsole {
     }
 }
 
+// How droid is controlled.
+pub enum ConsoleMode {
+    Interactive,
+    Script(String), // path of script file
+    Auto,
+}
+
+// Parse arguments as "{input path} [--script {script path} | --auto]".
+pub fn parse_args() -> Result<(String, ConsoleMode), Error> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.as_slice() {
+        [input_path] => Ok((input_path.clone(), ConsoleMode::Interactive)),
+        [input_path, flag] if flag == "--auto" => Ok((input_path.clone(), ConsoleMode::Auto)),
+        [input_path, flag, script_path] if flag == "--script" => {
+            Ok((input_path.clone(), ConsoleMode::Script(script_path.clone())))
+        }
+        _ => Err(Error::InvalidArgs(args.join(" "))),
+    }
+}
+
 pub fn check_args() -> Result<String, Error> {
     let args = env::args();
     let args_n = args.len();
@@ -161,3 +334,312 @@ pub fn check_args() -> Result<String, Error> {
         Ok(args.skip(1).next().unwrap().to_string())
     }
 }
+
+// Maximum steps droid can take to finish one command, taking some items makes it run forever.
+const COMMAND_STEP_LIMIT: usize = 1_000_000;
+
+// Droid driven by program instead of console, its process can be saved and restored to try dangerous commands.
+struct Droid {
+    computer: SeqIntCodeComputer,
+    proc_id: usize,
+    io_dev: SeqIODevice<AsciiTerminal>,
+    is_halted: bool,
+    log: String,
+}
+
+impl Droid {
+    // Start droid and return its first output.
+    fn start(intcode: &[i64]) -> Result<(Self, String), Error> {
+        let mut computer = SeqIntCodeComputer::new(false);
+        let io_dev = SeqIODevice::new(AsciiTerminal::new());
+        let proc_id = computer.new_proc(intcode, io_dev.input_device(), io_dev.output_device());
+        let mut droid = Self {
+            computer,
+            proc_id,
+            io_dev,
+            is_halted: false,
+            log: String::new(),
+        };
+        let output = droid.run("")?;
+
+        Ok((droid, output))
+    }
+
+    // Send command to droid, and return its output until it requires next command or halts.
+    fn send(&mut self, command: &str) -> Result<String, Error> {
+        self.log.push_str(command);
+        self.log.push('\n');
+        self.io_dev.tweak(|t| t.push_line(command));
+        self.run(command)
+    }
+
+    fn run(&mut self, command: &str) -> Result<String, Error> {
+        let reason = self
+            .computer
+            .run_proc(self.proc_id, Some(COMMAND_STEP_LIMIT))
+            .map_err(Error::ExecutionError)?;
+        let output = self.io_dev.tweak(|t| t.take_text());
+        self.log.push_str(&output);
+        match reason {
+            StopReason::StepLimit => Err(Error::CommandStepLimitExceeded(command.to_string())),
+            reason => {
+                self.is_halted = reason == StopReason::Halt;
+                Ok(output)
+            }
+        }
+    }
+
+    fn save(&self) -> ProcessSnapshot {
+        self.computer.snapshot_proc(self.proc_id).unwrap()
+    }
+
+    fn restore(&mut self, snapshot: &ProcessSnapshot) -> Result<(), Error> {
+        self.computer.take_proc_result(self.proc_id);
+        self.io_dev.tweak(|t| t.reset());
+        self.proc_id = self
+            .computer
+            .restore_proc(
+                snapshot,
+                self.io_dev.input_device(),
+                self.io_dev.output_device(),
+            )
+            .map_err(Error::ExecutionError)?;
+        self.is_halted = false;
+
+        Ok(())
+    }
+
+    // Try to take item, and keep it only if droid is still alive and can move after taking it.
+    fn take_if_safe(&mut self, item: &str, room: &Room) -> Result<bool, Error> {
+        let snapshot = self.save();
+        let is_safe = match self.send(&format!("take {}", item)) {
+            Ok(output) if !self.is_halted && output.contains("You take the") => {
+                self.can_move(room)?
+            }
+            Ok(_) | Err(Error::CommandStepLimitExceeded(_)) => false,
+            Err(e) => return Err(e),
+        };
+        if !is_safe {
+            self.restore(&snapshot)?;
+        }
+
+        Ok(is_safe)
+    }
+
+    // Move through the first door of given room and back.
+    fn can_move(&mut self, room: &Room) -> Result<bool, Error> {
+        let door = match room.doors.first() {
+            Some(door) => door,
+            None => return Ok(true),
+        };
+        let output = self.send(door)?;
+        if self.is_halted || output.contains("can't move") {
+            return Ok(false);
+        }
+
+        // Droid may be sent back to the same room, e.g. by pressure-sensitive floor.
+        if Room::parse_last(&output).is_some_and(|r| r.name != room.name) {
+            self.send(back_door(door))?;
+        }
+
+        Ok(!self.is_halted)
+    }
+}
+
+// Map of ship found by explorer, and the result of passing security checkpoint.
+#[derive(Debug, Default)]
+pub struct ExploreReport {
+    rooms: Vec<Room>,
+    links: HashMap<String, Vec<(String, String)>>, // room name => [(door, room name of the other side)]
+    items: Vec<String>,
+    unsafe_items: Vec<String>,
+    checkpoint: Option<(String, String)>, // (room name, door to pressure-sensitive floor)
+    passing_items: Vec<String>,
+    password: Option<String>,
+    log: String,
+}
+
+impl Display for ExploreReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Rooms:")?;
+        for room in &self.rooms {
+            writeln!(f, "  {}", room)?;
+            for (door, to) in self.links(&room.name) {
+                writeln!(f, "    {} -> {}", door, to)?;
+            }
+        }
+        writeln!(f, "Collected items: {}", self.items.join(", "))?;
+        writeln!(f, "Unsafe items: {}", self.unsafe_items.join(", "))?;
+        if let Some((room, door)) = &self.checkpoint {
+            writeln!(f, "Security checkpoint: {} (floor to {})", room, door)?;
+        }
+        writeln!(f, "Items passing floor: {}", self.passing_items.join(", "))?;
+        match &self.password {
+            Some(password) => write!(f, "Password: {}", password),
+            None => write!(f, "Password: unknown"),
+        }
+    }
+}
+
+impl ExploreReport {
+    pub fn rooms(&self) -> &[Room] {
+        &self.rooms
+    }
+
+    pub fn links(&self, room_name: &str) -> &[(String, String)] {
+        self.links.get(room_name).map_or(&[], |l| l.as_slice())
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    pub fn unsafe_items(&self) -> &[String] {
+        &self.unsafe_items
+    }
+
+    pub fn passing_items(&self) -> &[String] {
+        &self.passing_items
+    }
+
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    // All commands and outputs of droid.
+    pub fn log(&self) -> &str {
+        &self.log
+    }
+
+    fn link(&mut self, from: &str, door: &str, to: &str) {
+        let links = self.links.entry(from.to_string()).or_default();
+        if !links.iter().any(|(d, _)| d == door) {
+            links.push((door.to_string(), to.to_string()));
+        }
+    }
+
+    fn is_linked(&self, from: &str, door: &str) -> bool {
+        self.links(from).iter().any(|(d, _)| d == door)
+    }
+
+    // Doors to pass from one room to another, by breadth first search on known links.
+    fn path(&self, from: &str, to: &str) -> Result<Vec<String>, Error> {
+        let mut prevs: HashMap<&str, (&str, &str)> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        let mut visited = HashSet::from([from]);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                let mut path = Vec::new();
+                let mut cur = to;
+                while let Some(&(prev, door)) = prevs.get(cur) {
+                    path.push(door.to_string());
+                    cur = prev;
+                }
+                path.reverse();
+                return Ok(path);
+            }
+
+            for (door, next) in self.links(cur) {
+                if visited.insert(next.as_str()) {
+                    prevs.insert(next.as_str(), (cur, door.as_str()));
+                    queue.push_back(next.as_str());
+                }
+            }
+        }
+
+        Err(Error::NoPathToRoom(from.to_string(), to.to_string()))
+    }
+}
+
+// Explore the whole ship and collect every safe item, then find items whose weight can pass pressure-sensitive floor
+// by trying their combinations, and get password for main airlock.
+pub fn explore(intcode: &[i64]) -> Result<ExploreReport, Error> {
+    let (mut droid, output) = Droid::start(intcode)?;
+    let start = Room::parse_last(&output).ok_or(Error::UnknownRoom(output.clone()))?;
+    let mut report = ExploreReport::default();
+    explore_room(&mut droid, &mut report, start.clone())?;
+
+    let (checkpoint, floor_door) = report.checkpoint.clone().ok_or(Error::CheckpointNotFound)?;
+    for door in report.path(&start.name, &checkpoint)? {
+        droid.send(&door)?;
+    }
+    pass_floor(&mut droid, &mut report, &floor_door)?;
+    report.log = droid.log;
+
+    Ok(report)
+}
+
+// Explore given room and all rooms reachable from it by depth first search, droid is back to given room after it.
+fn explore_room(droid: &mut Droid, report: &mut ExploreReport, room: Room) -> Result<(), Error> {
+    report.rooms.push(room.clone());
+    for item in &room.items {
+        if droid.take_if_safe(item, &room)? {
+            report.items.push(item.clone());
+        } else {
+            report.unsafe_items.push(item.clone());
+        }
+    }
+
+    for door in &room.doors {
+        if report.is_linked(&room.name, door) {
+            continue;
+        }
+
+        let output = droid.send(door)?;
+        let next = Room::parse_last(&output).ok_or(Error::UnknownRoom(output.clone()))?;
+        if next.name == room.name {
+            // Droid is sent back by pressure-sensitive floor, so this room is security checkpoint.
+            report.checkpoint = Some((room.name.clone(), door.clone()));
+            continue;
+        }
+
+        report.link(&room.name, door, &next.name);
+        report.link(&next.name, back_door(door), &room.name);
+        if !report.rooms.iter().any(|r| r.name == next.name) {
+            explore_room(droid, report, next)?;
+        }
+        droid.send(back_door(door))?;
+    }
+
+    Ok(())
+}
+
+// Try every combination of collected items on pressure-sensitive floor, in gray code order so only one item is
+// taken or dropped between two tries.
+fn pass_floor(
+    droid: &mut Droid,
+    report: &mut ExploreReport,
+    floor_door: &str,
+) -> Result<(), Error> {
+    let items = report.items.clone();
+    for item in &items {
+        droid.send(&format!("drop {}", item))?;
+    }
+
+    let password_pattern = Regex::new(r"\d{4,}").unwrap();
+    let mut prev_code = 0usize;
+    for i in 0..(1usize << items.len()) {
+        let code = i ^ (i >> 1);
+        let changed = code ^ prev_code;
+        if changed != 0 {
+            let ind = changed.trailing_zeros() as usize;
+            let action = if code & changed != 0 { "take" } else { "drop" };
+            droid.send(&format!("{} {}", action, items[ind]))?;
+        }
+        prev_code = code;
+
+        let output = droid.send(floor_door)?;
+        if !output.contains("Alert!") {
+            report.passing_items = (0..items.len())
+                .filter(|ind| code & (1 << ind) != 0)
+                .map(|ind| items[ind].clone())
+                .collect();
+            report.password = password_pattern
+                .find(&output)
+                .map(|m| m.as_str().to_string());
+            return Ok(());
+        }
+    }
+
+    Err(Error::NoPassingItemCombination(items))
+}
@@ -1,8 +1,9 @@
 use std::fmt::Display;
 
 use crate::int_code::{
-    com::{ProcessState, SeqIntCodeComputer},
-    io::{Channel, SeqIODevice, SeqInputDevice, SeqOutputDevice},
+    com::{ConcIntCodeComputer, ProcessState, SeqIntCodeComputer},
+    io::{ParaInputDevice, ParaOutputDevice},
+    topo::Topology,
 };
 
 pub struct AmpSettings {
@@ -120,33 +121,37 @@ impl Display for Error {
     }
 }
 
+// First amplifier gets its setting and 0, others get their settings, as initial inputs.
+fn with_amp_inputs(topo: Topology, settings: &[i64]) -> Topology {
+    settings
+        .iter()
+        .enumerate()
+        .fold(topo, |topo, (i, &setting)| {
+            if i == 0 {
+                topo.with_input(i, &[setting, 0])
+            } else {
+                topo.with_input(i, &[setting])
+            }
+        })
+}
+
 pub fn amp_chain(
     computer: &mut SeqIntCodeComputer,
     int_code: &[i64],
     settings: &[i64],
 ) -> Result<i64, Error> {
-    let mut amp_res = 0;
-    for i in 0..settings.len() {
-        let image = Vec::from(int_code);
-        let input_dev = SeqInputDevice::new(Channel::new(&[settings[i], amp_res]));
-        let output_dev = SeqOutputDevice::new(Channel::new(&[]));
-        let res = computer
-            .execute_with_io(&image, input_dev, output_dev.clone())
-            .map_err(|e| Error::ExecutionError(e, Vec::from(settings)))?;
-
-        if res.state() != ProcessState::Halt {
-            return Err(Error::ProcessBlockInChain(i));
-        }
+    let res = with_amp_inputs(Topology::pipeline(settings.len()), settings)
+        .run(computer, int_code)
+        .map_err(|e| Error::ExecutionError(e, Vec::from(settings)))?;
 
-        amp_res = output_dev.check(|c| {
-            c.data()
-                .get(0)
-                .cloned()
-                .ok_or(Error::EmptyAmplifierResult(Vec::from(settings)))
-        })?;
+    if let Some(i) = (0..res.node_n()).find(|&i| res.state(i) != Some(ProcessState::Halt)) {
+        return Err(Error::ProcessBlockInChain(i));
     }
 
-    Ok(amp_res)
+    res.output(settings.len() - 1)
+        .first()
+        .copied()
+        .ok_or(Error::EmptyAmplifierResult(Vec::from(settings)))
 }
 
 pub fn amp_loop(
@@ -155,27 +160,52 @@ pub fn amp_loop(
     setting: &[i64],
 ) -> Result<i64, Error> {
     let amp_count = setting.len();
-    let amp_io_devs = (0..amp_count)
+    with_amp_inputs(Topology::ring(amp_count), setting)
+        .run(computer, int_code)
+        .map_err(Error::ProcessesExecutionError)
+        .and_then(|res| {
+            if !res.is_all_halted() {
+                Err(Error::AmplifierInLoopStuck)
+            } else {
+                // The last output of the last amplifier is left in input of the first one.
+                res.output(amp_count - 1)
+                    .last()
+                    .copied()
+                    .ok_or(Error::EmptyOutputFromAmplifierLoop)
+            }
+        })
+}
+
+// Amplifier loop in which every amplifier runs concurrently, and is connected to the next one by a blocking channel.
+pub fn amp_loop_conc(int_code: &[i64], setting: &[i64]) -> Result<i64, Error> {
+    let amp_count = setting.len();
+    let mut computer = ConcIntCodeComputer::new();
+    let (mut senders, receivers): (Vec<_>, Vec<_>) = (0..amp_count)
         .map(|i| {
             if i == 0 {
-                SeqIODevice::new(Channel::new(&[setting[0], 0]))
+                computer.channel(&[setting[0], 0])
             } else {
-                SeqIODevice::new(Channel::new(&setting[i..(i + 1)]))
+                computer.channel(&setting[i..(i + 1)])
             }
         })
-        .collect::<Vec<_>>();
-    let mut amp_procs = (0..amp_count)
-        .map(|i| {
-            computer.new_proc(
+        .unzip();
+    let first_recv = receivers[0].clone();
+    // Amplifier i reads from channel i, and writes into channel i + 1.
+    senders.rotate_left(1);
+    let amp_procs = receivers
+        .into_iter()
+        .zip(senders)
+        .map(|(recv, send)| {
+            computer.spawn(
                 int_code,
-                amp_io_devs[i].input_device(),
-                amp_io_devs[(i + 1) % amp_count].output_device(),
+                ParaInputDevice::new(recv),
+                ParaOutputDevice::new(send),
             )
         })
         .collect::<Vec<_>>();
 
     computer
-        .exe_procs(&mut amp_procs, 0)
+        .join()
         .map_err(Error::ProcessesExecutionError)
         .and_then(|res| {
             if amp_procs
@@ -185,12 +215,11 @@ pub fn amp_loop(
             {
                 Err(Error::AmplifierInLoopStuck)
             } else {
-                amp_io_devs[0].check(|c| {
-                    c.data()
-                        .get(0)
-                        .copied()
-                        .ok_or(Error::EmptyOutputFromAmplifierLoop)
-                })
+                first_recv
+                    .drain()
+                    .first()
+                    .copied()
+                    .ok_or(Error::EmptyOutputFromAmplifierLoop)
             }
         })
 }
@@ -1,10 +1,19 @@
+pub mod asm;
 pub mod com;
-mod inst;
+pub mod dbg;
+pub mod fuzz;
+pub mod inst;
 pub mod io;
+pub mod mem;
+pub mod prof;
+pub mod rec;
+pub mod rng;
+pub mod sched;
+pub mod topo;
 
 use std::{
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Write},
     path::Path,
 };
 
@@ -25,3 +34,19 @@ where
         })
     })
 }
+
+pub fn write_int_code<P>(path: P, int_code: &[i64]) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let mut code_file = File::create(path).map_err(Error::IOError)?;
+    writeln!(code_file, "{}", to_code_str(int_code)).map_err(Error::IOError)
+}
+
+pub fn to_code_str(int_code: &[i64]) -> String {
+    int_code
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,487 @@
+use std::{collections::HashMap, fmt::Display};
+
+use int_enum::IntEnum;
+
+use crate::Error;
+
+use super::{
+    inst::{encode_opcode, parse_cur_inst, InstOpcodeInd, ParameterMode},
+    mem::Memory,
+};
+
+// Maximum count of values listed in one data directive by disassembler.
+const DATA_PER_LINE: usize = 8;
+
+static MNEMONICS: [(InstOpcodeInd, &str); 10] = [
+    (InstOpcodeInd::Add, "add"),
+    (InstOpcodeInd::Multiply, "mul"),
+    (InstOpcodeInd::Input, "in"),
+    (InstOpcodeInd::Output, "out"),
+    (InstOpcodeInd::JumpIfTrue, "jnz"),
+    (InstOpcodeInd::JumpIfFalse, "jz"),
+    (InstOpcodeInd::LessThan, "lt"),
+    (InstOpcodeInd::Equals, "eq"),
+    (InstOpcodeInd::AdjustRelativeBase, "arb"),
+    (InstOpcodeInd::Halt, "hlt"),
+];
+
+pub fn mnemonic(opcode_ind: InstOpcodeInd) -> &'static str {
+    MNEMONICS
+        .iter()
+        .find(|(ind, _)| *ind == opcode_ind)
+        .map(|(_, m)| *m)
+        .unwrap()
+}
+
+fn opcode_ind_of(mnemonic: &str) -> Option<InstOpcodeInd> {
+    MNEMONICS
+        .iter()
+        .find(|(_, m)| m.eq_ignore_ascii_case(mnemonic))
+        .map(|(ind, _)| *ind)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Param {
+    mode: ParameterMode,
+    value: i64,
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mode {
+            ParameterMode::Position => write!(f, "[{}]", self.value),
+            ParameterMode::Immediate => write!(f, "{}", self.value),
+            ParameterMode::Relative => write!(f, "[rb{:+}]", self.value),
+        }
+    }
+}
+
+impl Param {
+    pub fn mode(&self) -> ParameterMode {
+        self.mode
+    }
+
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    Inst {
+        addr: usize,
+        opcode_ind: InstOpcodeInd,
+        params: Vec<Param>,
+    },
+    Data {
+        addr: usize,
+        values: Vec<i64>,
+    },
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Statement::Inst {
+                addr,
+                opcode_ind,
+                params,
+            } => {
+                if params.is_empty() {
+                    return write!(f, "{:>5}: {}", addr, mnemonic(*opcode_ind));
+                }
+
+                write!(f, "{:>5}: {:<4}", addr, mnemonic(*opcode_ind))?;
+                for (i, param) in params.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { "" } else { ", " }, param)?;
+                }
+
+                Ok(())
+            }
+            Statement::Data { addr, values } => {
+                write!(f, "{:>5}: .data ", addr)?;
+                for (i, value) in values.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { "" } else { ", " }, value)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Statement {
+    pub fn addr(&self) -> usize {
+        match self {
+            Statement::Inst { addr, .. } | Statement::Data { addr, .. } => *addr,
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            Statement::Inst { params, .. } => params.len() + 1,
+            Statement::Data { values, .. } => values.len(),
+        }
+    }
+}
+
+// Listing of an intcode image, every line is an instruction or a data directive prefixed by its address.
+pub struct Listing {
+    stmts: Vec<Statement>,
+}
+
+impl Display for Listing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for stmt in &self.stmts {
+            writeln!(f, "{}", stmt)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Listing {
+    pub fn stmts(&self) -> &[Statement] {
+        &self.stmts
+    }
+
+    // Find the statement which covers given address.
+    pub fn stmt_at(&self, addr: usize) -> Option<&Statement> {
+        let ind = self
+            .stmts
+            .partition_point(|s| s.addr() + s.length() <= addr);
+        self.stmts.get(ind).filter(|s| s.addr() <= addr)
+    }
+}
+
+// Disassemble given image by linear sweep, values which can't be decoded (or can't be encoded back
+// to the same value) are listed as data.
+pub fn disassemble(image: &[i64]) -> Listing {
+    let mut stmts = Vec::new();
+    let mut addr = 0;
+    while addr < image.len() {
+        if let Some(stmt) = decode_inst(&image[addr..], addr) {
+            addr += stmt.length();
+            stmts.push(stmt);
+            continue;
+        }
+
+        match stmts.last_mut() {
+            Some(Statement::Data { values, .. }) if values.len() < DATA_PER_LINE => {
+                values.push(image[addr])
+            }
+            _ => stmts.push(Statement::Data {
+                addr,
+                values: vec![image[addr]],
+            }),
+        }
+        addr += 1;
+    }
+
+    Listing { stmts }
+}
+
+// Disassemble one statement at given address, value which can't be decoded is listed as data.
+pub fn disassemble_at(image: &[i64], addr: usize) -> Option<Statement> {
+    if addr >= image.len() {
+        return None;
+    }
+
+    Some(decode_stmt(&image[addr..], addr))
+}
+
+// Like disassemble_at, but reads only code of the statement from memory, so sparse memory isn't copied.
+pub fn disassemble_mem_at(mem: &Memory, addr: usize) -> Option<Statement> {
+    if addr >= mem.len() {
+        return None;
+    }
+
+    Some(decode_stmt(&mem.code_at(addr), addr))
+}
+
+// Decode statement from given code which starts at given address.
+fn decode_stmt(code: &[i64], addr: usize) -> Statement {
+    decode_inst(code, addr).unwrap_or(Statement::Data {
+        addr,
+        values: vec![code[0]],
+    })
+}
+
+fn decode_inst(code: &[i64], addr: usize) -> Option<Statement> {
+    let inst = parse_cur_inst(code).ok()?;
+    let opcode_ind = InstOpcodeInd::from_int(inst.opcode_ind()).ok()?;
+    if encode_opcode(opcode_ind, inst.param_modes()) != code[0] {
+        // Redundant digits in operation code, keep it as data to preserve the original value.
+        return None;
+    }
+
+    let params = inst
+        .params()
+        .iter()
+        .zip(inst.param_modes())
+        .map(|(&value, &mode)| Param { mode, value })
+        .collect();
+    Some(Statement::Inst {
+        addr,
+        opcode_ind,
+        params,
+    })
+}
+
+#[derive(Debug)]
+enum Expr {
+    Value(i64),
+    Label(String, i64), // (label name, offset)
+}
+
+impl Expr {
+    fn resolve(&self, line_n: usize, labels: &HashMap<String, usize>) -> Result<i64, Error> {
+        match self {
+            Expr::Value(v) => Ok(*v),
+            Expr::Label(name, offset) => labels
+                .get(name)
+                .map(|&addr| addr as i64 + offset)
+                .ok_or(Error::UndefinedLabel(line_n, name.clone())),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SrcStatement {
+    Inst(InstOpcodeInd, Vec<(ParameterMode, Expr)>),
+    Data(Vec<Expr>),
+}
+
+impl SrcStatement {
+    fn length(&self) -> usize {
+        match self {
+            SrcStatement::Inst(_, params) => params.len() + 1,
+            SrcStatement::Data(values) => values.len(),
+        }
+    }
+}
+
+// Assemble given source into an intcode image.
+// Source is line oriented, comment starts with ';', and each line can have these parts(all are optional):
+// "{address}:" address check, "{label}:" label definition, then an instruction or a ".data" directive.
+// Parameter "[x]" is in position mode, "[rb+x]" in relative mode, and plain "x" in immediate mode,
+// x can be integer, label or label with integer offset(e.g. "loop+2").
+pub fn assemble(source: &str) -> Result<Vec<i64>, Error> {
+    let mut labels = HashMap::new();
+    let mut src_stmts = Vec::new();
+    let mut addr = 0;
+    for (line_ind, line) in source.lines().enumerate() {
+        let line_n = line_ind + 1;
+        let mut rest = line.split(';').next().unwrap().trim();
+        while let Some((prefix, remain)) = split_label(rest) {
+            if let Ok(expect_addr) = prefix.parse::<usize>() {
+                if expect_addr != addr {
+                    return Err(Error::AsmSyntaxError(
+                        line_n,
+                        format!("expect address {}, found {}", expect_addr, addr),
+                    ));
+                }
+            } else if labels.insert(prefix.to_string(), addr).is_some() {
+                return Err(Error::DuplicateLabel(line_n, prefix.to_string()));
+            }
+            rest = remain;
+        }
+
+        if rest.is_empty() {
+            continue;
+        }
+
+        let stmt = parse_stmt(line_n, rest)?;
+        addr += stmt.length();
+        src_stmts.push((line_n, stmt));
+    }
+
+    let mut image = Vec::with_capacity(addr);
+    for (line_n, stmt) in src_stmts {
+        match stmt {
+            SrcStatement::Inst(opcode_ind, params) => {
+                let modes = params.iter().map(|(m, _)| *m).collect::<Vec<_>>();
+                image.push(encode_opcode(opcode_ind, &modes));
+                for (_, expr) in params {
+                    image.push(expr.resolve(line_n, &labels)?);
+                }
+            }
+            SrcStatement::Data(values) => {
+                for expr in values {
+                    image.push(expr.resolve(line_n, &labels)?);
+                }
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+fn split_label(s: &str) -> Option<(&str, &str)> {
+    let (prefix, remain) = s.split_once(':')?;
+    let prefix = prefix.trim();
+    if !prefix.is_empty()
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Some((prefix, remain.trim()))
+    } else {
+        None
+    }
+}
+
+fn parse_stmt(line_n: usize, s: &str) -> Result<SrcStatement, Error> {
+    let (head, operands) = match s.split_once(char::is_whitespace) {
+        Some((h, o)) => (h, o.trim()),
+        None => (s, ""),
+    };
+    let operands = if operands.is_empty() {
+        Vec::new()
+    } else {
+        operands.split(',').map(|o| o.trim()).collect::<Vec<_>>()
+    };
+
+    if head.eq_ignore_ascii_case(".data") {
+        return operands
+            .iter()
+            .map(|o| parse_expr(line_n, o))
+            .collect::<Result<Vec<_>, _>>()
+            .map(SrcStatement::Data);
+    }
+
+    let opcode_ind = opcode_ind_of(head).ok_or(Error::AsmSyntaxError(
+        line_n,
+        format!("unknown mnemonic({})", head),
+    ))?;
+    if operands.len() != opcode_ind.param_count() {
+        return Err(Error::AsmSyntaxError(
+            line_n,
+            format!(
+                "{} expects {} parameter(s), found {}",
+                head,
+                opcode_ind.param_count(),
+                operands.len()
+            ),
+        ));
+    }
+
+    operands
+        .iter()
+        .map(|o| parse_param(line_n, o))
+        .collect::<Result<Vec<_>, _>>()
+        .map(|params| SrcStatement::Inst(opcode_ind, params))
+}
+
+fn parse_param(line_n: usize, s: &str) -> Result<(ParameterMode, Expr), Error> {
+    match s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(inner) => {
+            let inner = inner.trim();
+            match inner.strip_prefix("rb") {
+                Some(offset) => {
+                    let offset = offset.trim();
+                    if offset.is_empty() {
+                        Ok((ParameterMode::Relative, Expr::Value(0)))
+                    } else {
+                        parse_offset(offset)
+                            .map(|v| (ParameterMode::Relative, Expr::Value(v)))
+                            .ok_or(Error::AsmSyntaxError(
+                                line_n,
+                                format!("invalid relative offset({})", offset),
+                            ))
+                    }
+                }
+                None => parse_expr(line_n, inner).map(|e| (ParameterMode::Position, e)),
+            }
+        }
+        None => parse_expr(line_n, s).map(|e| (ParameterMode::Immediate, e)),
+    }
+}
+
+fn parse_expr(line_n: usize, s: &str) -> Result<Expr, Error> {
+    if let Some(v) = parse_int(s) {
+        return Ok(Expr::Value(v));
+    }
+
+    let (name, offset) = match s.rfind(['+', '-']) {
+        Some(ind) if ind > 0 => {
+            let offset = parse_offset(&s[ind..]).ok_or(Error::AsmSyntaxError(
+                line_n,
+                format!("invalid offset in operand({})", s),
+            ))?;
+            (s[..ind].trim(), offset)
+        }
+        _ => (s, 0),
+    };
+    if is_label(name) {
+        Ok(Expr::Label(name.to_string(), offset))
+    } else {
+        Err(Error::AsmSyntaxError(
+            line_n,
+            format!("invalid operand({})", s),
+        ))
+    }
+}
+
+// Integer without whitespace inside, so "1 2" isn't taken as 12.
+fn parse_int(s: &str) -> Option<i64> {
+    s.trim().parse::<i64>().ok()
+}
+
+// Signed offset like "+2", "-2" or "- 2", whitespace is only allowed between sign and digits.
+fn parse_offset(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (sign, digits) = match s.strip_prefix(['+', '-']) {
+        Some(digits) => (&s[..1], digits.trim_start()),
+        None => ("", s),
+    };
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+
+    parse_int(&format!("{}{}", sign, digits))
+}
+
+fn is_label(s: &str) -> bool {
+    s.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && s != "rb"
+}
+
+#[test]
+fn test_round_trip_puzzle_inputs() {
+    use super::read_int_code;
+
+    for day in [2, 5, 7, 9, 11, 13, 15, 17, 19, 21, 23, 25] {
+        let image = read_int_code(format!("day{}_inputs.txt", day)).unwrap();
+        let source = disassemble(&image).to_string();
+        assert_eq!(assemble(&source).unwrap(), image, "day {}", day);
+    }
+}
+
+#[test]
+fn test_assemble_operands() {
+    assert_eq!(
+        assemble("add [rb - 3], -2, [x + 1]\nx: hlt").unwrap(),
+        vec![1201, -3, -2, 5, 99]
+    );
+    assert_eq!(assemble(".data x-1, 7\nx:").unwrap(), vec![1, 7]);
+}
+
+#[test]
+fn test_assemble_rejects_embedded_whitespace() {
+    assert!(matches!(
+        assemble("out 1 2"),
+        Err(Error::AsmSyntaxError(1, _))
+    ));
+    assert!(matches!(
+        assemble("out [rb+1 2]"),
+        Err(Error::AsmSyntaxError(1, _))
+    ));
+    assert!(matches!(
+        assemble(".data x+1 2\nx:"),
+        Err(Error::AsmSyntaxError(1, _))
+    ));
+}
@@ -1,10 +1,26 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    str::FromStr,
+};
 
 use crate::Error;
 
+use super::{
+    mem::{Memory, MemoryModel},
+    to_code_str,
+};
+
+mod conc;
+pub use conc::{ConcIntCodeComputer, ConcReceiver, ConcSender};
+
 pub trait ExecutionContext {
-    fn read_mem(&mut self, ind: usize) -> i64;
-    fn write_mem(&mut self, ind: usize, value: i64);
+    fn read_mem(&mut self, ind: usize) -> Result<i64, Error>;
+    fn write_mem(&mut self, ind: usize, value: i64) -> Result<(), Error>;
     fn input(&mut self) -> Option<i64>;
     fn output(&mut self, value: i64) -> Result<(), Error>;
     fn inst_p_mut(&mut self) -> &mut usize;
@@ -16,7 +32,7 @@ pub trait ExecutionContext {
 pub struct ProcessResult {
     step_count: usize,
     state: ProcessState,
-    image: Vec<i64>,
+    mem: Memory,
 }
 
 impl ProcessResult {
@@ -28,8 +44,140 @@ impl ProcessResult {
         self.step_count
     }
 
-    pub fn image(&self) -> &[i64] {
-        &self.image
+    // Whole memory in one vector, it fails if sparse memory is too long to be copied, see MAX_DENSE_LEN.
+    pub fn image(&self) -> Result<Cow<'_, [i64]>, Error> {
+        self.mem.as_slice()
+    }
+
+    pub fn mem(&self) -> &Memory {
+        &self.mem
+    }
+
+    // Value at given address of memory, without copying sparse memory.
+    pub fn mem_at(&self, ind: usize) -> i64 {
+        self.mem.get(ind)
+    }
+}
+
+// Full state of a process, which can be saved to disk and restored into a new process later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessSnapshot {
+    state: ProcessState,
+    inst_p: usize,
+    rel_base: i64,
+    step_count: usize,
+    mem: Memory,
+    pending_input: Vec<i64>,
+    pending_output: Vec<i64>,
+}
+
+impl Display for ProcessSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "state {:?}", self.state)?;
+        writeln!(f, "inst_p {}", self.inst_p)?;
+        writeln!(f, "rel_base {}", self.rel_base)?;
+        writeln!(f, "step_count {}", self.step_count)?;
+        writeln!(f, "input {}", to_code_str(&self.pending_input))?;
+        writeln!(f, "output {}", to_code_str(&self.pending_output))?;
+        writeln!(f, "mem {}", self.mem)
+    }
+}
+
+impl FromStr for ProcessSnapshot {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = HashMap::new();
+        for line in s.lines().filter(|l| !l.trim().is_empty()) {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            fields.insert(key.trim(), value.trim());
+        }
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .copied()
+                .ok_or(Error::InvalidSnapshot(format!("missing field({})", key)))
+        };
+        fn parse_num<T: FromStr>(key: &str, s: &str) -> Result<T, Error> {
+            s.parse::<T>()
+                .map_err(|_| Error::InvalidSnapshot(format!("invalid {}({})", key, s)))
+        }
+        fn parse_values(key: &str, s: &str) -> Result<Vec<i64>, Error> {
+            s.split(',')
+                .filter(|v| !v.is_empty())
+                .map(|v| parse_num(key, v))
+                .collect()
+        }
+        // Memory was saved as plain values from address 0 before it's saved by segments.
+        fn parse_mem(s: &str) -> Result<Memory, Error> {
+            if s.starts_with("len") {
+                return s.parse();
+            }
+
+            let values = parse_values("mem", s)?;
+            Memory::from_segments(MemoryModel::Sparse, values.len(), [(0, values.as_slice())])
+        }
+
+        let state = match field("state")? {
+            "Ready" => ProcessState::Ready,
+            "Running" => ProcessState::Running,
+            "Block" => ProcessState::Block,
+            "Halt" => ProcessState::Halt,
+            other => return Err(Error::InvalidSnapshot(format!("invalid state({})", other))),
+        };
+        Ok(Self {
+            state,
+            inst_p: parse_num("inst_p", field("inst_p")?)?,
+            rel_base: parse_num("rel_base", field("rel_base")?)?,
+            step_count: parse_num("step_count", field("step_count")?)?,
+            mem: parse_mem(field("mem")?)?,
+            pending_input: parse_values("input", field("input")?)?,
+            pending_output: parse_values("output", field("output")?)?,
+        })
+    }
+}
+
+impl ProcessSnapshot {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        fs::read_to_string(path)
+            .map_err(Error::IOError)
+            .and_then(|s| s.parse())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        File::create(path)
+            .and_then(|mut f| write!(f, "{}", self))
+            .map_err(Error::IOError)
+    }
+
+    pub fn state(&self) -> ProcessState {
+        self.state
+    }
+
+    pub fn inst_p(&self) -> usize {
+        self.inst_p
+    }
+
+    pub fn rel_base(&self) -> i64 {
+        self.rel_base
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.step_count
+    }
+
+    pub fn mem(&self) -> &Memory {
+        &self.mem
+    }
+
+    // Data in input port which hadn't been read when snapshot was taken, can be used to initialize new input device.
+    pub fn pending_input(&self) -> &[i64] {
+        &self.pending_input
+    }
+
+    // Data in output port which hadn't been consumed when snapshot was taken.
+    pub fn pending_output(&self) -> &[i64] {
+        &self.pending_output
     }
 }
 
@@ -47,6 +195,18 @@ impl ProcsExecutionResult {
     }
 }
 
+// How computer decodes and executes instructions.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum ExecutionEngine {
+    // Parse every instruction into boxed trait object before executing it.
+    #[default]
+    Boxed,
+    // Decode every instruction without allocation, and execute it by matching operation code.
+    Decoded,
+    // Like Decoded, and cache decoded instructions by address until their code is overwritten.
+    Cached,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ProcessState {
     Ready,
@@ -59,14 +219,28 @@ macro_rules! def_computer {
     (name=$name:ident, input_device_type=$input_device_type:ident, output_device_type=$output_device_type:ident, input_ref_type=$input_ref_type:ident, output_ref_type=$output_ref_type:ident $(, device_additional_markers=$device_additional_markers:tt)?) => {
         pub struct $name {
             pub enable_debug_output: bool,
+            pub engine: ExecutionEngine,
+            // Memory model of processes created later.
+            pub memory_model: MemoryModel,
+            // How processes share processor in exe_procs_sched.
+            pub schedule_policy: SchedulePolicy,
             processes: Vec<Option<Process>>,
+            yield_on_output: bool,
+            debugger: Debugger,
+            profiler: Option<Profiler>,
         }
 
         impl $name {
             pub fn new(enable_debug_output: bool) -> Self {
                 $name {
                     enable_debug_output,
+                    engine: ExecutionEngine::default(),
+                    memory_model: MemoryModel::default(),
+                    schedule_policy: SchedulePolicy::default(),
                     processes: Vec::new(),
+                    yield_on_output: false,
+                    debugger: Debugger::default(),
+                    profiler: None,
                 }
             }
 
@@ -97,34 +271,8 @@ macro_rules! def_computer {
                 proc_ids: &[usize],
                 start_proc_ind: usize,
             ) -> Result<ProcsExecutionResult, Error> {
-                let mut cur_proc_id_ind = Some(start_proc_ind);
-                loop {
-                    match cur_proc_id_ind {
-                        Some(ind) => {
-                            self.exe_proc(proc_ids[ind], None)?;
-
-                            cur_proc_id_ind = None;
-                            for i in 1..=proc_ids.len() {
-                                let next_proc_id_ind = (i + ind) % proc_ids.len();
-                                let next_proc_id = proc_ids[next_proc_id_ind];
-                                if self
-                                    .proc(next_proc_id)
-                                    .is_some_and(|p| p.state == ProcessState::Ready)
-                                {
-                                    cur_proc_id_ind = Some(next_proc_id_ind);
-                                    break;
-                                }
-                            }
-                        }
-                        None => {
-                            let images = proc_ids
-                                .iter()
-                                .flat_map(|&id| self.take_proc(id).map(|p| (id, p.into_snap())))
-                                .collect::<HashMap<_, _>>();
-                            return Ok(ProcsExecutionResult::new(images));
-                        }
-                    };
-                }
+                let scheduler = Scheduler::starting_at(SchedulePolicy::RunToBlock, start_proc_ind);
+                self.exe_procs_with(proc_ids, scheduler, || false).map(|(res, _)| res)
             }
 
             // Execute processes in a preemptive way.
@@ -135,77 +283,131 @@ macro_rules! def_computer {
                 reserved_step_count: usize,
                 interrupt_cond: F,
             ) -> Result<ProcsExecutionResult, Error> {
-                let mut cur_proc_id_ind = Some(start_proc_ind);
-                loop {
-                    if interrupt_cond() {
-                        // Interrupt execution when satisfy given condition
-                        break;
-                    }
+                let scheduler = Scheduler::starting_at(SchedulePolicy::RoundRobin(reserved_step_count), start_proc_ind);
+                self.exe_procs_with(proc_ids, scheduler, interrupt_cond).map(|(res, _)| res)
+            }
 
-                    match cur_proc_id_ind {
-                        Some(ind) => {
-                            self.exe_proc(proc_ids[ind], Some(reserved_step_count))?;
-
-                            cur_proc_id_ind = None;
-                            for i in 1..=proc_ids.len() {
-                                let next_proc_id_ind = (i + ind) % proc_ids.len();
-                                let next_proc_id = proc_ids[next_proc_id_ind];
-                                if self
-                                    .proc(next_proc_id)
-                                    .is_some_and(|p| p.state == ProcessState::Ready)
-                                {
-                                    cur_proc_id_ind = Some(next_proc_id_ind);
-                                    break;
-                                }
-                            }
-                        }
-                        None => {
-                            break;
-                        }
+            // Execute processes under schedule policy of computer, until none of them is ready or given condition is
+            // satisfied, and return how processor is shared among them.
+            pub fn exe_procs_sched<F: FnMut() -> bool>(
+                &mut self,
+                proc_ids: &[usize],
+                interrupt_cond: F,
+            ) -> Result<(ProcsExecutionResult, ScheduleStats), Error> {
+                let scheduler = Scheduler::new(self.schedule_policy.clone());
+                self.exe_procs_with(proc_ids, scheduler, interrupt_cond)
+            }
+
+            fn exe_procs_with<F: FnMut() -> bool>(
+                &mut self,
+                proc_ids: &[usize],
+                mut scheduler: Scheduler,
+                mut interrupt_cond: F,
+            ) -> Result<(ProcsExecutionResult, ScheduleStats), Error> {
+                self.yield_on_output = scheduler.yields_on_output();
+                let mut exe_res = Ok(());
+                // Interrupt execution when satisfy given condition
+                while !interrupt_cond() {
+                    let ready = proc_ids
+                        .iter()
+                        .map(|&id| self.proc(id).is_some_and(|p| p.state == ProcessState::Ready))
+                        .collect::<Vec<_>>();
+                    let Some(ind) = scheduler.pick(&ready) else {
+                        break;
                     };
+
+                    let proc_id = proc_ids[ind];
+                    let start_step_count = self.proc(proc_id).unwrap().step_count;
+                    exe_res = self.exe_proc(proc_id, scheduler.quantum()).map(|_| ());
+                    if exe_res.is_err() {
+                        break;
+                    }
+                    scheduler.record(ind, self.proc(proc_id).unwrap().step_count - start_step_count);
                 }
+                self.yield_on_output = false;
+                exe_res?;
 
                 let images = proc_ids
                     .iter()
                     .flat_map(|&id| self.take_proc(id).map(|p| (id, p.into_snap())))
                     .collect::<HashMap<_, _>>();
-                Ok(ProcsExecutionResult::new(images))
+                Ok((ProcsExecutionResult::new(images), scheduler.into_stats()))
+            }
+
+            fn exe_proc(&mut self, cur_proc_id: usize, reserved_step_count: Option<usize>) -> Result<StopReason, Error> {
+                self.exe_proc_dbg(cur_proc_id, reserved_step_count, false)
             }
 
-            fn exe_proc(&mut self, cur_proc_id: usize, reserved_step_count: Option<usize>) -> Result<(), Error> {
+            // Execute given process, and stop at breakpoints and watchpoints of debugger if is_debugging is true.
+            // Breakpoint at the instruction where process starts is skipped, so the process can be resumed from it.
+            fn exe_proc_dbg(&mut self, cur_proc_id: usize, reserved_step_count: Option<usize>, is_debugging: bool) -> Result<StopReason, Error> {
                 self.proc_mut(cur_proc_id)
                     .ok_or(Error::RunningUnknownProcess(cur_proc_id))?;
 
                 let debug_enabled = self.enable_debug_output;
+                let engine = self.engine;
+                self.debugger.take_watch_hit();
                 let mut run_proc = RunningProcess {
                     computer: self,
                     run_proc_id: cur_proc_id,
                 };
-                if run_proc.run_proc_mut().state != ProcessState::Ready {
-                    return Ok(());
+                match run_proc.run_proc().state {
+                    ProcessState::Ready => (),
+                    ProcessState::Halt => return Ok(StopReason::Halt),
+                    _ => return Ok(StopReason::Block),
                 }
 
                 run_proc.run_proc_mut().state = ProcessState::Running;
                 let start_step_count = run_proc.run_proc().step_count;
+                let start_output_n = run_proc.run_proc().output_n;
                 loop {
-                    let inst = run_proc.run_proc_mut().cur_inst()?;
-                    if debug_enabled {
-                        let step_count = run_proc.run_proc().step_count;
-                        let inst_p = run_proc.run_proc().inst_p();
-                        println!(
-                            "Process({}) step # {}: {:?} @ {}.",
-                            cur_proc_id, step_count, inst, inst_p
-                        );
+                    let inst_p = run_proc.run_proc().inst_p();
+                    if is_debugging
+                        && run_proc.run_proc().step_count > start_step_count
+                        && run_proc.computer.debugger.is_break_at(inst_p)
+                    {
+                        run_proc.run_proc_mut().state = ProcessState::Ready;
+                        return Ok(StopReason::Breakpoint(inst_p));
                     }
 
-                    match inst.execute(&mut run_proc) {
+                    let (exe_res, opcode_ind) = match engine {
+                        ExecutionEngine::Boxed => {
+                            let inst = run_proc.run_proc_mut().cur_inst()?;
+                            if debug_enabled {
+                                let step_count = run_proc.run_proc().step_count;
+                                println!(
+                                    "Process({}) step # {}: {:?} @ {}.",
+                                    cur_proc_id, step_count, inst, inst_p
+                                );
+                            }
+
+                            (inst.execute(&mut run_proc), inst.opcode_ind())
+                        }
+                        ExecutionEngine::Decoded | ExecutionEngine::Cached => {
+                            let inst = run_proc.run_proc_mut().cur_decoded_inst(engine == ExecutionEngine::Cached)?;
+                            if debug_enabled {
+                                let step_count = run_proc.run_proc().step_count;
+                                println!(
+                                    "Process({}) step # {}: {:?} @ {}.",
+                                    cur_proc_id, step_count, inst, inst_p
+                                );
+                            }
+
+                            (inst.execute(&mut run_proc), inst.opcode_ind().int_value())
+                        }
+                    };
+
+                    match exe_res {
                         Ok(_) => {
                             run_proc.run_proc_mut().step_count += 1;
+                            if let Some(profiler) = run_proc.computer.profiler.as_mut() {
+                                profiler.on_exec(inst_p, opcode_ind);
+                            }
                             if run_proc.run_proc().is_halt() {
                                 if debug_enabled {
                                     println!("Process({}) halt.", cur_proc_id);
                                 }
-                                break;
+                                return Ok(StopReason::Halt);
                             }
                         }
                         Err(e) => match e {
@@ -215,18 +417,103 @@ macro_rules! def_computer {
                                 if debug_enabled {
                                     println!("Process({}) blocked for requiring input.", cur_proc_id);
                                 }
-                                break;
+                                return Ok(StopReason::Block);
                             }
                             _ => return Err(e),
                         },
                     };
 
+                    if is_debugging {
+                        if let Some(hit) = run_proc.computer.debugger.take_watch_hit() {
+                            run_proc.run_proc_mut().state = ProcessState::Ready;
+                            return Ok(hit);
+                        }
+                    }
+
+                    if run_proc.computer.yield_on_output && run_proc.run_proc().output_n > start_output_n {
+                        run_proc.run_proc_mut().state = ProcessState::Ready;
+                        return Ok(StopReason::Output);
+                    }
+
                     if reserved_step_count.as_ref().is_some_and(|rsc| *rsc <= run_proc.run_proc().step_count - start_step_count) {
                         // Run over allowed steps, yield processor
                         run_proc.run_proc_mut().state = ProcessState::Ready;
-                        break;
+                        return Ok(StopReason::StepLimit);
                     }
                 }
+            }
+
+            pub fn debugger(&self) -> &Debugger {
+                &self.debugger
+            }
+
+            pub fn debugger_mut(&mut self) -> &mut Debugger {
+                &mut self.debugger
+            }
+
+            // Start recording execution profile of all processes, profile recorded before is dropped.
+            pub fn enable_profiler(&mut self) {
+                self.profiler = Some(Profiler::default());
+            }
+
+            // Stop recording execution profile, and return the recorded one.
+            pub fn disable_profiler(&mut self) -> Option<Profiler> {
+                self.profiler.take()
+            }
+
+            pub fn profiler(&self) -> Option<&Profiler> {
+                self.profiler.as_ref()
+            }
+
+            // Execute one instruction of given process, process blocked by input will be retried.
+            pub fn step(&mut self, proc_id: usize) -> Result<StopReason, Error> {
+                self.awake_proc(proc_id);
+                self.exe_proc_dbg(proc_id, Some(1), true)
+            }
+
+            // Execute given process until it hits breakpoint or watchpoint, or it's halted or blocked.
+            pub fn cont(&mut self, proc_id: usize) -> Result<StopReason, Error> {
+                self.awake_proc(proc_id);
+                self.exe_proc_dbg(proc_id, None, true)
+            }
+
+            pub fn proc_state(&self, proc_id: usize) -> Option<ProcessState> {
+                self.proc(proc_id).map(|p| p.state)
+            }
+
+            pub fn proc_inst_p(&self, proc_id: usize) -> Option<usize> {
+                self.proc(proc_id).map(|p| p.inst_p)
+            }
+
+            pub fn proc_rel_base(&self, proc_id: usize) -> Option<i64> {
+                self.proc(proc_id).map(|p| p.rel_base)
+            }
+
+            pub fn proc_step_count(&self, proc_id: usize) -> Option<usize> {
+                self.proc(proc_id).map(|p| p.step_count)
+            }
+
+            // Memory of given process, the cells beyond its end are all zero.
+            pub fn proc_mem(&self, proc_id: usize) -> Option<&Memory> {
+                self.proc(proc_id).map(|p| &p.mem)
+            }
+
+            pub fn set_proc_mem(&mut self, proc_id: usize, ind: usize, value: i64) -> Result<(), Error> {
+                let proc = self.proc_mut(proc_id).ok_or(Error::RunningUnknownProcess(proc_id))?;
+                proc.mem.write(ind, value)?;
+                proc.invalidate_inst_cache(ind);
+
+                Ok(())
+            }
+
+            pub fn proc_memory_model(&self, proc_id: usize) -> Option<MemoryModel> {
+                self.proc(proc_id).map(|p| p.mem.model())
+            }
+
+            // Move memory of given process into another model, it fails if content exceeds cap of new model.
+            pub fn set_proc_memory_model(&mut self, proc_id: usize, model: MemoryModel) -> Result<(), Error> {
+                let proc = self.proc_mut(proc_id).ok_or(Error::RunningUnknownProcess(proc_id))?;
+                proc.mem = proc.mem.convert(model)?;
 
                 Ok(())
             }
@@ -241,7 +528,11 @@ macro_rules! def_computer {
                 ID: InputPort $(+ $device_additional_markers)?,
                 OD: OutputPort $(+ $device_additional_markers)?,
             {
-                let proc = Process::new(int_code, input_dev.input_port(), output_dev.output_port());
+                let proc = Process::new(
+                    Memory::new(self.memory_model, int_code),
+                    input_dev.input_port(),
+                    output_dev.output_port(),
+                );
                 let mut exist_slot_id = None;
                 for (i, slot) in self.processes.iter_mut().enumerate() {
                     if slot.is_none() {
@@ -265,6 +556,63 @@ macro_rules! def_computer {
                 id
             }
 
+            // Run given process until it's halted, blocked or has executed given count of steps.
+            pub fn run_proc(&mut self, proc_id: usize, max_step_count: Option<usize>) -> Result<StopReason, Error> {
+                self.awake_proc(proc_id);
+                self.exe_proc(proc_id, max_step_count)
+            }
+
+            // Remove given process from computer, and return its result.
+            pub fn take_proc_result(&mut self, proc_id: usize) -> Option<ProcessResult> {
+                self.take_proc(proc_id).map(|p| p.into_snap())
+            }
+
+            pub fn snapshot_proc(&self, proc_id: usize) -> Option<ProcessSnapshot> {
+                self.proc(proc_id).map(|p| p.snapshot())
+            }
+
+            // Create a new process from given snapshot, pending I/O data in snapshot isn't put into given devices.
+            // Memory of snapshot is moved into memory model of computer, it fails if it's too long for dense model.
+            pub fn restore_proc<ID, OD>(
+                &mut self,
+                snapshot: &ProcessSnapshot,
+                input_dev: $input_device_type<ID>,
+                output_dev: $output_device_type<OD>,
+            ) -> Result<usize, Error>
+            where
+                ID: InputPort $(+ $device_additional_markers)?,
+                OD: OutputPort $(+ $device_additional_markers)?,
+            {
+                let mem = Memory::from_segments(self.memory_model, snapshot.mem.len(), snapshot.mem.segments())?;
+                let proc_id = self.new_proc(&[], input_dev, output_dev);
+                let proc = self.proc_mut(proc_id).unwrap();
+                proc.mem = mem;
+                proc.state = match snapshot.state {
+                    ProcessState::Running => ProcessState::Ready,
+                    s => s,
+                };
+                proc.inst_p = snapshot.inst_p;
+                proc.rel_base = snapshot.rel_base;
+                proc.step_count = snapshot.step_count;
+
+                Ok(proc_id)
+            }
+
+            // Create given count of processes from the same snapshot, devices of each process are made by given function.
+            pub fn fork_proc<ID, OD, F>(&mut self, snapshot: &ProcessSnapshot, fork_n: usize, mut make_devs: F) -> Result<Vec<usize>, Error>
+            where
+                ID: InputPort $(+ $device_additional_markers)? + 'static,
+                OD: OutputPort $(+ $device_additional_markers)? + 'static,
+                F: FnMut(usize, &ProcessSnapshot) -> ($input_device_type<ID>, $output_device_type<OD>),
+            {
+                (0..fork_n)
+                    .map(|i| {
+                        let (input_dev, output_dev) = make_devs(i, snapshot);
+                        self.restore_proc(snapshot, input_dev, output_dev)
+                    })
+                    .collect()
+            }
+
             fn take_proc(&mut self, proc_id: usize) -> Option<Process> {
                 self.processes.get_mut(proc_id).and_then(|o| o.take())
             }
@@ -289,23 +637,27 @@ macro_rules! def_computer {
         struct Process {
             state: ProcessState,
             inst_p: usize,
-            mem: Vec<i64>,
+            mem: Memory,
             input_port: $input_ref_type,
             output_port: $output_ref_type,
             step_count: usize,
             rel_base: i64,
+            inst_cache: Vec<Option<DecodedInst>>,
+            output_n: usize,
         }
 
         impl Process {
-            fn new(image: &[i64], input_port: $input_ref_type, output_port: $output_ref_type) -> Self {
+            fn new(mem: Memory, input_port: $input_ref_type, output_port: $output_ref_type) -> Self {
                 Process {
                     state: ProcessState::Ready,
                     inst_p: 0,
-                    mem: Vec::from(image),
+                    mem,
                     input_port,
                     output_port,
                     step_count: 0,
                     rel_base: 0,
+                    inst_cache: Vec::new(),
+                    output_n: 0,
                 }
             }
 
@@ -317,7 +669,37 @@ macro_rules! def_computer {
                 if self.inst_p >= self.mem.len() {
                     Err(Error::ExecutionExceedIntCode(self.inst_p, self.mem.len()))
                 } else {
-                    parse_cur_inst(&self.mem[self.inst_p..])
+                    parse_cur_inst(&self.mem.code_at(self.inst_p))
+                }
+            }
+
+            fn cur_decoded_inst(&mut self, use_cache: bool) -> Result<DecodedInst, Error> {
+                if self.inst_p >= self.mem.len() {
+                    return Err(Error::ExecutionExceedIntCode(self.inst_p, self.mem.len()));
+                }
+
+                if !use_cache {
+                    return DecodedInst::decode(&self.mem.code_at(self.inst_p));
+                }
+
+                if let Some(Some(inst)) = self.inst_cache.get(self.inst_p) {
+                    return Ok(*inst);
+                }
+
+                let inst = DecodedInst::decode(&self.mem.code_at(self.inst_p))?;
+                if self.inst_cache.len() <= self.inst_p {
+                    self.inst_cache.resize(self.inst_p + 1, None);
+                }
+                self.inst_cache[self.inst_p] = Some(inst);
+
+                Ok(inst)
+            }
+
+            // Drop cached instructions whose code covers given address.
+            fn invalidate_inst_cache(&mut self, ind: usize) {
+                let end = (ind + 1).min(self.inst_cache.len());
+                for slot in &mut self.inst_cache[ind.saturating_sub(MAX_INST_LENGTH - 1).min(end)..end] {
+                    *slot = None;
                 }
             }
 
@@ -325,11 +707,23 @@ macro_rules! def_computer {
                 self.state == ProcessState::Halt
             }
 
+            fn snapshot(&self) -> ProcessSnapshot {
+                ProcessSnapshot {
+                    state: self.state,
+                    inst_p: self.inst_p,
+                    rel_base: self.rel_base,
+                    step_count: self.step_count,
+                    mem: self.mem.clone(),
+                    pending_input: self.input_port.apply(|p| p.pending_input()),
+                    pending_output: self.output_port.apply(|p| p.pending_output()),
+                }
+            }
+
             fn into_snap(self) -> ProcessResult {
                 ProcessResult {
                     step_count: self.step_count,
                     state: self.state,
-                    image: self.mem,
+                    mem: self.mem,
                 }
             }
         }
@@ -350,25 +744,29 @@ macro_rules! def_computer {
         }
 
         impl<'a> ExecutionContext for RunningProcess<'a> {
-            fn read_mem(&mut self, ind: usize) -> i64 {
-                if ind < self.run_proc().mem.len() {
-                    self.run_proc().mem[ind]
-                } else {
-                    self.run_proc_mut().mem.resize(ind + 1, 0);
-                    self.run_proc().mem[ind]
+            fn read_mem(&mut self, ind: usize) -> Result<i64, Error> {
+                if let Some(profiler) = self.computer.profiler.as_mut() {
+                    profiler.on_read(ind);
                 }
+
+                self.run_proc_mut().mem.read(ind)
             }
 
-            fn write_mem(&mut self, ind: usize, value: i64) {
-                if ind >= self.run_proc().mem.len() {
-                    self.run_proc_mut().mem.resize(ind + 1, 0);
+            fn write_mem(&mut self, ind: usize, value: i64) -> Result<(), Error> {
+                let proc = self.run_proc_mut();
+                let old_value = proc.mem.write(ind, value)?;
+                proc.invalidate_inst_cache(ind);
+                self.computer.debugger.on_write(ind, old_value, value);
+                if let Some(profiler) = self.computer.profiler.as_mut() {
+                    profiler.on_write(ind);
                 }
 
-                self.run_proc_mut().mem[ind] = value;
+                Ok(())
             }
 
             fn input(&mut self) -> Option<i64> {
-                let res = self.run_proc_mut().input_port.apply_mut(|p| p.get());
+                let step = self.run_proc().step_count;
+                let res = self.run_proc_mut().input_port.apply_mut(|p| p.get_at(step));
                 if res.is_none() {
                     assert!(self.run_proc().state == ProcessState::Running);
                     self.run_proc_mut().state = ProcessState::Block;
@@ -378,21 +776,20 @@ macro_rules! def_computer {
             }
 
             fn output(&mut self, value: i64) -> Result<(), Error> {
-                self.run_proc_mut().output_port.apply_mut(|p| p.put(value))?;
+                let step = self.run_proc().step_count;
+                self.run_proc_mut().output_port.apply_mut(|p| p.put_at(step, value))?;
+                self.run_proc_mut().output_n += 1;
 
-                let wait_proc_id = self.run_proc_mut().output_port.apply(|p| p.wait_proc_id());
-                match wait_proc_id {
-                    Some(id) => {
-                        if self.computer.enable_debug_output {
-                            println!(
-                                "Process({}) output data({}) and try to awake process({})",
-                                self.run_proc_id, value, id
-                            );
-                        }
-                        self.computer.awake_proc(id)
+                let wait_proc_ids = self.run_proc_mut().output_port.apply(|p| p.wait_proc_ids());
+                for id in wait_proc_ids {
+                    if self.computer.enable_debug_output {
+                        println!(
+                            "Process({}) output data({}) and try to awake process({})",
+                            self.run_proc_id, value, id
+                        );
                     }
-                    _ => (),
-                };
+                    self.computer.awake_proc(id)
+                }
 
                 Ok(())
             }
@@ -418,16 +815,22 @@ macro_rules! def_computer {
 
 mod seq {
     use super::{
+        super::dbg::{Debugger, StopReason},
+        super::mem::{Memory, MemoryModel},
+        super::prof::Profiler,
+        super::sched::{SchedulePolicy, ScheduleStats, Scheduler},
         super::{
-            inst::{parse_cur_inst, Instruction},
+            inst::{parse_cur_inst, DecodedInst, Instruction, MAX_INST_LENGTH},
             io::{
                 InputPort, OutputPort, Ref, SeqInputDevice, SeqInputRef, SeqOutputDevice,
                 SeqOutputRef,
             },
         },
-        ExecutionContext, ProcessResult, ProcessState, ProcsExecutionResult,
+        ExecutionContext, ExecutionEngine, ProcessResult, ProcessSnapshot, ProcessState,
+        ProcsExecutionResult,
     };
     use crate::Error;
+    use int_enum::IntEnum;
     use std::collections::HashMap;
 
     def_computer!(
@@ -442,16 +845,22 @@ pub use seq::SeqIntCodeComputer;
 
 mod para {
     use super::{
+        super::dbg::{Debugger, StopReason},
+        super::mem::{Memory, MemoryModel},
+        super::prof::Profiler,
+        super::sched::{SchedulePolicy, ScheduleStats, Scheduler},
         super::{
-            inst::{parse_cur_inst, Instruction},
+            inst::{parse_cur_inst, DecodedInst, Instruction, MAX_INST_LENGTH},
             io::{
                 InputPort, OutputPort, ParaInputDevice, ParaInputRef, ParaOutputDevice,
                 ParaOutputRef, Ref,
             },
         },
-        ExecutionContext, ProcessResult, ProcessState, ProcsExecutionResult,
+        ExecutionContext, ExecutionEngine, ProcessResult, ProcessSnapshot, ProcessState,
+        ProcsExecutionResult,
     };
     use crate::Error;
+    use int_enum::IntEnum;
     use std::collections::HashMap;
 
     def_computer!(
@@ -464,3 +873,37 @@ mod para {
     );
 }
 pub use para::ParaIntCodeComputer;
+
+#[test]
+fn test_snapshot_of_far_sparse_write() {
+    use super::io::{Channel, SeqInputDevice, SeqOutputDevice};
+
+    let far = 1i64 << 40;
+    let mut computer = SeqIntCodeComputer::new(false);
+    computer.memory_model = MemoryModel::Sparse;
+    let image = [1101, 7, 0, far, 1101, 1, 1, 0, 99];
+    let input_dev = SeqInputDevice::new(Channel::new(&[]));
+    let output_dev = SeqOutputDevice::new(Channel::new(&[]));
+    let proc_id = computer.new_proc(&image, input_dev.clone(), output_dev.clone());
+    computer.run_proc(proc_id, Some(1)).unwrap();
+
+    let snapshot = computer.snapshot_proc(proc_id).unwrap();
+    let loaded = snapshot.to_string().parse::<ProcessSnapshot>().unwrap();
+    assert_eq!(loaded, snapshot);
+    assert_eq!(loaded.mem().get(far as usize), 7);
+
+    let restored_id = computer
+        .restore_proc(&loaded, input_dev.clone(), output_dev.clone())
+        .unwrap();
+    computer.run_proc(restored_id, None).unwrap();
+    let res = computer.take_proc_result(restored_id).unwrap();
+    assert_eq!(res.state(), ProcessState::Halt);
+    assert_eq!(res.mem_at(0), 2);
+    assert_eq!(res.mem_at(far as usize), 7);
+    assert!(matches!(res.image(), Err(Error::MemoryLimitExceeded(..))));
+
+    computer.memory_model = MemoryModel::Dense(None);
+    assert!(computer
+        .restore_proc(&snapshot, input_dev, output_dev)
+        .is_err());
+}
@@ -0,0 +1,453 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    int_code::{
+        inst::DecodedInst,
+        io::{
+            InputPort, OutputPort, ParaInputDevice, ParaInputRef, ParaOutputDevice, ParaOutputRef,
+            Ref,
+        },
+        mem::{Memory, MemoryModel},
+    },
+    Error,
+};
+
+use super::{ExecutionContext, ProcessResult, ProcessState, ProcsExecutionResult};
+
+// Interval to check stop condition when waiting for tasks.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+struct RuntimeState {
+    queues: Vec<VecDeque<i64>>,
+    sender_counts: Vec<usize>,
+    waiting_ns: Vec<usize>,
+    alive_n: usize,
+    is_deadlock: bool,
+}
+
+impl RuntimeState {
+    // Count of tasks waiting for channels which are empty and still open, they can't go on by themselves.
+    fn stuck_n(&self) -> usize {
+        (0..self.queues.len())
+            .filter(|&id| self.queues[id].is_empty() && self.sender_counts[id] > 0)
+            .map(|id| self.waiting_ns[id])
+            .sum()
+    }
+}
+
+// State shared by all tasks and channels in one computer.
+struct Runtime {
+    state: Mutex<RuntimeState>,
+    cond: Condvar,
+    is_stopped: AtomicBool,
+}
+
+impl Runtime {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(RuntimeState {
+                queues: Vec::new(),
+                sender_counts: Vec::new(),
+                waiting_ns: Vec::new(),
+                alive_n: 0,
+                is_deadlock: false,
+            }),
+            cond: Condvar::new(),
+            is_stopped: AtomicBool::new(false),
+        }
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.is_stopped.load(Ordering::Relaxed)
+    }
+
+    fn stop(&self) {
+        self.is_stopped.store(true, Ordering::Relaxed);
+        let _state = self.state.lock().unwrap();
+        self.cond.notify_all();
+    }
+
+    fn finish_task(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.alive_n -= 1;
+        self.cond.notify_all();
+    }
+
+    // Wait for data in given queue, return None if all senders are dropped, computer is stopped,
+    // or all alive tasks are waiting(deadlock).
+    fn recv(&self, queue_id: usize) -> Option<i64> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(value) = state.queues[queue_id].pop_front() {
+                return Some(value);
+            }
+
+            if state.sender_counts[queue_id] == 0 || state.is_deadlock || self.is_stopped() {
+                return None;
+            }
+
+            if state.stuck_n() + 1 >= state.alive_n {
+                // Every other task is stuck too, no one can send data any more.
+                state.is_deadlock = true;
+                self.cond.notify_all();
+                return None;
+            }
+
+            state.waiting_ns[queue_id] += 1;
+            state = self.cond.wait(state).unwrap();
+            state.waiting_ns[queue_id] -= 1;
+        }
+    }
+
+    fn send(&self, queue_id: usize, value: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.queues[queue_id].push_back(value);
+        self.cond.notify_all();
+    }
+}
+
+// Sending end of channel between tasks, which is an output port.
+pub struct ConcSender {
+    runtime: Arc<Runtime>,
+    queue_id: usize,
+}
+
+impl Clone for ConcSender {
+    fn clone(&self) -> Self {
+        self.runtime.state.lock().unwrap().sender_counts[self.queue_id] += 1;
+        Self {
+            runtime: self.runtime.clone(),
+            queue_id: self.queue_id,
+        }
+    }
+}
+
+impl Drop for ConcSender {
+    fn drop(&mut self) {
+        let mut state = self.runtime.state.lock().unwrap();
+        state.sender_counts[self.queue_id] -= 1;
+        self.runtime.cond.notify_all();
+    }
+}
+
+impl OutputPort for ConcSender {
+    fn put(&mut self, value: i64) -> Result<(), Error> {
+        self.runtime.send(self.queue_id, value);
+        Ok(())
+    }
+
+    fn wait_proc_id(&self) -> Option<usize> {
+        None
+    }
+}
+
+// Receiving end of channel between tasks, which is an input port blocking its task until data arrives.
+#[derive(Clone)]
+pub struct ConcReceiver {
+    runtime: Arc<Runtime>,
+    queue_id: usize,
+}
+
+impl ConcReceiver {
+    // Take all data left in channel without waiting.
+    pub fn drain(&self) -> Vec<i64> {
+        let mut state = self.runtime.state.lock().unwrap();
+        state.queues[self.queue_id].drain(..).collect()
+    }
+}
+
+impl InputPort for ConcReceiver {
+    fn get(&mut self) -> Option<i64> {
+        self.runtime.recv(self.queue_id)
+    }
+
+    fn reg_proc(&mut self, _proc_id: usize) {}
+
+    fn pending_input(&self) -> Vec<i64> {
+        let state = self.runtime.state.lock().unwrap();
+        Vec::from_iter(state.queues[self.queue_id].iter().copied())
+    }
+}
+
+struct Task {
+    state: ProcessState,
+    inst_p: usize,
+    mem: Memory,
+    rel_base: i64,
+    step_count: usize,
+    input_port: ParaInputRef,
+    output_port: ParaOutputRef,
+}
+
+impl ExecutionContext for Task {
+    fn read_mem(&mut self, ind: usize) -> Result<i64, Error> {
+        self.mem.read(ind)
+    }
+
+    fn write_mem(&mut self, ind: usize, value: i64) -> Result<(), Error> {
+        self.mem.write(ind, value).map(|_| ())
+    }
+
+    fn input(&mut self) -> Option<i64> {
+        let step = self.step_count;
+        self.input_port.apply_mut(|p| p.get_at(step))
+    }
+
+    fn output(&mut self, value: i64) -> Result<(), Error> {
+        let step = self.step_count;
+        self.output_port.apply_mut(|p| p.put_at(step, value))
+    }
+
+    fn inst_p_mut(&mut self) -> &mut usize {
+        &mut self.inst_p
+    }
+
+    fn rel_base(&self) -> i64 {
+        self.rel_base
+    }
+
+    fn rel_base_mut(&mut self) -> &mut i64 {
+        &mut self.rel_base
+    }
+
+    fn halt(&mut self) {
+        self.state = ProcessState::Halt;
+    }
+}
+
+impl Task {
+    fn run(mut self, runtime: &Runtime) -> Result<ProcessResult, Error> {
+        self.state = ProcessState::Running;
+        while self.state == ProcessState::Running {
+            if runtime.is_stopped() {
+                self.state = ProcessState::Ready;
+                break;
+            }
+
+            if self.inst_p >= self.mem.len() {
+                return Err(Error::ExecutionExceedIntCode(self.inst_p, self.mem.len()));
+            }
+
+            match DecodedInst::decode(&self.mem.code_at(self.inst_p))?.execute(&mut self) {
+                Ok(_) => self.step_count += 1,
+                Err(Error::NotEnoughInput) => self.state = ProcessState::Block,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(ProcessResult {
+            step_count: self.step_count,
+            state: self.state,
+            mem: self.mem,
+        })
+    }
+}
+
+// Computer which runs every process in its own thread, processes talk to each other through blocking channels.
+pub struct ConcIntCodeComputer {
+    // Memory model of tasks spawned later.
+    pub memory_model: MemoryModel,
+    runtime: Arc<Runtime>,
+    tasks: Vec<Task>,
+}
+
+impl Default for ConcIntCodeComputer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConcIntCodeComputer {
+    pub fn new() -> Self {
+        Self {
+            memory_model: MemoryModel::default(),
+            runtime: Arc::new(Runtime::new()),
+            tasks: Vec::new(),
+        }
+    }
+
+    // Create a channel with initial data, its receiver returns None after all senders are dropped.
+    pub fn channel(&self, init_data: &[i64]) -> (ConcSender, ConcReceiver) {
+        let mut state = self.runtime.state.lock().unwrap();
+        let queue_id = state.queues.len();
+        state
+            .queues
+            .push(VecDeque::from_iter(init_data.iter().copied()));
+        state.sender_counts.push(1);
+        state.waiting_ns.push(0);
+
+        (
+            ConcSender {
+                runtime: self.runtime.clone(),
+                queue_id,
+            },
+            ConcReceiver {
+                runtime: self.runtime.clone(),
+                queue_id,
+            },
+        )
+    }
+
+    // Add a task running given image, all tasks start together when computer is joined. Return its process id.
+    pub fn spawn<ID, OD>(
+        &mut self,
+        image: &[i64],
+        input_dev: ParaInputDevice<ID>,
+        output_dev: ParaOutputDevice<OD>,
+    ) -> usize
+    where
+        ID: InputPort + Send,
+        OD: OutputPort + Send,
+    {
+        let task = Task {
+            state: ProcessState::Ready,
+            inst_p: 0,
+            mem: Memory::new(self.memory_model, image),
+            rel_base: 0,
+            step_count: 0,
+            input_port: input_dev.input_port(),
+            output_port: output_dev.output_port(),
+        };
+        self.tasks.push(task);
+
+        self.tasks.len() - 1
+    }
+
+    // Wait until all tasks are halted or blocked forever.
+    pub fn join(self) -> Result<ProcsExecutionResult, Error> {
+        self.join_until(|| false)
+    }
+
+    // Wait until all tasks are halted or blocked forever, or stop them when given condition is satisfied.
+    pub fn join_until<F: FnMut() -> bool>(
+        self,
+        mut stop_cond: F,
+    ) -> Result<ProcsExecutionResult, Error> {
+        self.runtime.state.lock().unwrap().alive_n = self.tasks.len();
+        let handles = self
+            .tasks
+            .into_iter()
+            .map(|task| {
+                let runtime = self.runtime.clone();
+                thread::spawn(move || {
+                    let res = task.run(&runtime);
+                    runtime.finish_task();
+                    res
+                })
+            })
+            .collect::<Vec<JoinHandle<_>>>();
+        while self.runtime.state.lock().unwrap().alive_n > 0 {
+            if stop_cond() {
+                self.runtime.stop();
+                break;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        let mut proc_snapshots = HashMap::new();
+        for (id, handle) in handles.into_iter().enumerate() {
+            let res = handle
+                .join()
+                .map_err(|_| Error::IOProcessError(format!("task({}) panicked", id)))??;
+            proc_snapshots.insert(id, res);
+        }
+
+        Ok(ProcsExecutionResult::new(proc_snapshots))
+    }
+}
+
+#[test]
+fn test_conc_amp_loop() {
+    use crate::{
+        day7::{amp_loop, amp_loop_conc, AmpSettings},
+        int_code::{com::SeqIntCodeComputer, read_int_code},
+    };
+
+    let amp_code = read_int_code("day7_inputs.txt").unwrap();
+    let mut computer = SeqIntCodeComputer::new(false);
+    let mut setting_n = 0;
+    for setting in AmpSettings::from((5..10).collect::<Vec<_>>().as_slice()).iter() {
+        let seq_res = amp_loop(&mut computer, &amp_code, setting).map_err(|e| e.to_string());
+        let conc_res = amp_loop_conc(&amp_code, setting).map_err(|e| e.to_string());
+        assert!(seq_res.is_ok(), "{:?}", setting);
+        assert_eq!(conc_res, seq_res, "{:?}", setting);
+        setting_n += 1;
+    }
+    assert_eq!(setting_n, 120);
+}
+
+#[test]
+fn test_conc_network() {
+    use crate::{day23, int_code::read_int_code};
+
+    let network_code = read_int_code("day23_inputs.txt").unwrap();
+    let (host_n, check_addr) = (50, 255);
+    let first_y = |hub: day23::NetworkHub| hub.recv_pac_log(check_addr, 0).map(|p| p.y());
+    let seq_y = day23::run_network(host_n, &network_code)
+        .map(first_y)
+        .unwrap();
+    let conc_y = day23::run_network_conc(host_n, &network_code)
+        .map(first_y)
+        .unwrap();
+    assert!(seq_y.is_some());
+    assert_eq!(conc_y, seq_y);
+}
+
+#[test]
+fn test_conc_network_nat() {
+    use crate::{day23, int_code::read_int_code};
+
+    let network_code = read_int_code("day23_inputs.txt").unwrap();
+    let last_nat_y = |hub: day23::NetworkHub| hub.nat().unwrap().sent_pacs().last().map(|p| p.y());
+    let seq_nat_y = day23::run_network_nat(50, &network_code, 255, 0)
+        .map(last_nat_y)
+        .unwrap();
+    let conc_nat_y = day23::run_network_nat_conc(50, &network_code, 255, 0)
+        .map(last_nat_y)
+        .unwrap();
+    assert!(seq_nat_y.is_some());
+    assert_eq!(conc_nat_y, seq_nat_y);
+}
+
+#[test]
+fn test_conc_deadlock() {
+    use std::sync::mpsc;
+
+    // Each task reads from the other's channel before writing anything.
+    let image = [3, 0, 4, 0, 99];
+    let mut computer = ConcIntCodeComputer::new();
+    let (send_a, recv_a) = computer.channel(&[]);
+    let (send_b, recv_b) = computer.channel(&[]);
+    let id_a = computer.spawn(
+        &image,
+        ParaInputDevice::new(recv_b),
+        ParaOutputDevice::new(send_a),
+    );
+    let id_b = computer.spawn(
+        &image,
+        ParaInputDevice::new(recv_a),
+        ParaOutputDevice::new(send_b),
+    );
+
+    // Join in another thread, so the test fails instead of hanging if deadlock isn't found.
+    let (res_send, res_recv) = mpsc::channel();
+    thread::spawn(move || res_send.send(computer.join()).unwrap());
+    let res = res_recv
+        .recv_timeout(Duration::from_secs(10))
+        .unwrap()
+        .unwrap();
+    for id in [id_a, id_b] {
+        let proc_res = res.proc_snapshots(id).unwrap();
+        assert_eq!(proc_res.state(), ProcessState::Block);
+        assert_eq!(proc_res.step_count(), 0);
+    }
+}
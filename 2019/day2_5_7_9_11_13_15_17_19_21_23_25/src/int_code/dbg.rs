@@ -0,0 +1,81 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+// Why a process stopped running in computer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    StepLimit,
+    Breakpoint(usize),
+    Watchpoint(usize, i64, i64), // (address, old value, new value)
+    Block,
+    Halt,
+    // Process yields processor after output, when scheduled until output.
+    Output,
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::StepLimit => write!(f, "stopped after given steps"),
+            StopReason::Breakpoint(addr) => write!(f, "hit breakpoint at {}", addr),
+            StopReason::Watchpoint(addr, old, new) => write!(
+                f,
+                "hit watchpoint at {}, value changed from {} to {}",
+                addr, old, new
+            ),
+            StopReason::Block => write!(f, "blocked for requiring input"),
+            StopReason::Halt => write!(f, "halted"),
+            StopReason::Output => write!(f, "yielded after output"),
+        }
+    }
+}
+
+// Breakpoints(on instruction pointer) and watchpoints(on memory writing) shared by all processes in one computer.
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+    watch_hit: Option<StopReason>,
+}
+
+impl Debugger {
+    pub fn add_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.insert(addr)
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.remove(&addr)
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.watchpoints.iter().copied()
+    }
+
+    pub(super) fn is_break_at(&self, addr: usize) -> bool {
+        self.breakpoints.contains(&addr)
+    }
+
+    pub(super) fn on_write(&mut self, addr: usize, old_value: i64, new_value: i64) {
+        if !self.watchpoints.is_empty()
+            && self.watch_hit.is_none()
+            && self.watchpoints.contains(&addr)
+        {
+            self.watch_hit = Some(StopReason::Watchpoint(addr, old_value, new_value));
+        }
+    }
+
+    pub(super) fn take_watch_hit(&mut self) -> Option<StopReason> {
+        self.watch_hit.take()
+    }
+}
@@ -0,0 +1,573 @@
+use std::fmt::Display;
+
+use crate::Error;
+
+use super::{
+    com::{ConcIntCodeComputer, ExecutionEngine, ParaIntCodeComputer, SeqIntCodeComputer},
+    dbg::StopReason,
+    io::{Channel, ParaInputDevice, ParaOutputDevice, SeqInputDevice, SeqOutputDevice},
+    mem::{Memory, MemoryModel},
+    rng::SplitMix64,
+    to_code_str,
+};
+
+// Count of cells after code in generated images, which instructions read and write.
+const DATA_LEN: usize = 8;
+
+// Memory cap of reference target, images accessing beyond it only run on capped targets, as computed addresses can be
+// too far for other memory(or copy of sparse memory).
+pub const MEM_CAP: usize = 1 << 16;
+
+// Which edge case is injected into generated image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseKind {
+    // Every instruction is well formed, runtime errors may still happen.
+    Valid,
+    // Access memory at negative address, by position or relative mode.
+    NegativeAddress,
+    // Write parameter in immediate mode.
+    ImmediateWrite,
+    // No halt at the end, or the last instruction is truncated.
+    RunOffEnd,
+    BadOpcode,
+    BadMode,
+    // Random cells of valid image are overwritten.
+    Mutated,
+}
+
+impl CaseKind {
+    pub fn all() -> [CaseKind; 7] {
+        [
+            CaseKind::Valid,
+            CaseKind::NegativeAddress,
+            CaseKind::ImmediateWrite,
+            CaseKind::RunOffEnd,
+            CaseKind::BadOpcode,
+            CaseKind::BadMode,
+            CaseKind::Mutated,
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FuzzCase {
+    kind: CaseKind,
+    image: Vec<i64>,
+    inputs: Vec<i64>,
+}
+
+impl Display for FuzzCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "kind {:?}", self.kind)?;
+        writeln!(f, "inputs {}", to_code_str(&self.inputs))?;
+        write!(f, "image {}", to_code_str(&self.image))
+    }
+}
+
+impl FuzzCase {
+    pub fn new(kind: CaseKind, image: Vec<i64>, inputs: Vec<i64>) -> Self {
+        Self {
+            kind,
+            image,
+            inputs,
+        }
+    }
+
+    pub fn kind(&self) -> CaseKind {
+        self.kind
+    }
+
+    pub fn image(&self) -> &[i64] {
+        &self.image
+    }
+
+    pub fn inputs(&self) -> &[i64] {
+        &self.inputs
+    }
+}
+
+// Generates random images, made of random instructions followed by halt and a small data area.
+pub struct ImageGenerator {
+    rng: SplitMix64,
+}
+
+impl ImageGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    // Case of random kind.
+    pub fn next_case(&mut self) -> FuzzCase {
+        let kinds = CaseKind::all();
+        let kind = kinds[self.rng.below(kinds.len())];
+        self.case(kind)
+    }
+
+    pub fn case(&mut self, kind: CaseKind) -> FuzzCase {
+        let mut insts = self.valid_insts();
+        match kind {
+            CaseKind::Valid | CaseKind::Mutated | CaseKind::RunOffEnd => (),
+            CaseKind::NegativeAddress => {
+                let inst = if self.rng.below(2) == 0 {
+                    // Read at negative address in position mode, below placeholders of data cells.
+                    vec![1, self.rng.between(-30, -(DATA_LEN as i64) - 1), 0, 0]
+                } else {
+                    // Move relative base below zero, then read relative to it.
+                    vec![109, self.rng.between(-20, -1), 204, 0]
+                };
+                let ind = self.rng.below(insts.len() + 1);
+                insts.insert(ind, inst);
+            }
+            CaseKind::ImmediateWrite => {
+                let inst = match self.rng.below(3) {
+                    0 => vec![11101, 1, 2, 0],
+                    1 => vec![103],
+                    _ => vec![11107, 1, 2, 0],
+                };
+                let ind = self.rng.below(insts.len() + 1);
+                insts.insert(ind, inst);
+            }
+            CaseKind::BadOpcode => {
+                let opcode = match self.rng.below(3) {
+                    0 => 0,
+                    1 => self.rng.between(10, 98),
+                    _ => self.rng.between(-99, -1),
+                };
+                let ind = self.rng.below(insts.len() + 1);
+                insts.insert(ind, vec![opcode, 0, 0, 0]);
+            }
+            CaseKind::BadMode => {
+                let ind = self.rng.below(insts.len() + 1);
+                let mode = self.rng.between(3, 9);
+                insts.insert(ind, vec![mode * 100 + 1, 0, 0, 0]);
+            }
+        }
+
+        let mut image = Vec::new();
+        let mut inst_addrs = Vec::new();
+        for inst in &insts {
+            inst_addrs.push(image.len());
+            image.extend(inst);
+        }
+        if kind == CaseKind::RunOffEnd {
+            // Either the last instruction is truncated, or code runs into data area without halt.
+            if self.rng.below(2) == 0 {
+                image.truncate(image.len() - 1);
+            }
+        } else {
+            image.push(99);
+        }
+        let code_len = image.len();
+        image.extend((0..DATA_LEN).map(|_| self.rng.between(-5, 5)));
+        self.link(&mut image, &inst_addrs, &insts, code_len);
+
+        if kind == CaseKind::Mutated {
+            for _ in 0..=self.rng.below(3) {
+                let ind = self.rng.below(image.len());
+                image[ind] = self.rng.between(-10, 300);
+            }
+        }
+
+        let inputs = (0..self.rng.below(4))
+            .map(|_| self.rng.between(-100, 100))
+            .collect();
+        FuzzCase::new(kind, image, inputs)
+    }
+
+    // Well formed instructions, addresses are fixed by link.
+    fn valid_insts(&mut self) -> Vec<Vec<i64>> {
+        let inst_n = 1 + self.rng.below(12);
+        (0..inst_n)
+            .map(|_| {
+                let opcode = self.rng.between(1, 9);
+                let param_n = match opcode {
+                    1 | 2 | 7 | 8 => 3,
+                    5 | 6 => 2,
+                    _ => 1,
+                };
+                let is_write = |i: usize| match opcode {
+                    1 | 2 | 7 | 8 => i == 2,
+                    3 => i == 0,
+                    _ => false,
+                };
+
+                let mut full_opcode = opcode;
+                let mut inst = vec![0];
+                let mut ratio = 100;
+                for i in 0..param_n {
+                    let mode = if is_write(i) {
+                        [0, 2][self.rng.below(2)]
+                    } else {
+                        self.rng.between(0, 2)
+                    };
+                    full_opcode += mode * ratio;
+                    ratio *= 10;
+                    inst.push(match mode {
+                        1 => self.rng.between(-10, 10),
+                        2 => self.rng.between(-2, DATA_LEN as i64),
+                        // Position parameters are placeholders of data cells.
+                        _ => -(self.rng.below(DATA_LEN) as i64) - 1,
+                    });
+                }
+                inst[0] = full_opcode;
+                inst
+            })
+            .collect()
+    }
+
+    // Point position mode parameters at data cells, and jump targets at instructions.
+    fn link(
+        &mut self,
+        image: &mut [i64],
+        inst_addrs: &[usize],
+        insts: &[Vec<i64>],
+        code_len: usize,
+    ) {
+        for (addr, inst) in inst_addrs.iter().zip(insts) {
+            let opcode = inst[0];
+            if !(1..=9).contains(&(opcode % 100)) || opcode < 0 {
+                continue;
+            }
+
+            for i in 1..inst.len() {
+                if addr + i >= image.len() {
+                    break;
+                }
+                let mode = opcode / 10i64.pow(i as u32 + 1) % 10;
+                let is_jump_target = matches!(opcode % 100, 5 | 6) && i == 2;
+                if is_jump_target && mode == 1 {
+                    image[addr + i] = inst_addrs[self.rng.below(inst_addrs.len())] as i64;
+                } else if mode == 0 && inst[i] < 0 && (inst[i] >= -(DATA_LEN as i64)) {
+                    image[addr + i] = (code_len as i64) - inst[i] - 1;
+                }
+            }
+        }
+    }
+}
+
+// Computer and configuration an image runs on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Seq(ExecutionEngine, MemoryModel),
+    Para(ExecutionEngine, MemoryModel),
+    // Concurrent computer can't limit steps, so it only runs images known to stop.
+    Conc(MemoryModel),
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Seq(engine, model) => write!(f, "seq/{:?}/{:?}", engine, model),
+            Target::Para(engine, model) => write!(f, "para/{:?}/{:?}", engine, model),
+            Target::Conc(model) => write!(f, "conc/{:?}", model),
+        }
+    }
+}
+
+impl Target {
+    // Every computer with every engine and memory model, the first one is capped dense memory with boxed engine.
+    pub fn all() -> Vec<Target> {
+        let engines = [
+            ExecutionEngine::Boxed,
+            ExecutionEngine::Decoded,
+            ExecutionEngine::Cached,
+        ];
+        let models = [
+            MemoryModel::Dense(Some(MEM_CAP)),
+            MemoryModel::Dense(None),
+            MemoryModel::Sparse,
+        ];
+        let mut targets = Vec::new();
+        for model in models {
+            targets.extend(engines.iter().map(|&e| Target::Seq(e, model)));
+            targets.extend(engines.iter().map(|&e| Target::Para(e, model)));
+            targets.push(Target::Conc(model));
+        }
+
+        targets
+    }
+
+    fn memory_model(&self) -> MemoryModel {
+        match *self {
+            Target::Seq(_, model) | Target::Para(_, model) | Target::Conc(model) => model,
+        }
+    }
+
+    // Run given case at most given steps, concurrent computer ignores the limit.
+    pub fn run(&self, case: &FuzzCase, step_limit: usize) -> Outcome {
+        match *self {
+            Target::Seq(engine, model) => {
+                let mut computer = SeqIntCodeComputer::new(false);
+                computer.engine = engine;
+                computer.memory_model = model;
+                let input_dev = SeqInputDevice::new(Channel::new(case.inputs()));
+                let output_dev = SeqOutputDevice::new(Channel::new(&[]));
+                let proc_id = computer.new_proc(case.image(), input_dev, output_dev.clone());
+                let res = computer.run_proc(proc_id, Some(step_limit));
+                let image = computer.take_proc_result(proc_id).map(|r| r.mem().clone());
+                let outputs = output_dev.check(|c| c.data().iter().copied().collect());
+                Outcome::new(res, outputs, image)
+            }
+            Target::Para(engine, model) => {
+                let mut computer = ParaIntCodeComputer::new(false);
+                computer.engine = engine;
+                computer.memory_model = model;
+                let input_dev = ParaInputDevice::new(Channel::new(case.inputs()));
+                let output_dev = ParaOutputDevice::new(Channel::new(&[]));
+                let proc_id = computer.new_proc(case.image(), input_dev, output_dev.clone());
+                let res = computer.run_proc(proc_id, Some(step_limit));
+                let image = computer.take_proc_result(proc_id).map(|r| r.mem().clone());
+                let outputs = output_dev.check(|c| c.data().iter().copied().collect());
+                Outcome::new(res, outputs, image)
+            }
+            Target::Conc(model) => {
+                let mut computer = ConcIntCodeComputer::new();
+                computer.memory_model = model;
+                let input_dev = ParaInputDevice::new(Channel::new(case.inputs()));
+                let output_dev = ParaOutputDevice::new(Channel::new(&[]));
+                let proc_id = computer.spawn(case.image(), input_dev, output_dev.clone());
+                let res = computer.join();
+                let outputs = output_dev.check(|c| c.data().iter().copied().collect());
+                match res {
+                    Ok(res) => {
+                        let res = res.proc_snapshots(proc_id).unwrap();
+                        let end = match res.state() {
+                            super::com::ProcessState::Halt => End::Halt,
+                            _ => End::Block,
+                        };
+                        Outcome {
+                            end,
+                            outputs,
+                            image: Some(res.mem().clone()),
+                        }
+                    }
+                    // Image of failed task is dropped.
+                    Err(e) => Outcome {
+                        end: End::Error(format!("{:?}", e)),
+                        outputs,
+                        image: None,
+                    },
+                }
+            }
+        }
+    }
+}
+
+// How execution ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum End {
+    Halt,
+    Block,
+    StepLimit,
+    // Debug text of error, so variant and its values are compared.
+    Error(String),
+}
+
+impl Display for End {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            End::Halt => write!(f, "halt"),
+            End::Block => write!(f, "block"),
+            End::StepLimit => write!(f, "step limit"),
+            End::Error(e) => write!(f, "error {}", e),
+        }
+    }
+}
+
+impl End {
+    // Name of error variant or end, for grouping outcomes.
+    pub fn name(&self) -> &str {
+        match self {
+            End::Halt => "Halt",
+            End::Block => "Block",
+            End::StepLimit => "StepLimit",
+            End::Error(e) => e.split('(').next().unwrap_or(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    end: End,
+    outputs: Vec<i64>,
+    image: Option<Memory>,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, outputs [{}]", self.end, to_code_str(&self.outputs))?;
+        match &self.image {
+            Some(image) => write!(f, ", image [{}]", image),
+            None => write!(f, ", no image"),
+        }
+    }
+}
+
+impl Outcome {
+    fn new(res: Result<StopReason, Error>, outputs: Vec<i64>, image: Option<Memory>) -> Self {
+        let end = match res {
+            Ok(StopReason::Halt) => End::Halt,
+            Ok(StopReason::Block) => End::Block,
+            Ok(_) => End::StepLimit,
+            Err(e) => End::Error(format!("{:?}", e)),
+        };
+
+        Self {
+            end,
+            outputs,
+            image,
+        }
+    }
+
+    pub fn end(&self) -> &End {
+        &self.end
+    }
+
+    pub fn outputs(&self) -> &[i64] {
+        &self.outputs
+    }
+
+    pub fn image(&self) -> Option<&Memory> {
+        self.image.as_ref()
+    }
+
+    // Same end, outputs and image, missing image matches any one.
+    pub fn agrees(&self, other: &Outcome) -> bool {
+        self.end == other.end
+            && self.outputs == other.outputs
+            && (self.image.is_none() || other.image.is_none() || self.image == other.image)
+    }
+}
+
+// Case whose outcomes differ among targets.
+#[derive(Debug)]
+pub struct Divergence {
+    case: FuzzCase,
+    outcomes: Vec<(Target, Outcome)>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.case)?;
+        for (target, outcome) in &self.outcomes {
+            writeln!(f, "  {}: {}", target, outcome)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Divergence {
+    pub fn case(&self) -> &FuzzCase {
+        &self.case
+    }
+
+    pub fn outcomes(&self) -> &[(Target, Outcome)] {
+        &self.outcomes
+    }
+}
+
+// Runs cases on every target, the first target is the reference others are compared to.
+pub struct DiffTester {
+    targets: Vec<Target>,
+    step_limit: usize,
+}
+
+impl DiffTester {
+    pub fn new(targets: Vec<Target>, step_limit: usize) -> Self {
+        assert!(!targets.is_empty(), "Differential test needs a target.");
+        Self {
+            targets,
+            step_limit,
+        }
+    }
+
+    // Outcome of reference target if all targets agree. Reference target should have capped memory, see MEM_CAP.
+    pub fn check(&self, case: &FuzzCase) -> Result<Outcome, Divergence> {
+        let reference = self.targets[0].run(case, self.step_limit);
+        let mut outcomes = vec![(self.targets[0], reference.clone())];
+        for &target in &self.targets[1..] {
+            if matches!(target, Target::Conc(_)) && reference.end == End::StepLimit {
+                continue;
+            }
+            if reference.end.name() == "MemoryLimitExceeded"
+                && !matches!(target.memory_model(), MemoryModel::Dense(Some(_)))
+            {
+                continue;
+            }
+            outcomes.push((target, target.run(case, self.step_limit)));
+        }
+
+        if outcomes.iter().all(|(_, o)| o.agrees(&reference)) {
+            Ok(reference)
+        } else {
+            Err(Divergence {
+                case: case.clone(),
+                outcomes,
+            })
+        }
+    }
+}
+
+#[test]
+fn test_diff_random_cases() {
+    let tester = DiffTester::new(Target::all(), 1000);
+    for seed in [1, 2019, 0xdead_beef] {
+        let mut generator = ImageGenerator::new(seed);
+        for _ in 0..100 {
+            let case = generator.next_case();
+            if let Err(divergence) = tester.check(&case) {
+                panic!("Seed {} diverges:\n{}", seed, divergence);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_diff_error_cases() {
+    let tester = DiffTester::new(Target::all(), 1000);
+    let cases = [
+        // Read from negative address in position mode, then in relative mode.
+        (
+            CaseKind::NegativeAddress,
+            vec![1, -1, 0, 0, 99],
+            "ImageIndexError(-1)",
+        ),
+        (
+            CaseKind::NegativeAddress,
+            vec![109, -5, 1201, 0, 0, 0, 99],
+            "ImageIndexError(-5)",
+        ),
+        // Write parameter of add in immediate mode.
+        (
+            CaseKind::ImmediateWrite,
+            vec![11101, 1, 1, 0, 99],
+            "InvalidWriteMemoryMode(1)",
+        ),
+        // No halt after add, execution runs off the end of image.
+        (
+            CaseKind::RunOffEnd,
+            vec![1101, 1, 1, 0],
+            "ExecutionExceedIntCode(4, 4)",
+        ),
+    ];
+    for (kind, image, error) in cases {
+        let case = FuzzCase::new(kind, image, Vec::new());
+        match tester.check(&case) {
+            Ok(outcome) => assert_eq!(*outcome.end(), End::Error(error.to_string()), "{}", case),
+            Err(divergence) => panic!("Case diverges:\n{}", divergence),
+        }
+    }
+
+    let case = FuzzCase::new(
+        CaseKind::Valid,
+        vec![3, 9, 1002, 9, 3, 9, 4, 9, 99, 0],
+        vec![7],
+    );
+    let outcome = tester.check(&case).unwrap();
+    assert_eq!(*outcome.end(), End::Halt);
+    assert_eq!(outcome.outputs(), &[21]);
+    assert_eq!(outcome.image().unwrap().get(9), 21);
+}
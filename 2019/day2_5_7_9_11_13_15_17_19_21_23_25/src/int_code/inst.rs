@@ -37,22 +37,7 @@ pub trait Instruction: Debug {
     where
         Self: Sized,
     {
-        match param_mode {
-            ParameterMode::Position | ParameterMode::Relative => {
-                let pos = if param_mode == ParameterMode::Relative {
-                    exe_state.rel_base() + param
-                } else {
-                    param
-                };
-
-                if pos < 0 {
-                    Err(Error::ImageIndexError(pos))
-                } else {
-                    Ok(exe_state.read_mem(pos as usize))
-                }
-            }
-            ParameterMode::Immediate => Ok(param),
-        }
+        read_param(exe_state, param, param_mode)
     }
 
     fn write_mem(
@@ -64,25 +49,60 @@ pub trait Instruction: Debug {
     where
         Self: Sized,
     {
-        match param_mode {
-            ParameterMode::Position | ParameterMode::Relative => {
-                let pos = if param_mode == ParameterMode::Relative {
-                    exe_state.rel_base() + param
-                } else {
-                    param
-                };
+        write_param(exe_state, param, param_mode, value)
+    }
+}
 
-                if pos < 0 {
-                    Err(Error::ImageIndexError(param))
-                } else {
-                    Ok(exe_state.write_mem(pos as usize, value))
-                }
+fn read_param(
+    exe_state: &mut dyn ExecutionContext,
+    param: i64,
+    param_mode: ParameterMode,
+) -> Result<i64, Error> {
+    match param_mode {
+        ParameterMode::Position | ParameterMode::Relative => {
+            let pos = if param_mode == ParameterMode::Relative {
+                exe_state.rel_base() + param
+            } else {
+                param
+            };
+
+            if pos < 0 {
+                Err(Error::ImageIndexError(pos))
+            } else {
+                exe_state.read_mem(pos as usize)
+            }
+        }
+        ParameterMode::Immediate => Ok(param),
+    }
+}
+
+fn write_param(
+    exe_state: &mut dyn ExecutionContext,
+    param: i64,
+    param_mode: ParameterMode,
+    value: i64,
+) -> Result<(), Error> {
+    match param_mode {
+        ParameterMode::Position | ParameterMode::Relative => {
+            let pos = if param_mode == ParameterMode::Relative {
+                exe_state.rel_base() + param
+            } else {
+                param
+            };
+
+            if pos < 0 {
+                Err(Error::ImageIndexError(param))
+            } else {
+                exe_state.write_mem(pos as usize, value)
             }
-            ParameterMode::Immediate => Err(Error::InvalidWriteMemoryMode(param_mode.int_value())),
         }
+        ParameterMode::Immediate => Err(Error::InvalidWriteMemoryMode(param_mode.int_value())),
     }
 }
 
+// Length of the longest instruction(operation code with three parameters).
+pub const MAX_INST_LENGTH: usize = 4;
+
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntEnum, Hash)]
 pub enum InstOpcodeInd {
@@ -98,6 +118,32 @@ pub enum InstOpcodeInd {
     Halt = 99,
 }
 
+impl InstOpcodeInd {
+    pub fn param_count(&self) -> usize {
+        match self {
+            InstOpcodeInd::Add
+            | InstOpcodeInd::Multiply
+            | InstOpcodeInd::LessThan
+            | InstOpcodeInd::Equals => 3,
+            InstOpcodeInd::JumpIfTrue | InstOpcodeInd::JumpIfFalse => 2,
+            InstOpcodeInd::Input | InstOpcodeInd::Output | InstOpcodeInd::AdjustRelativeBase => 1,
+            InstOpcodeInd::Halt => 0,
+        }
+    }
+}
+
+// Inverse of parse_opcode, encode operation code index and parameter modes into one integer.
+pub fn encode_opcode(opcode_ind: InstOpcodeInd, param_modes: &[ParameterMode]) -> i64 {
+    let mut opcode = i64::from(opcode_ind.int_value());
+    let mut cur_ratio = 100;
+    for mode in param_modes {
+        opcode += i64::from(mode.int_value()) * cur_ratio;
+        cur_ratio *= 10;
+    }
+
+    opcode
+}
+
 type ParseFunc = fn(&[i64]) -> Result<Box<dyn Instruction>, Error>;
 static INST_PARSE_MAP: Lazy<HashMap<InstOpcodeInd, ParseFunc>> = Lazy::new(|| {
     let mut map = HashMap::new();
@@ -163,9 +209,10 @@ fn parse_opcode(
         ));
     }
 
+    let opcode = u32::try_from(opcode).map_err(|_| Error::InvalidOpcode(opcode))?;
     let mut cur_ratio = 100;
     for i in 0..param_modes.len() {
-        let cur_digit = u32::try_from(opcode).unwrap() / cur_ratio % 10;
+        let cur_digit = opcode / cur_ratio % 10;
         param_modes[i] = ParameterMode::from_int(cur_digit as u8)
             .map_err(|_| Error::UnknownParameterMode(cur_digit))?;
 
@@ -176,11 +223,9 @@ fn parse_opcode(
 }
 
 fn parse_opcode_ind(opcode: i64) -> Result<u32, Error> {
-    if opcode < 0 {
-        Err(Error::InvalidOpcode(opcode))
-    } else {
-        Ok(u32::try_from(opcode).unwrap() % 100)
-    }
+    u32::try_from(opcode)
+        .map(|opcode| opcode % 100)
+        .map_err(|_| Error::InvalidOpcode(opcode))
 }
 
 macro_rules! def_instruction {
@@ -340,3 +385,100 @@ def_instruction!(name=AdjustRelativeBase, length=2, index=InstOpcodeInd::AdjustR
 
     Ok(())
 });
+
+// Instruction decoded without allocation, executed by matching its operation code index.
+#[derive(Debug, Clone, Copy)]
+pub struct DecodedInst {
+    opcode_ind: InstOpcodeInd,
+    params: [i64; 3],
+    param_modes: [ParameterMode; 3],
+}
+
+impl DecodedInst {
+    // Decode instruction at the start of given code, report the same errors as parse_cur_inst.
+    pub fn decode(code: &[i64]) -> Result<Self, Error> {
+        let opcode = code[0];
+        let opcode_ind = parse_opcode_ind(opcode)
+            .and_then(|n| InstOpcodeInd::from_int(n).map_err(|_| Error::InvalidOpcodeIndex(n)))?;
+        let param_count = opcode_ind.param_count();
+        if code.len() < param_count + 1 {
+            return Err(Error::MissingCodeForInstruction(opcode_ind.int_value()));
+        }
+
+        let mut inst = DecodedInst {
+            opcode_ind,
+            params: [0; 3],
+            param_modes: [ParameterMode::default(); 3],
+        };
+        parse_opcode(
+            opcode,
+            opcode_ind.int_value(),
+            &mut inst.param_modes[..param_count],
+        )?;
+        inst.params[..param_count].copy_from_slice(&code[1..=param_count]);
+
+        Ok(inst)
+    }
+
+    pub fn opcode_ind(&self) -> InstOpcodeInd {
+        self.opcode_ind
+    }
+
+    pub fn length(&self) -> usize {
+        self.opcode_ind.param_count() + 1
+    }
+
+    pub fn params(&self) -> &[i64] {
+        &self.params[..self.opcode_ind.param_count()]
+    }
+
+    pub fn param_modes(&self) -> &[ParameterMode] {
+        &self.param_modes[..self.opcode_ind.param_count()]
+    }
+
+    pub fn execute(&self, exe_state: &mut dyn ExecutionContext) -> Result<(), Error> {
+        let [p0, p1, p2] = self.params;
+        let [m0, m1, m2] = self.param_modes;
+        match self.opcode_ind {
+            InstOpcodeInd::Add
+            | InstOpcodeInd::Multiply
+            | InstOpcodeInd::LessThan
+            | InstOpcodeInd::Equals => {
+                let input0 = read_param(exe_state, p0, m0)?;
+                let input1 = read_param(exe_state, p1, m1)?;
+                let value = match self.opcode_ind {
+                    InstOpcodeInd::Add => input0 + input1,
+                    InstOpcodeInd::Multiply => input0 * input1,
+                    InstOpcodeInd::LessThan => i64::from(input0 < input1),
+                    _ => i64::from(input0 == input1),
+                };
+                write_param(exe_state, p2, m2, value)?;
+            }
+            InstOpcodeInd::Input => {
+                let input = exe_state.input().ok_or(Error::NotEnoughInput)?;
+                write_param(exe_state, p0, m0, input)?;
+            }
+            InstOpcodeInd::Output => {
+                let value = read_param(exe_state, p0, m0)?;
+                exe_state.output(value)?;
+            }
+            InstOpcodeInd::JumpIfTrue | InstOpcodeInd::JumpIfFalse => {
+                let condition = read_param(exe_state, p0, m0)?;
+                if (condition != 0) == (self.opcode_ind == InstOpcodeInd::JumpIfTrue) {
+                    let target = read_param(exe_state, p1, m1)?;
+                    *exe_state.inst_p_mut() =
+                        usize::try_from(target).map_err(|_| Error::InvalidJumpTarget(target))?;
+                    return Ok(());
+                }
+            }
+            InstOpcodeInd::AdjustRelativeBase => {
+                let offset = read_param(exe_state, p0, m0)?;
+                *exe_state.rel_base_mut() += offset;
+            }
+            InstOpcodeInd::Halt => exe_state.halt(),
+        }
+        *exe_state.inst_p_mut() += self.length();
+
+        Ok(())
+    }
+}
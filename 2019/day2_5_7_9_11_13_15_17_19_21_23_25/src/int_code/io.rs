@@ -1,6 +1,7 @@
 use std::{
     cell::RefCell,
     collections::VecDeque,
+    fmt::Display,
     rc::Rc,
     sync::{Arc, Mutex},
 };
@@ -11,12 +12,37 @@ use crate::Error;
 pub trait InputPort {
     fn get(&mut self) -> Option<i64>;
     fn reg_proc(&mut self, proc_id: usize);
+
+    // Get data for input instruction executed at given step of process, for port which cares about timing.
+    fn get_at(&mut self, _step: usize) -> Option<i64> {
+        self.get()
+    }
+
+    // Data buffered in port but not read by process yet, saved in process snapshot.
+    fn pending_input(&self) -> Vec<i64> {
+        Vec::new()
+    }
 }
 
 // Output port for process, data sink
 pub trait OutputPort {
     fn put(&mut self, value: i64) -> Result<(), Error>;
     fn wait_proc_id(&self) -> Option<usize>;
+
+    // Put data from output instruction executed at given step of process, for port which cares about timing.
+    fn put_at(&mut self, _step: usize, value: i64) -> Result<(), Error> {
+        self.put(value)
+    }
+
+    // All processes waiting for data from this port, for port which feeds more than one process.
+    fn wait_proc_ids(&self) -> Vec<usize> {
+        self.wait_proc_id().into_iter().collect()
+    }
+
+    // Data written by process but not consumed yet, saved in process snapshot.
+    fn pending_output(&self) -> Vec<i64> {
+        Vec::new()
+    }
 }
 
 pub trait IOPort: InputPort + OutputPort {}
@@ -310,6 +336,10 @@ impl InputPort for Channel {
     fn reg_proc(&mut self, proc_id: usize) {
         self.output_reg_proc_id = Some(proc_id);
     }
+
+    fn pending_input(&self) -> Vec<i64> {
+        Vec::from_iter(self.data.iter().copied())
+    }
 }
 
 impl OutputPort for Channel {
@@ -320,6 +350,10 @@ impl OutputPort for Channel {
     fn wait_proc_id(&self) -> Option<usize> {
         self.output_reg_proc_id
     }
+
+    fn pending_output(&self) -> Vec<i64> {
+        Vec::from_iter(self.data.iter().copied())
+    }
 }
 
 impl Channel {
@@ -338,3 +372,248 @@ impl Channel {
         &mut self.data
     }
 }
+
+// Port made of separate input port and output port, so they can be used by one I/O device.
+pub struct PortPair<I, O> {
+    input: I,
+    output: O,
+}
+
+impl<I: InputPort, O: OutputPort> PortPair<I, O> {
+    pub fn new(input: I, output: O) -> Self {
+        Self { input, output }
+    }
+
+    pub fn input(&self) -> &I {
+        &self.input
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+}
+
+impl<I: InputPort, O> InputPort for PortPair<I, O> {
+    fn get(&mut self) -> Option<i64> {
+        self.input.get()
+    }
+
+    fn get_at(&mut self, step: usize) -> Option<i64> {
+        self.input.get_at(step)
+    }
+
+    fn reg_proc(&mut self, proc_id: usize) {
+        self.input.reg_proc(proc_id)
+    }
+
+    fn pending_input(&self) -> Vec<i64> {
+        self.input.pending_input()
+    }
+}
+
+impl<I, O: OutputPort> OutputPort for PortPair<I, O> {
+    fn put(&mut self, value: i64) -> Result<(), Error> {
+        self.output.put(value)
+    }
+
+    fn put_at(&mut self, step: usize, value: i64) -> Result<(), Error> {
+        self.output.put_at(step, value)
+    }
+
+    fn wait_proc_id(&self) -> Option<usize> {
+        self.output.wait_proc_id()
+    }
+
+    fn wait_proc_ids(&self) -> Vec<usize> {
+        self.output.wait_proc_ids()
+    }
+
+    fn pending_output(&self) -> Vec<i64> {
+        self.output.pending_output()
+    }
+}
+
+// Value passed through ASCII terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsciiEvent {
+    Input(String),  // line read by process, without '\n'
+    Output(String), // line written by process, without '\n'
+    Value(i64),     // output value out of ASCII range
+}
+
+impl Display for AsciiEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsciiEvent::Input(line) | AsciiEvent::Output(line) => write!(f, "{}", line),
+            AsciiEvent::Value(value) => write!(f, "[{}]", value),
+        }
+    }
+}
+
+// Port for programs talking in lines of ASCII text, input lines are queued before process reads them, output is
+// decoded into lines, and output values out of ASCII range are kept separately. Everything passed through it is
+// captured in transcript.
+#[derive(Debug, Default)]
+pub struct AsciiTerminal {
+    input: VecDeque<char>,
+    input_line: String,
+    output_line: String,
+    lines: VecDeque<String>,
+    values: Vec<i64>,
+    transcript: Vec<AsciiEvent>,
+}
+
+impl InputPort for AsciiTerminal {
+    fn get(&mut self) -> Option<i64> {
+        let c = self.input.pop_front()?;
+        if c == '\n' {
+            self.transcript
+                .push(AsciiEvent::Input(std::mem::take(&mut self.input_line)));
+        } else {
+            self.input_line.push(c);
+        }
+
+        Some(c as i64)
+    }
+
+    fn reg_proc(&mut self, _proc_id: usize) {}
+
+    fn pending_input(&self) -> Vec<i64> {
+        self.input.iter().map(|&c| c as i64).collect()
+    }
+}
+
+impl OutputPort for AsciiTerminal {
+    fn put(&mut self, value: i64) -> Result<(), Error> {
+        match u8::try_from(value).ok().filter(|v| v.is_ascii()) {
+            Some(b'\n') => {
+                let line = std::mem::take(&mut self.output_line);
+                self.transcript.push(AsciiEvent::Output(line.clone()));
+                self.lines.push_back(line);
+            }
+            Some(v) => self.output_line.push(char::from(v)),
+            None => {
+                self.values.push(value);
+                self.transcript.push(AsciiEvent::Value(value));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn wait_proc_id(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl AsciiTerminal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Terminal with given lines queued as input.
+    pub fn with_lines<I, S>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut term = Self::new();
+        for line in lines {
+            term.push_line(line.as_ref());
+        }
+        term
+    }
+
+    // Queue one line of input, '\n' is appended.
+    pub fn push_line(&mut self, line: &str) {
+        self.input.extend(line.chars().chain(std::iter::once('\n')));
+    }
+
+    // Whether there's queued input not read by process yet.
+    pub fn has_input(&self) -> bool {
+        !self.input.is_empty()
+    }
+
+    pub fn clear_input(&mut self) {
+        self.input.clear();
+        self.input_line.clear();
+    }
+
+    // Drop everything in terminal, including queued input, output not taken yet, partial line and transcript.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Take the earliest output line not taken yet.
+    pub fn pop_line(&mut self) -> Option<String> {
+        self.lines.pop_front()
+    }
+
+    // Take all output lines not taken yet.
+    pub fn take_lines(&mut self) -> Vec<String> {
+        self.lines.drain(..).collect()
+    }
+
+    // Output not ended by '\n' yet.
+    pub fn partial_line(&self) -> &str {
+        &self.output_line
+    }
+
+    // Take all output lines not taken yet as text, every line is ended by '\n'.
+    pub fn take_text(&mut self) -> String {
+        self.lines.drain(..).map(|line| line + "\n").collect()
+    }
+
+    // All output values out of ASCII range.
+    pub fn values(&self) -> &[i64] {
+        &self.values
+    }
+
+    pub fn transcript(&self) -> &[AsciiEvent] {
+        &self.transcript
+    }
+
+    // Transcript as text, like what's shown in terminal with input echoed, values out of ASCII range are omitted.
+    pub fn log(&self) -> String {
+        let mut log = self
+            .transcript
+            .iter()
+            .filter(|e| !matches!(e, AsciiEvent::Value(_)))
+            .map(|e| format!("{}\n", e))
+            .collect::<String>();
+        log.push_str(&self.input_line);
+        log.push_str(&self.output_line);
+        log
+    }
+}
+
+#[test]
+fn test_ascii_terminal_reset() {
+    let mut term = AsciiTerminal::with_lines(["north"]);
+    for c in "nor".chars() {
+        assert_eq!(term.get(), Some(c as i64));
+    }
+    for value in "Room\nDoor".chars().map(|c| c as i64).chain([1000]) {
+        term.put(value).unwrap();
+    }
+    assert_eq!(term.partial_line(), "Door");
+
+    term.reset();
+    assert!(!term.has_input());
+    assert_eq!(term.partial_line(), "");
+    assert!(term.values().is_empty() && term.transcript().is_empty());
+    assert_eq!(term.log(), "");
+
+    for value in "Hall\n".chars().map(|c| c as i64) {
+        term.put(value).unwrap();
+    }
+    assert_eq!(term.take_text(), "Hall\n");
+}
@@ -0,0 +1,367 @@
+use std::{borrow::Cow, collections::HashMap, fmt::Display, str::FromStr};
+
+use crate::Error;
+
+use super::{inst::MAX_INST_LENGTH, to_code_str};
+
+// Count of cells in one page of sparse memory.
+const PAGE_SIZE: usize = 1024;
+
+// Most cells sparse memory can be copied into one vector, copying more fails instead of allocating without bound.
+pub const MAX_DENSE_LEN: usize = 1 << 24;
+
+// How memory of process is stored, memory beyond image is all zero in every model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryModel {
+    // Vector growing up to the highest address accessed, accessing address reaching cap fails.
+    // Cap is raised to image length when image is longer, no cap means MAX_DENSE_LEN.
+    Dense(Option<usize>),
+    // Fixed size pages allocated when they are written, for programs touching far addresses.
+    Sparse,
+}
+
+impl Default for MemoryModel {
+    fn default() -> Self {
+        MemoryModel::Dense(None)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Memory {
+    Dense {
+        cells: Vec<i64>,
+        cap: Option<usize>,
+    },
+    Sparse {
+        pages: HashMap<usize, Box<[i64]>>,
+        len: usize,
+    },
+}
+
+impl Memory {
+    pub fn new(model: MemoryModel, image: &[i64]) -> Self {
+        match model {
+            MemoryModel::Dense(cap) => Memory::Dense {
+                cells: Vec::from(image),
+                cap: cap.map(|cap| cap.max(image.len())),
+            },
+            MemoryModel::Sparse => {
+                let mut pages = HashMap::new();
+                for (page_ind, chunk) in image.chunks(PAGE_SIZE).enumerate() {
+                    if chunk.iter().any(|&v| v != 0) {
+                        let mut page = vec![0; PAGE_SIZE].into_boxed_slice();
+                        page[..chunk.len()].copy_from_slice(chunk);
+                        pages.insert(page_ind, page);
+                    }
+                }
+
+                Memory::Sparse {
+                    pages,
+                    len: image.len(),
+                }
+            }
+        }
+    }
+
+    pub fn model(&self) -> MemoryModel {
+        match self {
+            Memory::Dense { cap, .. } => MemoryModel::Dense(*cap),
+            Memory::Sparse { .. } => MemoryModel::Sparse,
+        }
+    }
+
+    // Memory in another model with the same content.
+    pub fn convert(&self, model: MemoryModel) -> Result<Self, Error> {
+        if let MemoryModel::Dense(Some(cap)) = model {
+            if self.len() > cap {
+                return Err(Error::MemoryLimitExceeded(self.len() - 1, cap));
+            }
+        }
+
+        Self::from_segments(model, self.len(), self.segments())
+    }
+
+    // Memory of given length holding given segments(start address, values), cells not in segments are zero.
+    // Like image, length of memory raises cap of dense model.
+    pub fn from_segments<'a, I>(model: MemoryModel, len: usize, segments: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (usize, &'a [i64])>,
+    {
+        let mut mem = match model {
+            MemoryModel::Dense(cap) => {
+                if len > MAX_DENSE_LEN {
+                    return Err(Error::MemoryLimitExceeded(len - 1, MAX_DENSE_LEN));
+                }
+
+                Memory::Dense {
+                    cells: vec![0; len],
+                    cap: cap.map(|cap| cap.max(len)),
+                }
+            }
+            MemoryModel::Sparse => Memory::Sparse {
+                pages: HashMap::new(),
+                len,
+            },
+        };
+        for (start, values) in segments {
+            for (ind, &value) in values.iter().enumerate() {
+                if start + ind >= len {
+                    return Err(Error::MemoryLimitExceeded(start + ind, len));
+                }
+                mem.write(start + ind, value)?;
+            }
+        }
+
+        Ok(mem)
+    }
+
+    // One past the highest address accessed(or in image).
+    pub fn len(&self) -> usize {
+        match self {
+            Memory::Dense { cells, .. } => cells.len(),
+            Memory::Sparse { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Value at given address without touching memory.
+    pub fn get(&self, ind: usize) -> i64 {
+        match self {
+            Memory::Dense { cells, .. } => cells.get(ind).copied().unwrap_or(0),
+            Memory::Sparse { pages, .. } => pages
+                .get(&(ind / PAGE_SIZE))
+                .map_or(0, |page| page[ind % PAGE_SIZE]),
+        }
+    }
+
+    // Value at given address, memory grows to cover it.
+    pub fn read(&mut self, ind: usize) -> Result<i64, Error> {
+        self.grow(ind)?;
+        Ok(self.get(ind))
+    }
+
+    // Write value at given address and return the old one, memory grows to cover it.
+    pub fn write(&mut self, ind: usize, value: i64) -> Result<i64, Error> {
+        self.grow(ind)?;
+        match self {
+            Memory::Dense { cells, .. } => Ok(std::mem::replace(&mut cells[ind], value)),
+            Memory::Sparse { pages, .. } => {
+                if value == 0 && !pages.contains_key(&(ind / PAGE_SIZE)) {
+                    return Ok(0);
+                }
+
+                let page = pages
+                    .entry(ind / PAGE_SIZE)
+                    .or_insert_with(|| vec![0; PAGE_SIZE].into_boxed_slice());
+                Ok(std::mem::replace(&mut page[ind % PAGE_SIZE], value))
+            }
+        }
+    }
+
+    fn grow(&mut self, ind: usize) -> Result<(), Error> {
+        match self {
+            Memory::Dense { cells, cap } => {
+                let cap = cap.unwrap_or(MAX_DENSE_LEN);
+                if ind >= cap {
+                    return Err(Error::MemoryLimitExceeded(ind, cap));
+                }
+
+                if ind >= cells.len() {
+                    cells.resize(ind + 1, 0);
+                }
+            }
+            Memory::Sparse { len, .. } => *len = (*len).max(ind + 1),
+        }
+
+        Ok(())
+    }
+
+    // Code starting from given address, which is long enough for decoding one instruction.
+    pub fn code_at(&self, ind: usize) -> Cow<'_, [i64]> {
+        match self {
+            Memory::Dense { cells, .. } => Cow::Borrowed(&cells[ind.min(cells.len())..]),
+            Memory::Sparse { pages, len } => {
+                let end = (ind + MAX_INST_LENGTH).min(*len).max(ind);
+                match pages.get(&(ind / PAGE_SIZE)) {
+                    // Borrow from page if the whole instruction is in it.
+                    Some(page) if (end - 1) / PAGE_SIZE == ind / PAGE_SIZE => {
+                        Cow::Borrowed(&page[(ind % PAGE_SIZE)..=((end - 1) % PAGE_SIZE)])
+                    }
+                    _ => Cow::Owned((ind..end).map(|i| self.get(i)).collect()),
+                }
+            }
+        }
+    }
+
+    // Allocated parts of memory as (start address, values) in address order, the whole vector of dense memory or
+    // every written page of sparse memory.
+    pub fn segments(&self) -> Vec<(usize, &[i64])> {
+        match self {
+            Memory::Dense { cells, .. } => vec![(0, cells.as_slice())],
+            Memory::Sparse { pages, len } => {
+                let mut segments = pages
+                    .iter()
+                    .map(|(&page_ind, page)| {
+                        let start = page_ind * PAGE_SIZE;
+                        (start, &page[..PAGE_SIZE.min(len - start)])
+                    })
+                    .collect::<Vec<_>>();
+                segments.sort_unstable_by_key(|(start, _)| *start);
+                segments
+            }
+        }
+    }
+
+    // (address, value) of every allocated cell in address order, cells not listed are zero.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, i64)> + '_ {
+        self.segments().into_iter().flat_map(|(start, values)| {
+            values
+                .iter()
+                .enumerate()
+                .map(move |(ind, &value)| (start + ind, value))
+        })
+    }
+
+    // Whole content of memory, sparse memory is copied into one vector and fails if it's longer than MAX_DENSE_LEN.
+    pub fn as_slice(&self) -> Result<Cow<'_, [i64]>, Error> {
+        match self {
+            Memory::Dense { cells, .. } => Ok(Cow::Borrowed(cells)),
+            Memory::Sparse { .. } => self.to_vec().map(Cow::Owned),
+        }
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<i64>, Error> {
+        match self {
+            Memory::Dense { cells, .. } => Ok(cells.clone()),
+            Memory::Sparse { len, .. } if *len > MAX_DENSE_LEN => {
+                Err(Error::MemoryLimitExceeded(len - 1, MAX_DENSE_LEN))
+            }
+            Memory::Sparse { .. } => {
+                let mut cells = vec![0; self.len()];
+                for (ind, value) in self.cells() {
+                    cells[ind] = value;
+                }
+                Ok(cells)
+            }
+        }
+    }
+}
+
+// Memories are equal if they have the same length and content, no matter how they are stored.
+impl PartialEq for Memory {
+    fn eq(&self, other: &Self) -> bool {
+        let non_zero = |mem: &Memory| {
+            mem.cells()
+                .filter(|&(_, value)| value != 0)
+                .collect::<Vec<_>>()
+        };
+        self.len() == other.len() && non_zero(self) == non_zero(other)
+    }
+}
+
+// Length and segments of memory, like "len 2049 0:1,2,3 2048:7".
+impl Display for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "len {}", self.len())?;
+        for (start, values) in self.segments() {
+            write!(f, " {}:{}", start, to_code_str(values))?;
+        }
+
+        Ok(())
+    }
+}
+
+// Parsed memory is sparse, and it can be moved into another model by convert.
+impl FromStr for Memory {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidSnapshot(format!("invalid memory({})", s));
+        let mut parts = s.split_whitespace();
+        let len = parts
+            .next()
+            .filter(|p| *p == "len")
+            .and(parts.next())
+            .and_then(|p| p.parse::<usize>().ok())
+            .ok_or_else(invalid)?;
+        let segments = parts
+            .map(|p| {
+                let (start, values) = p.split_once(':').ok_or_else(invalid)?;
+                let start = start.parse::<usize>().map_err(|_| invalid())?;
+                let values = values
+                    .split(',')
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.parse::<i64>().map_err(|_| invalid()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((start, values))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Self::from_segments(
+            MemoryModel::Sparse,
+            len,
+            segments
+                .iter()
+                .map(|(start, values)| (*start, values.as_slice())),
+        )
+    }
+}
+
+#[test]
+fn test_sparse_memory_far_write() {
+    let far = 1 << 40;
+    let mut mem = Memory::new(MemoryModel::Sparse, &[1, 2, 3]);
+    mem.write(far, 7).unwrap();
+    assert_eq!(mem.len(), far + 1);
+    assert_eq!(mem.get(far), 7);
+    assert_eq!(
+        mem.cells().filter(|&(_, v)| v != 0).collect::<Vec<_>>(),
+        vec![(0, 1), (1, 2), (2, 3), (far, 7)]
+    );
+    assert!(
+        matches!(mem.to_vec(), Err(Error::MemoryLimitExceeded(addr, MAX_DENSE_LEN)) if addr == far)
+    );
+    assert!(matches!(
+        mem.convert(MemoryModel::Dense(None)),
+        Err(Error::MemoryLimitExceeded(_, MAX_DENSE_LEN))
+    ));
+    assert!(mem.convert(MemoryModel::Sparse).is_ok_and(|m| m == mem));
+}
+
+#[test]
+fn test_dense_memory_far_write() {
+    let far = 100_000_000_000;
+    let mut mem = Memory::new(MemoryModel::Dense(None), &[1, 2, 3]);
+    assert!(
+        matches!(mem.write(far, 7), Err(Error::MemoryLimitExceeded(addr, MAX_DENSE_LEN)) if addr == far)
+    );
+    assert!(matches!(
+        mem.read(MAX_DENSE_LEN),
+        Err(Error::MemoryLimitExceeded(_, MAX_DENSE_LEN))
+    ));
+    assert_eq!(mem.len(), 3);
+
+    let mut mem = Memory::new(MemoryModel::Dense(Some(8)), &[1, 2, 3]);
+    assert!(matches!(
+        mem.write(8, 7),
+        Err(Error::MemoryLimitExceeded(8, 8))
+    ));
+    assert_eq!(mem.write(7, 7).unwrap(), 0);
+    assert_eq!(mem.len(), 8);
+}
+
+#[test]
+fn test_memory_text_round_trip() {
+    let mut mem = Memory::new(MemoryModel::Sparse, &[1, 0, 3]);
+    mem.write(5000, -4).unwrap();
+    let text = mem.to_string();
+    assert_eq!(text.parse::<Memory>().unwrap(), mem);
+
+    let dense = mem.convert(MemoryModel::Dense(None)).unwrap();
+    assert_eq!(dense, mem);
+    assert_eq!(dense.to_string().parse::<Memory>().unwrap(), mem);
+    assert!("len 2 0:1,2,3".parse::<Memory>().is_err());
+}
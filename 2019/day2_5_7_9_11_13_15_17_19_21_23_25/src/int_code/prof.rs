@@ -0,0 +1,251 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+};
+
+use int_enum::IntEnum;
+
+use super::{
+    asm::{disassemble, mnemonic, Statement},
+    inst::InstOpcodeInd,
+};
+
+// Count of hottest addresses listed in text report.
+const HOT_SPOT_N: usize = 10;
+
+// Execution profile shared by all processes in one computer, it's recorded only when profiling is enabled.
+#[derive(Debug, Default, Clone)]
+pub struct Profiler {
+    step_count: usize,
+    hits: BTreeMap<usize, usize>,
+    opcodes: HashMap<u32, usize>,
+    reads: BTreeMap<usize, usize>,
+    writes: BTreeMap<usize, usize>,
+}
+
+// Profile of one statement in disassembly, counts are summed over all addresses covered by the statement.
+struct StmtProfile {
+    stmt: Statement,
+    hits: usize,
+    reads: usize,
+    writes: usize,
+}
+
+impl Profiler {
+    pub fn step_count(&self) -> usize {
+        self.step_count
+    }
+
+    // How many times the instruction at given address was executed.
+    pub fn hits(&self, addr: usize) -> usize {
+        self.hits.get(&addr).copied().unwrap_or(0)
+    }
+
+    pub fn reads(&self, addr: usize) -> usize {
+        self.reads.get(&addr).copied().unwrap_or(0)
+    }
+
+    pub fn writes(&self, addr: usize) -> usize {
+        self.writes.get(&addr).copied().unwrap_or(0)
+    }
+
+    // Executed count of every operation code, ordered by operation code.
+    pub fn opcode_histogram(&self) -> Vec<(InstOpcodeInd, usize)> {
+        let mut histogram = self
+            .opcodes
+            .iter()
+            .flat_map(|(&ind, &n)| InstOpcodeInd::from_int(ind).ok().map(|ind| (ind, n)))
+            .collect::<Vec<_>>();
+        histogram.sort_by_key(|(ind, _)| ind.int_value());
+        histogram
+    }
+
+    // Executed addresses ordered by hit count, the hottest one first.
+    pub fn hot_spots(&self) -> Vec<(usize, usize)> {
+        let mut spots = self
+            .hits
+            .iter()
+            .map(|(&addr, &n)| (addr, n))
+            .collect::<Vec<_>>();
+        spots.sort_by(|(addr0, n0), (addr1, n1)| n1.cmp(n0).then(addr0.cmp(addr1)));
+        spots
+    }
+
+    pub(super) fn on_exec(&mut self, addr: usize, opcode_ind: u32) {
+        self.step_count += 1;
+        *self.hits.entry(addr).or_insert(0) += 1;
+        *self.opcodes.entry(opcode_ind).or_insert(0) += 1;
+    }
+
+    pub(super) fn on_read(&mut self, addr: usize) {
+        *self.reads.entry(addr).or_insert(0) += 1;
+    }
+
+    pub(super) fn on_write(&mut self, addr: usize) {
+        *self.writes.entry(addr).or_insert(0) += 1;
+    }
+
+    fn sum_in(map: &BTreeMap<usize, usize>, addr: usize, length: usize) -> usize {
+        map.range(addr..(addr + length)).map(|(_, &n)| n).sum()
+    }
+
+    // Profile every statement in disassembly of given image, and memory beyond image as data.
+    fn stmt_profiles(&self, image: &[i64]) -> Vec<StmtProfile> {
+        let mut stmts = disassemble(image).stmts().to_vec();
+        let beyond_addrs = self
+            .reads
+            .keys()
+            .chain(self.writes.keys())
+            .chain(self.hits.keys())
+            .copied()
+            .filter(|&addr| addr >= image.len())
+            .collect::<BTreeSet<_>>();
+        stmts.extend(beyond_addrs.into_iter().map(|addr| Statement::Data {
+            addr,
+            values: vec![0],
+        }));
+
+        stmts
+            .into_iter()
+            .map(|stmt| {
+                let (addr, length) = (stmt.addr(), stmt.length());
+                StmtProfile {
+                    hits: Self::sum_in(&self.hits, addr, length),
+                    reads: Self::sum_in(&self.reads, addr, length),
+                    writes: Self::sum_in(&self.writes, addr, length),
+                    stmt,
+                }
+            })
+            .collect()
+    }
+
+    // Count of (executed, all) instruction statements in disassembly of given image.
+    pub fn coverage(&self, image: &[i64]) -> (usize, usize) {
+        let listing = disassemble(image);
+        let insts = listing
+            .stmts()
+            .iter()
+            .filter(|stmt| matches!(stmt, Statement::Inst { .. }))
+            .collect::<Vec<_>>();
+        let executed_n = insts
+            .iter()
+            .filter(|stmt| self.hits(stmt.addr()) > 0)
+            .count();
+
+        (executed_n, insts.len())
+    }
+
+    // Human readable report, with summary, opcode histogram, hot spots and disassembly of given image annotated by
+    // hit, read and write counts.
+    pub fn text_report(&self, image: &[i64]) -> String {
+        let mut report = String::new();
+        let (executed_n, inst_n) = self.coverage(image);
+        writeln!(report, "Steps: {}", self.step_count).unwrap();
+        writeln!(
+            report,
+            "Coverage: {} of {} instruction(s) executed",
+            executed_n, inst_n
+        )
+        .unwrap();
+
+        writeln!(report, "Opcodes:").unwrap();
+        for (ind, n) in self.opcode_histogram() {
+            writeln!(
+                report,
+                "  {:<4}{:>12} {:>6.2}%",
+                mnemonic(ind),
+                n,
+                n as f64 * 100.0 / self.step_count.max(1) as f64
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "Hot spots:").unwrap();
+        for (addr, n) in self.hot_spots().into_iter().take(HOT_SPOT_N) {
+            writeln!(report, "  {:>5}: {}", addr, n).unwrap();
+        }
+
+        writeln!(report, "Listing:").unwrap();
+        writeln!(
+            report,
+            "{:>10} {:>8} {:>8} | statement",
+            "hits", "reads", "writes"
+        )
+        .unwrap();
+        let count_str = |n: usize| {
+            if n == 0 {
+                String::from("-")
+            } else {
+                n.to_string()
+            }
+        };
+        for prof in self.stmt_profiles(image) {
+            writeln!(
+                report,
+                "{:>10} {:>8} {:>8} | {}",
+                count_str(prof.hits),
+                count_str(prof.reads),
+                count_str(prof.writes),
+                prof.stmt
+            )
+            .unwrap();
+        }
+
+        report
+    }
+
+    // Same content as text report in JSON.
+    pub fn json_report(&self, image: &[i64]) -> String {
+        let (executed_n, inst_n) = self.coverage(image);
+        let opcodes = self
+            .opcode_histogram()
+            .into_iter()
+            .map(|(ind, n)| format!("\"{}\":{}", mnemonic(ind), n))
+            .collect::<Vec<_>>();
+        let hot_spots = self
+            .hot_spots()
+            .into_iter()
+            .map(|(addr, n)| format!("{{\"addr\":{},\"hits\":{}}}", addr, n))
+            .collect::<Vec<_>>();
+        let stmts = self
+            .stmt_profiles(image)
+            .into_iter()
+            .map(|prof| {
+                let text = prof.stmt.to_string();
+                format!(
+                    "{{\"addr\":{},\"length\":{},\"text\":\"{}\",\"hits\":{},\"reads\":{},\"writes\":{}}}",
+                    prof.stmt.addr(),
+                    prof.stmt.length(),
+                    escape_json(text.split_once(": ").map_or(text.as_str(), |(_, t)| t.trim())),
+                    prof.hits,
+                    prof.reads,
+                    prof.writes
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"steps\":{},\"coverage\":{{\"executed\":{},\"total\":{}}},\"opcodes\":{{{}}},\"hot_spots\":[{}],\"statements\":[{}]}}",
+            self.step_count,
+            executed_n,
+            inst_n,
+            opcodes.join(","),
+            hot_spots.join(","),
+            stmts.join(",")
+        )
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
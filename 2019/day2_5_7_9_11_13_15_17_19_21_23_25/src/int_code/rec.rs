@@ -0,0 +1,469 @@
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::Error;
+
+use super::io::{InputPort, OutputPort};
+
+// One value passed through port, with the step of process in which it's passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IOEvent {
+    Input { step: usize, value: i64 },
+    Output { step: usize, value: i64 },
+}
+
+impl Display for IOEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IOEvent::Input { step, value } => write!(f, "in {} {}", step, value),
+            IOEvent::Output { step, value } => write!(f, "out {} {}", step, value),
+        }
+    }
+}
+
+impl FromStr for IOEvent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        let invalid = || Error::InvalidTranscript(format!("invalid event({})", s));
+        if fields.len() != 3 {
+            return Err(invalid());
+        }
+
+        let step = fields[1].parse::<usize>().map_err(|_| invalid())?;
+        let value = fields[2].parse::<i64>().map_err(|_| invalid())?;
+        match fields[0] {
+            "in" => Ok(IOEvent::Input { step, value }),
+            "out" => Ok(IOEvent::Output { step, value }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl IOEvent {
+    pub fn step(&self) -> usize {
+        match self {
+            IOEvent::Input { step, .. } | IOEvent::Output { step, .. } => *step,
+        }
+    }
+
+    pub fn value(&self) -> i64 {
+        match self {
+            IOEvent::Input { value, .. } | IOEvent::Output { value, .. } => *value,
+        }
+    }
+}
+
+// All values passed through ports of a process in order, one event per line, lines starting with '#' are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    events: Vec<IOEvent>,
+}
+
+impl Display for Transcript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Transcript {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.parse::<IOEvent>())
+            .collect::<Result<Vec<_>, _>>()
+            .map(|events| Self { events })
+    }
+}
+
+impl Transcript {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        fs::read_to_string(path)
+            .map_err(Error::IOError)
+            .and_then(|s| s.parse())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        File::create(path)
+            .and_then(|mut f| write!(f, "{}", self))
+            .map_err(Error::IOError)
+    }
+
+    pub fn events(&self) -> &[IOEvent] {
+        &self.events
+    }
+
+    pub fn inputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().flat_map(|e| match e {
+            IOEvent::Input { value, .. } => Some(*value),
+            _ => None,
+        })
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().flat_map(|e| match e {
+            IOEvent::Output { value, .. } => Some(*value),
+            _ => None,
+        })
+    }
+}
+
+// Port wrapper which passes everything to wrapped port, and records every value passed into transcript.
+pub struct Recorder<P> {
+    port: P,
+    transcript: Transcript,
+}
+
+impl<P> Recorder<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port,
+            transcript: Transcript::default(),
+        }
+    }
+
+    pub fn port(&self) -> &P {
+        &self.port
+    }
+
+    pub fn port_mut(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+}
+
+impl<P: InputPort> InputPort for Recorder<P> {
+    fn get(&mut self) -> Option<i64> {
+        self.get_at(0)
+    }
+
+    fn get_at(&mut self, step: usize) -> Option<i64> {
+        let res = self.port.get_at(step);
+        if let Some(value) = res {
+            self.transcript.events.push(IOEvent::Input { step, value });
+        }
+
+        res
+    }
+
+    fn reg_proc(&mut self, proc_id: usize) {
+        self.port.reg_proc(proc_id)
+    }
+
+    fn pending_input(&self) -> Vec<i64> {
+        self.port.pending_input()
+    }
+}
+
+impl<P: OutputPort> OutputPort for Recorder<P> {
+    fn put(&mut self, value: i64) -> Result<(), Error> {
+        self.put_at(0, value)
+    }
+
+    fn put_at(&mut self, step: usize, value: i64) -> Result<(), Error> {
+        self.port.put_at(step, value)?;
+        self.transcript.events.push(IOEvent::Output { step, value });
+        Ok(())
+    }
+
+    fn wait_proc_id(&self) -> Option<usize> {
+        self.port.wait_proc_id()
+    }
+
+    fn wait_proc_ids(&self) -> Vec<usize> {
+        self.port.wait_proc_ids()
+    }
+
+    fn pending_output(&self) -> Vec<i64> {
+        self.port.pending_output()
+    }
+}
+
+// Where replayed process went differently from transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    ind: usize,
+    step: usize,
+    output: Option<i64>, // None if process required input.
+    expected: Option<IOEvent>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "event #{}: process ", self.ind)?;
+        match self.output {
+            Some(value) => write!(f, "output {}", value)?,
+            None => write!(f, "required input")?,
+        }
+        write!(f, " at step {}, but ", self.step)?;
+        match self.expected {
+            Some(event) => write!(f, "({}) is expected", event),
+            None => write!(f, "transcript ended"),
+        }
+    }
+}
+
+impl Divergence {
+    pub fn ind(&self) -> usize {
+        self.ind
+    }
+
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    pub fn output(&self) -> Option<i64> {
+        self.output
+    }
+
+    pub fn expected(&self) -> Option<IOEvent> {
+        self.expected
+    }
+}
+
+// Port which feeds inputs from transcript back to process, and verifies its outputs against transcript.
+// After the first divergence, process gets no more input and fails on output.
+pub struct Replayer {
+    transcript: Transcript,
+    next_ind: usize,
+    divergence: Option<Divergence>,
+}
+
+impl Replayer {
+    pub fn new(transcript: Transcript) -> Self {
+        Self {
+            transcript,
+            next_ind: 0,
+            divergence: None,
+        }
+    }
+
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_ind >= self.transcript.events.len()
+    }
+
+    // Fail with the first divergence, or if process stopped before the end of transcript.
+    pub fn verify(&self) -> Result<(), Error> {
+        if let Some(divergence) = &self.divergence {
+            Err(Error::ReplayDivergence(divergence.to_string()))
+        } else if !self.is_finished() {
+            Err(Error::ReplayDivergence(format!(
+                "process stopped at event #{}, but ({}) is expected",
+                self.next_ind, self.transcript.events[self.next_ind]
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn diverge(&mut self, step: usize, output: Option<i64>) -> Divergence {
+        let divergence = Divergence {
+            ind: self.next_ind,
+            step,
+            output,
+            expected: self.transcript.events.get(self.next_ind).copied(),
+        };
+        self.divergence = Some(divergence.clone());
+
+        divergence
+    }
+}
+
+impl InputPort for Replayer {
+    fn get(&mut self) -> Option<i64> {
+        self.transcript
+            .events
+            .get(self.next_ind)
+            .map(|e| e.step())
+            .and_then(|step| self.get_at(step))
+    }
+
+    fn get_at(&mut self, step: usize) -> Option<i64> {
+        if self.divergence.is_some() {
+            return None;
+        }
+
+        match self.transcript.events.get(self.next_ind).copied() {
+            Some(IOEvent::Input { step: s, value }) if s == step => {
+                self.next_ind += 1;
+                Some(value)
+            }
+            // Process blocks for input after transcript ends, just like what it did while recording.
+            None => None,
+            _ => {
+                self.diverge(step, None);
+                None
+            }
+        }
+    }
+
+    fn reg_proc(&mut self, _proc_id: usize) {}
+
+    fn pending_input(&self) -> Vec<i64> {
+        self.transcript.events[self.next_ind.min(self.transcript.events.len())..]
+            .iter()
+            .flat_map(|e| match e {
+                IOEvent::Input { value, .. } => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl OutputPort for Replayer {
+    fn put(&mut self, value: i64) -> Result<(), Error> {
+        let step = self
+            .transcript
+            .events
+            .get(self.next_ind)
+            .map_or(0, |e| e.step());
+        self.put_at(step, value)
+    }
+
+    fn put_at(&mut self, step: usize, value: i64) -> Result<(), Error> {
+        if let Some(divergence) = &self.divergence {
+            return Err(Error::ReplayDivergence(divergence.to_string()));
+        }
+
+        if self.transcript.events.get(self.next_ind) == Some(&IOEvent::Output { step, value }) {
+            self.next_ind += 1;
+            Ok(())
+        } else {
+            Err(Error::ReplayDivergence(
+                self.diverge(step, Some(value)).to_string(),
+            ))
+        }
+    }
+
+    fn wait_proc_id(&self) -> Option<usize> {
+        None
+    }
+}
+
+// Reads two values, outputs their sum and product.
+#[cfg(test)]
+const SUM_PRODUCT: [i64; 20] = [
+    3, 17, 3, 18, 1, 17, 18, 19, 4, 19, 2, 17, 18, 19, 4, 19, 99, 0, 0, 0,
+];
+
+#[cfg(test)]
+fn record(image: &[i64], inputs: &[i64]) -> Transcript {
+    use super::{
+        com::SeqIntCodeComputer,
+        io::{Channel, PortPair, SeqIODevice},
+    };
+
+    let port = PortPair::new(Channel::new(inputs), Channel::new(&[]));
+    let io_dev = SeqIODevice::new(Recorder::new(port));
+    let mut computer = SeqIntCodeComputer::new(false);
+    computer
+        .execute_with_io(image, io_dev.input_device(), io_dev.output_device())
+        .unwrap();
+    io_dev.check(|r| r.transcript().clone())
+}
+
+// Whether process ran without error, and the result of verifying replay.
+#[cfg(test)]
+fn replay(image: &[i64], transcript: Transcript) -> (bool, Result<(), Error>, Option<Divergence>) {
+    use super::{com::SeqIntCodeComputer, io::SeqIODevice};
+
+    let io_dev = SeqIODevice::new(Replayer::new(transcript));
+    let mut computer = SeqIntCodeComputer::new(false);
+    let res = computer.execute_with_io(image, io_dev.input_device(), io_dev.output_device());
+    let (verified, divergence) = io_dev.check(|r| (r.verify(), r.divergence().cloned()));
+    (res.is_ok(), verified, divergence)
+}
+
+#[test]
+fn test_record_and_replay() {
+    let transcript = record(&SUM_PRODUCT, &[6, 7]);
+    assert_eq!(transcript.inputs().collect::<Vec<_>>(), vec![6, 7]);
+    assert_eq!(transcript.outputs().collect::<Vec<_>>(), vec![13, 42]);
+    assert_eq!(
+        transcript
+            .events()
+            .iter()
+            .map(|e| e.step())
+            .collect::<Vec<_>>(),
+        vec![0, 1, 3, 5]
+    );
+
+    let text = format!("# sum and product\n{}", transcript);
+    let parsed = text.parse::<Transcript>().unwrap();
+    assert_eq!(parsed, transcript);
+    let (is_ok, verified, divergence) = replay(&SUM_PRODUCT, parsed);
+    assert!(is_ok);
+    assert!(verified.is_ok());
+    assert_eq!(divergence, None);
+
+    assert!("in 0".parse::<Transcript>().is_err());
+    assert!("put 0 1".parse::<Transcript>().is_err());
+}
+
+#[test]
+fn test_replay_tampered_output() {
+    let transcript = record(&SUM_PRODUCT, &[6, 7]);
+    let tampered = transcript
+        .to_string()
+        .replace("out 5 42", "out 5 41")
+        .parse::<Transcript>()
+        .unwrap();
+
+    let (is_ok, verified, divergence) = replay(&SUM_PRODUCT, tampered);
+    assert!(!is_ok);
+    assert!(matches!(verified, Err(Error::ReplayDivergence(_))));
+    let divergence = divergence.unwrap();
+    assert_eq!((divergence.ind(), divergence.step()), (3, 5));
+    assert_eq!(divergence.output(), Some(42));
+    assert_eq!(
+        divergence.expected(),
+        Some(IOEvent::Output { step: 5, value: 41 })
+    );
+}
+
+#[test]
+fn test_replay_truncated() {
+    let transcript = record(&SUM_PRODUCT, &[6, 7]);
+
+    // Transcript ends before the last output.
+    let mut truncated = transcript.clone();
+    truncated.events.pop();
+    let (is_ok, _, divergence) = replay(&SUM_PRODUCT, truncated);
+    assert!(!is_ok);
+    let divergence = divergence.unwrap();
+    assert_eq!((divergence.ind(), divergence.output()), (3, Some(42)));
+    assert_eq!(divergence.expected(), None);
+
+    // Process halts before the last output.
+    let mut image = SUM_PRODUCT;
+    image[10] = 99;
+    let (is_ok, verified, divergence) = replay(&image, transcript);
+    assert!(is_ok);
+    assert_eq!(divergence, None);
+    match verified {
+        Err(Error::ReplayDivergence(s)) => {
+            assert_eq!(s, "process stopped at event #3, but (out 5 42) is expected")
+        }
+        other => panic!("unexpected verification({:?})", other),
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt::Display;
+
+use super::rng::SplitMix64;
+
+// How computer shares processor among processes executed together.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SchedulePolicy {
+    // Each ready process runs until it's blocked or halted, in turn.
+    #[default]
+    RunToBlock,
+    // Each ready process runs at most given steps(quantum), in turn.
+    RoundRobin(usize),
+    // Ready process with the highest priority runs given steps, processes with the same priority run in turn.
+    // Priorities are given in order of executed processes, missing ones are 0.
+    Priority(Vec<u32>, usize),
+    // Ready process picked by random generator with given seed runs given steps.
+    Random {
+        seed: u64,
+        quantum: usize,
+    },
+    // Each ready process runs until it outputs a value, or has run given steps, in turn.
+    UntilOutput(usize),
+}
+
+impl Display for SchedulePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchedulePolicy::RunToBlock => write!(f, "run to block"),
+            SchedulePolicy::RoundRobin(quantum) => write!(f, "round robin({})", quantum),
+            SchedulePolicy::Priority(priorities, quantum) => {
+                write!(f, "priority({:?}, {})", priorities, quantum)
+            }
+            SchedulePolicy::Random { seed, quantum } => {
+                write!(f, "random(seed {}, {})", seed, quantum)
+            }
+            SchedulePolicy::UntilOutput(quantum) => write!(f, "until output({})", quantum),
+        }
+    }
+}
+
+// Picks the next process by policy, and records how processor is shared.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    policy: SchedulePolicy,
+    last_ind: Option<usize>,
+    rng: SplitMix64,
+    stats: ScheduleStats,
+}
+
+impl Scheduler {
+    pub fn new(policy: SchedulePolicy) -> Self {
+        let seed = match policy {
+            SchedulePolicy::Random { seed, .. } => seed,
+            _ => 0,
+        };
+        Self {
+            policy,
+            last_ind: None,
+            rng: SplitMix64::new(seed),
+            stats: ScheduleStats::default(),
+        }
+    }
+
+    // Scheduler whose first turn goes to process at given index, if it's ready.
+    pub fn starting_at(policy: SchedulePolicy, start_ind: usize) -> Self {
+        let mut scheduler = Self::new(policy);
+        scheduler.last_ind = start_ind.checked_sub(1);
+        scheduler
+    }
+
+    pub fn policy(&self) -> &SchedulePolicy {
+        &self.policy
+    }
+
+    // Max steps of one time slice, None for running until blocked.
+    pub fn quantum(&self) -> Option<usize> {
+        match self.policy {
+            SchedulePolicy::RunToBlock => None,
+            SchedulePolicy::UntilOutput(quantum)
+            | SchedulePolicy::RoundRobin(quantum)
+            | SchedulePolicy::Priority(_, quantum)
+            | SchedulePolicy::Random { quantum, .. } => Some(quantum),
+        }
+    }
+
+    pub fn yields_on_output(&self) -> bool {
+        matches!(self.policy, SchedulePolicy::UntilOutput(_))
+    }
+
+    // Index of process running in the next time slice, among processes whose ready states are given.
+    pub fn pick(&mut self, ready: &[bool]) -> Option<usize> {
+        // Processes never scheduled are counted in stats too.
+        self.stats.resize(ready.len());
+        let ind = match &self.policy {
+            SchedulePolicy::Priority(priorities, _) => {
+                let priority = |ind: usize| priorities.get(ind).copied().unwrap_or(0);
+                let max_priority = (0..ready.len())
+                    .filter(|&ind| ready[ind])
+                    .map(priority)
+                    .max()?;
+                self.next_in_turn(ready, |ind| priority(ind) == max_priority)
+            }
+            SchedulePolicy::Random { .. } => {
+                let ready_inds = (0..ready.len())
+                    .filter(|&ind| ready[ind])
+                    .collect::<Vec<_>>();
+                if ready_inds.is_empty() {
+                    None
+                } else {
+                    let rand = self.rng.next_u64();
+                    Some(ready_inds[(rand % ready_inds.len() as u64) as usize])
+                }
+            }
+            _ => self.next_in_turn(ready, |_| true),
+        }?;
+        self.last_ind = Some(ind);
+
+        Some(ind)
+    }
+
+    // The first ready and accepted process after the last one, in cyclic order.
+    fn next_in_turn<F: Fn(usize) -> bool>(&self, ready: &[bool], accept: F) -> Option<usize> {
+        let n = ready.len();
+        let start = self.last_ind.map_or(0, |ind| ind + 1);
+        (0..n)
+            .map(|i| (start + i) % n)
+            .find(|&ind| ready[ind] && accept(ind))
+    }
+
+    // Record one time slice of process at given index, in which it runs given steps.
+    pub fn record(&mut self, ind: usize, step_n: usize) {
+        self.stats.record(ind, step_n);
+    }
+
+    pub fn stats(&self) -> &ScheduleStats {
+        &self.stats
+    }
+
+    pub fn into_stats(self) -> ScheduleStats {
+        self.stats
+    }
+}
+
+// Time slices and steps each process gets, indexed in order of executed processes.
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleStats {
+    slices: Vec<usize>,
+    steps: Vec<usize>,
+}
+
+impl Display for ScheduleStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} time slice(s), {} step(s), fairness {:.3}",
+            self.total_slices(),
+            self.total_steps(),
+            self.fairness()
+        )?;
+        for (ind, (slice_n, step_n)) in self.slices.iter().zip(self.steps.iter()).enumerate() {
+            writeln!(
+                f,
+                "  #{:<4} {:>8} slice(s) {:>10} step(s)",
+                ind, slice_n, step_n
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ScheduleStats {
+    fn resize(&mut self, proc_n: usize) {
+        if self.slices.len() < proc_n {
+            self.slices.resize(proc_n, 0);
+            self.steps.resize(proc_n, 0);
+        }
+    }
+
+    fn record(&mut self, ind: usize, step_n: usize) {
+        self.resize(ind + 1);
+        self.slices[ind] += 1;
+        self.steps[ind] += step_n;
+    }
+
+    pub fn slices(&self, ind: usize) -> usize {
+        self.slices.get(ind).copied().unwrap_or(0)
+    }
+
+    pub fn steps(&self, ind: usize) -> usize {
+        self.steps.get(ind).copied().unwrap_or(0)
+    }
+
+    pub fn total_slices(&self) -> usize {
+        self.slices.iter().sum()
+    }
+
+    pub fn total_steps(&self) -> usize {
+        self.steps.iter().sum()
+    }
+
+    // Jain's fairness index of steps among processes, 1 when every process runs the same steps.
+    pub fn fairness(&self) -> f64 {
+        let sum = self.steps.iter().map(|&s| s as f64).sum::<f64>();
+        let square_sum = self.steps.iter().map(|&s| (s as f64).powi(2)).sum::<f64>();
+        if square_sum == 0.0 {
+            1.0
+        } else {
+            sum * sum / (self.steps.len() as f64 * square_sum)
+        }
+    }
+}
+
+#[cfg(test)]
+fn picks(scheduler: &mut Scheduler, ready: &[bool], n: usize) -> Vec<Option<usize>> {
+    (0..n).map(|_| scheduler.pick(ready)).collect()
+}
+
+#[test]
+fn test_round_robin_pick() {
+    let mut scheduler = Scheduler::starting_at(SchedulePolicy::RoundRobin(10), 2);
+    assert_eq!(scheduler.quantum(), Some(10));
+    assert_eq!(
+        picks(&mut scheduler, &[true; 4], 5),
+        vec![Some(2), Some(3), Some(0), Some(1), Some(2)]
+    );
+    assert_eq!(
+        picks(&mut scheduler, &[true, false, true, false], 3),
+        vec![Some(0), Some(2), Some(0)]
+    );
+    assert_eq!(scheduler.pick(&[false; 4]), None);
+
+    // Process at start index isn't ready, so the next ready one goes first.
+    let mut scheduler = Scheduler::starting_at(SchedulePolicy::RunToBlock, 1);
+    assert_eq!(scheduler.quantum(), None);
+    assert_eq!(
+        picks(&mut scheduler, &[true, false, true], 3),
+        vec![Some(2), Some(0), Some(2)]
+    );
+}
+
+#[test]
+fn test_priority_pick() {
+    let mut scheduler = Scheduler::new(SchedulePolicy::Priority(vec![1, 3, 3, 2], 5));
+    assert_eq!(
+        picks(&mut scheduler, &[true; 5], 4),
+        vec![Some(1), Some(2), Some(1), Some(2)]
+    );
+    assert_eq!(
+        picks(&mut scheduler, &[true, false, false, true, true], 2),
+        vec![Some(3), Some(3)]
+    );
+    // Process 4 has no priority given, so it's 0.
+    assert_eq!(
+        picks(&mut scheduler, &[true, false, false, false, true], 2),
+        vec![Some(0), Some(0)]
+    );
+    assert_eq!(
+        picks(&mut scheduler, &[false, false, false, false, true], 1),
+        vec![Some(4)]
+    );
+    // Rotation within ties goes on after the last pick.
+    assert_eq!(
+        picks(&mut scheduler, &[true; 5], 3),
+        vec![Some(1), Some(2), Some(1)]
+    );
+    assert_eq!(scheduler.pick(&[false; 5]), None);
+}
+
+#[test]
+fn test_random_pick() {
+    let ready = [true, false, true, true, false, true];
+    let policy = |seed| SchedulePolicy::Random { seed, quantum: 7 };
+    let sequence = picks(&mut Scheduler::new(policy(42)), &ready, 100);
+    assert_eq!(
+        picks(&mut Scheduler::new(policy(42)), &ready, 100),
+        sequence
+    );
+    assert_ne!(
+        picks(&mut Scheduler::new(policy(43)), &ready, 100),
+        sequence
+    );
+    for ind in sequence {
+        assert!(ready[ind.unwrap()]);
+    }
+    for ind in [0, 2, 3, 5] {
+        assert!(picks(&mut Scheduler::new(policy(42)), &ready, 100).contains(&Some(ind)));
+    }
+    assert_eq!(Scheduler::new(policy(42)).pick(&[false; 3]), None);
+}
+
+#[test]
+fn test_schedule_fairness() {
+    assert_eq!(ScheduleStats::default().fairness(), 1.0);
+
+    let mut scheduler = Scheduler::new(SchedulePolicy::RoundRobin(10));
+    for _ in 0..4 {
+        let ind = scheduler.pick(&[true; 4]).unwrap();
+        scheduler.record(ind, 10);
+    }
+    assert_eq!(scheduler.stats().fairness(), 1.0);
+
+    // Steps 30 and 10: (30 + 10)^2 / (2 * (30^2 + 10^2)) = 0.8
+    let mut stats = ScheduleStats::default();
+    stats.record(0, 20);
+    stats.record(1, 10);
+    stats.record(0, 10);
+    assert_eq!(
+        (stats.slices(0), stats.steps(0), stats.total_slices()),
+        (2, 30, 3)
+    );
+    assert!((stats.fairness() - 0.8).abs() < 1e-9);
+
+    // Processes never scheduled count as running no steps.
+    let mut scheduler = Scheduler::new(SchedulePolicy::RunToBlock);
+    scheduler.pick(&[true; 4]);
+    scheduler.record(0, 100);
+    assert!((scheduler.stats().fairness() - 0.25).abs() < 1e-9);
+}
@@ -0,0 +1,335 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    int_code::{
+        com::{ProcessState, ProcsExecutionResult, SeqIntCodeComputer},
+        io::{Channel, InputPort, OutputPort, SeqIODevice, SeqInputDevice, SeqOutputDevice},
+    },
+    Error,
+};
+
+// How output of a node is delivered to its successors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Routing {
+    // Every output value is sent to all successors.
+    Broadcast,
+    // Outputs are grouped into packets of given length, the first value of packet is index of target node, and the
+    // rest values are sent to it. Packet to a node which isn't a successor is kept as undelivered.
+    Packet(usize),
+}
+
+// Everything output by nodes so far, it's shared by output ports of nodes while running.
+#[derive(Debug, Default)]
+pub struct TopologyOutput {
+    outputs: Vec<Vec<i64>>,
+    undelivered: Vec<(usize, i64, Vec<i64>)>, // (source node, target, values of packet)
+}
+
+impl TopologyOutput {
+    pub fn output(&self, node: usize) -> &[i64] {
+        &self.outputs[node]
+    }
+
+    pub fn undelivered(&self) -> &[(usize, i64, Vec<i64>)] {
+        &self.undelivered
+    }
+}
+
+type TopologyOutputRef = Rc<RefCell<TopologyOutput>>;
+
+pub struct TopologyResult {
+    output: TopologyOutput,
+    states: Vec<Option<ProcessState>>,
+    step_counts: Vec<usize>,
+}
+
+impl TopologyResult {
+    pub fn node_n(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn output(&self, node: usize) -> &[i64] {
+        self.output.output(node)
+    }
+
+    pub fn undelivered(&self) -> &[(usize, i64, Vec<i64>)] {
+        self.output.undelivered()
+    }
+
+    pub fn state(&self, node: usize) -> Option<ProcessState> {
+        self.states[node]
+    }
+
+    pub fn step_count(&self, node: usize) -> usize {
+        self.step_counts[node]
+    }
+
+    pub fn is_all_halted(&self) -> bool {
+        self.states.iter().all(|&s| s == Some(ProcessState::Halt))
+    }
+}
+
+// Input port of node, reads from its inbox and gets idle value(if any) instead of blocking when inbox is empty.
+struct NodeInput {
+    inbox: SeqIODevice<Channel>,
+    idle_input: Option<i64>,
+}
+
+impl InputPort for NodeInput {
+    fn get(&mut self) -> Option<i64> {
+        self.inbox.tweak(|c| c.get()).or(self.idle_input)
+    }
+
+    fn reg_proc(&mut self, proc_id: usize) {
+        self.inbox.tweak(|c| c.reg_proc(proc_id))
+    }
+
+    fn pending_input(&self) -> Vec<i64> {
+        self.inbox.check(|c| c.pending_input())
+    }
+}
+
+// Output port of node, logs every output and delivers it into inboxes of successors.
+struct NodeOutput {
+    node: usize,
+    inboxes: Rc<Vec<SeqIODevice<Channel>>>,
+    succs: Vec<usize>,
+    routing: Routing,
+    packet: Vec<i64>,
+    last_targets: Vec<usize>,
+    output: TopologyOutputRef,
+}
+
+impl OutputPort for NodeOutput {
+    fn put(&mut self, value: i64) -> Result<(), Error> {
+        self.output.borrow_mut().outputs[self.node].push(value);
+        self.last_targets.clear();
+        match self.routing {
+            Routing::Broadcast => {
+                for &succ in &self.succs {
+                    self.inboxes[succ].tweak(|c| c.put(value))?;
+                }
+                self.last_targets.extend(&self.succs);
+            }
+            Routing::Packet(len) => {
+                self.packet.push(value);
+                if self.packet.len() >= len {
+                    let target = self.packet[0];
+                    let values = self.packet.split_off(1);
+                    self.packet.clear();
+                    match usize::try_from(target)
+                        .ok()
+                        .filter(|ind| self.succs.contains(ind))
+                    {
+                        Some(ind) => {
+                            self.inboxes[ind]
+                                .tweak(|c| values.iter().try_for_each(|&value| c.put(value)))?;
+                            self.last_targets.push(ind);
+                        }
+                        None => self
+                            .output
+                            .borrow_mut()
+                            .undelivered
+                            .push((self.node, target, values)),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn wait_proc_id(&self) -> Option<usize> {
+        None
+    }
+
+    fn wait_proc_ids(&self) -> Vec<usize> {
+        self.last_targets
+            .iter()
+            .flat_map(|&ind| self.inboxes[ind].check(|c| c.wait_proc_id()))
+            .collect()
+    }
+}
+
+// Description of processes running the same image, connected by channels along directed edges.
+#[derive(Debug, Clone)]
+pub struct Topology {
+    node_n: usize,
+    edges: Vec<(usize, usize)>,
+    init_inputs: Vec<(usize, Vec<i64>)>, // (node, values)
+    routing: Routing,
+    idle_input: Option<i64>,
+}
+
+impl Topology {
+    pub fn graph(node_n: usize, edges: &[(usize, usize)]) -> Self {
+        Self {
+            node_n,
+            edges: Vec::from(edges),
+            init_inputs: Vec::new(),
+            routing: Routing::Broadcast,
+            idle_input: None,
+        }
+    }
+
+    // Node i sends to node i + 1, and the last node sends to nowhere.
+    pub fn pipeline(node_n: usize) -> Self {
+        let edges = (1..node_n).map(|i| (i - 1, i)).collect::<Vec<_>>();
+        Self::graph(node_n, &edges)
+    }
+
+    // Like pipeline, and the last node sends back to the first one.
+    pub fn ring(node_n: usize) -> Self {
+        let edges = (0..node_n)
+            .map(|i| (i, (i + 1) % node_n))
+            .collect::<Vec<_>>();
+        Self::graph(node_n, &edges)
+    }
+
+    // Node 0 is the center, it sends to and receives from every other node.
+    pub fn star(node_n: usize) -> Self {
+        let edges = (1..node_n)
+            .flat_map(|i| [(0, i), (i, 0)])
+            .collect::<Vec<_>>();
+        Self::graph(node_n, &edges)
+    }
+
+    // Every node sends to every node(including itself).
+    pub fn mesh(node_n: usize) -> Self {
+        let edges = (0..node_n)
+            .flat_map(|i| (0..node_n).map(move |j| (i, j)))
+            .collect::<Vec<_>>();
+        Self::graph(node_n, &edges)
+    }
+
+    pub fn node_n(&self) -> usize {
+        self.node_n
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    // Append values into inbox of given node before running, invalid node is reported when running.
+    pub fn with_input(mut self, node: usize, values: &[i64]) -> Self {
+        self.init_inputs.push((node, Vec::from(values)));
+        self
+    }
+
+    pub fn with_routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    // Nodes get given value instead of blocking when their inboxes are empty, so they never block.
+    pub fn with_idle_input(mut self, value: i64) -> Self {
+        self.idle_input = Some(value);
+        self
+    }
+
+    // Run all nodes until all of them are halted or blocked.
+    pub fn run(
+        &self,
+        computer: &mut SeqIntCodeComputer,
+        image: &[i64],
+    ) -> Result<TopologyResult, Error> {
+        let (proc_ids, output) = self.build(computer, image)?;
+        let res = computer.exe_procs(&proc_ids, 0)?;
+        Ok(Self::collect(&proc_ids, &res, output))
+    }
+
+    // Run all nodes preemptively with given steps per turn, until all of them are halted or blocked, or given
+    // condition on outputs is satisfied.
+    pub fn run_until<F: Fn(&TopologyOutput) -> bool>(
+        &self,
+        computer: &mut SeqIntCodeComputer,
+        image: &[i64],
+        reserved_step_count: usize,
+        stop_cond: F,
+    ) -> Result<TopologyResult, Error> {
+        let (proc_ids, output) = self.build(computer, image)?;
+        let res = computer.exe_procs_pmp_cond(&proc_ids, 0, reserved_step_count, || {
+            stop_cond(&output.borrow())
+        })?;
+        Ok(Self::collect(&proc_ids, &res, output))
+    }
+
+    fn build(
+        &self,
+        computer: &mut SeqIntCodeComputer,
+        image: &[i64],
+    ) -> Result<(Vec<usize>, TopologyOutputRef), Error> {
+        if let Some(&(from, to)) = self
+            .edges
+            .iter()
+            .find(|&&(from, to)| from >= self.node_n || to >= self.node_n)
+        {
+            return Err(Error::InvalidTopologyEdge(from, to));
+        }
+
+        let mut init_inputs = vec![Vec::new(); self.node_n];
+        for (node, values) in &self.init_inputs {
+            init_inputs
+                .get_mut(*node)
+                .ok_or(Error::InvalidTopologyNode(*node, self.node_n))?
+                .extend_from_slice(values);
+        }
+        let inboxes = Rc::new(
+            init_inputs
+                .iter()
+                .map(|init_input| SeqIODevice::new(Channel::new(init_input)))
+                .collect::<Vec<_>>(),
+        );
+        let output = Rc::new(RefCell::new(TopologyOutput {
+            outputs: vec![Vec::new(); self.node_n],
+            undelivered: Vec::new(),
+        }));
+        let proc_ids = (0..self.node_n)
+            .map(|node| {
+                let input_dev = SeqInputDevice::new(NodeInput {
+                    inbox: inboxes[node].clone(),
+                    idle_input: self.idle_input,
+                });
+                let output_dev = SeqOutputDevice::new(NodeOutput {
+                    node,
+                    inboxes: inboxes.clone(),
+                    succs: self
+                        .edges
+                        .iter()
+                        .filter(|&&(from, _)| from == node)
+                        .map(|&(_, to)| to)
+                        .collect(),
+                    routing: self.routing,
+                    packet: Vec::new(),
+                    last_targets: Vec::new(),
+                    output: output.clone(),
+                });
+                computer.new_proc(image, input_dev, output_dev)
+            })
+            .collect::<Vec<_>>();
+
+        Ok((proc_ids, output))
+    }
+
+    fn collect(
+        proc_ids: &[usize],
+        res: &ProcsExecutionResult,
+        output: TopologyOutputRef,
+    ) -> TopologyResult {
+        let snapshots = proc_ids
+            .iter()
+            .map(|&id| res.proc_snapshots(id))
+            .collect::<Vec<_>>();
+        TopologyResult {
+            // Output ports of processes are dropped with processes, so output isn't shared any more.
+            output: Rc::try_unwrap(output)
+                .map(|o| o.into_inner())
+                .unwrap_or_else(|o| std::mem::take(&mut *o.borrow_mut())),
+            states: snapshots.iter().map(|s| s.map(|s| s.state())).collect(),
+            step_counts: snapshots
+                .iter()
+                .map(|s| s.map_or(0, |s| s.step_count()))
+                .collect(),
+        }
+    }
+}
@@ -10,6 +10,7 @@ pub mod day23;
 pub mod day25;
 pub mod day7;
 pub mod int_code;
+pub mod ppm;
 
 #[derive(Debug)]
 pub enum Error {
@@ -30,6 +31,15 @@ pub enum Error {
     RunningUnknownProcess(usize),
     ProcessResultNotFound(usize, usize),
     IOProcessError(String),
+    AsmSyntaxError(usize, String), // (line number, description)
+    UndefinedLabel(usize, String),
+    DuplicateLabel(usize, String),
+    InvalidSnapshot(String),
+    InvalidTopologyEdge(usize, usize),
+    InvalidTopologyNode(usize, usize), // (node, count of nodes)
+    InvalidTranscript(String),
+    ReplayDivergence(String),
+    MemoryLimitExceeded(usize, usize), // (address, cap)
 }
 
 impl Display for Error {
@@ -81,6 +91,35 @@ impl Display for Error {
                 pid, cid
             ),
             Error::IOProcessError(s) => write!(f, "Found error in I/O processing({})", s),
+            Error::AsmSyntaxError(l, s) => {
+                write!(f, "Syntax error in assembly source at line {}: {}", l, s)
+            }
+            Error::UndefinedLabel(l, s) => {
+                write!(f, "Undefined label({}) used at line {}", s, l)
+            }
+            Error::DuplicateLabel(l, s) => {
+                write!(f, "Label({}) defined again at line {}", s, l)
+            }
+            Error::InvalidSnapshot(s) => write!(f, "Invalid process snapshot({})", s),
+            Error::InvalidTopologyEdge(from, to) => {
+                write!(
+                    f,
+                    "Invalid edge from node({}) to node({}) in topology",
+                    from, to
+                )
+            }
+            Error::InvalidTopologyNode(node, node_n) => write!(
+                f,
+                "Invalid node({}) in topology of {} node(s)",
+                node, node_n
+            ),
+            Error::InvalidTranscript(s) => write!(f, "Invalid I/O transcript({})", s),
+            Error::ReplayDivergence(s) => write!(f, "Replay diverged from transcript({})", s),
+            Error::MemoryLimitExceeded(addr, cap) => write!(
+                f,
+                "Access memory at address({}) beyond cap({}) of memory",
+                addr, cap
+            ),
         }
     }
 }
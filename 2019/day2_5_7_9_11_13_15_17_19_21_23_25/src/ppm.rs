@@ -0,0 +1,12 @@
+// Binary PPM image of given size, colors of pixels are given row by row from the top left one.
+pub fn encode<I: IntoIterator<Item = [u8; 3]>>(width: usize, height: usize, pixels: I) -> Vec<u8> {
+    let mut data = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    data.extend(pixels.into_iter().flatten());
+    data
+}
+
+#[test]
+fn test_encode() {
+    let data = encode(2, 1, [[1, 2, 3], [4, 5, 6]]);
+    assert_eq!(data, b"P6\n2 1\n255\n\x01\x02\x03\x04\x05\x06");
+}